@@ -1,9 +1,10 @@
 use anyhow::Result;
 use clap::{Parser, Subcommand};
 
+mod logger;
 mod scripts;
 
-use scripts::split;
+use scripts::{create_config, options, split, validate};
 
 /// A binary splitting tool to assist with decompilation and modding projects
 
@@ -13,7 +14,21 @@ enum Commands {
         #[clap(flatten)]
         args: split::SplitArgs,
     },
-    CreateConfig,
+    /// Generate a starter config from an N64 ROM
+    CreateConfig {
+        #[clap(flatten)]
+        args: create_config::CreateConfigArgs,
+    },
+    /// Print the fully resolved options of a config, along with where each value came from
+    Options {
+        #[clap(flatten)]
+        args: options::OptionsArgs,
+    },
+    /// Check a config for mistakes, pointing at the offending lines
+    Validate {
+        #[clap(flatten)]
+        args: validate::ValidateArgs,
+    },
     Capy,
 }
 
@@ -27,15 +42,16 @@ struct Args {
 
 fn main() -> Result<()> {
     let args = Args::parse();
+    logger::init();
 
     match args.command {
         Commands::Split { args } => {
             println!("Splitting");
             args.do_stuff()?;
         }
-        Commands::CreateConfig => {
-            println!("Creating config");
-        }
+        Commands::CreateConfig { args } => args.do_stuff()?,
+        Commands::Options { args } => args.do_stuff()?,
+        Commands::Validate { args } => args.do_stuff()?,
         Commands::Capy => capybara(),
     }
 
@@ -0,0 +1,36 @@
+use anyhow::{Context, Result, bail};
+use clap::Args;
+use splat_core::create_config;
+use std::path::PathBuf;
+
+#[derive(Debug, Clone, Args)]
+pub struct CreateConfigArgs {
+    /// The N64 ROM to generate the config for
+    #[arg(required = true)]
+    rom: PathBuf,
+}
+
+impl CreateConfigArgs {
+    pub fn do_stuff(&self) -> Result<()> {
+        let (info, basename, yaml) = create_config::create_n64_config(&self.rom)?;
+
+        println!("Title: {}", info.title);
+        println!("Country: {}", info.header.country());
+        println!("CIC: {}", info.cic_name().unwrap_or("unknown"));
+        println!("Entrypoint: 0x{:08X}", info.entrypoint);
+        println!("SHA-1: {}", info.sha1);
+
+        let config_path = PathBuf::from(format!("{basename}.yaml"));
+        if config_path.exists() {
+            bail!(
+                "{} already exists, remove it first to generate a new one",
+                config_path.display()
+            );
+        }
+        std::fs::write(&config_path, yaml)
+            .with_context(|| format!("Failed to write {}", config_path.display()))?;
+
+        println!("Wrote {}", config_path.display());
+        Ok(())
+    }
+}
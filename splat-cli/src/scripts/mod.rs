@@ -1 +1,4 @@
+pub mod create_config;
+pub mod options;
 pub mod split;
+pub mod validate;
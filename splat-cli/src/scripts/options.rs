@@ -0,0 +1,20 @@
+use anyhow::Result;
+use clap::Args;
+use splat_core::yaml;
+use std::path::PathBuf;
+
+#[derive(Debug, Clone, Args)]
+pub struct OptionsArgs {
+    #[arg(required = true)]
+    config: PathBuf,
+}
+
+impl OptionsArgs {
+    pub fn do_stuff(&self) -> Result<()> {
+        let splat_yaml = yaml::load_yaml(&self.config)?;
+
+        print!("{}", splat_yaml.options.describe()?);
+
+        Ok(())
+    }
+}
@@ -1,6 +1,6 @@
 use anyhow::Result;
 use clap::Args;
-use splat_core::yaml;
+use splat_core::split;
 use std::path::PathBuf;
 
 #[derive(Debug, Clone, Args)]
@@ -9,8 +9,13 @@ pub struct SplitArgs {
     //pub config: Vec<PathBuf>,
     config: PathBuf,
 
-    #[arg(long, default_values_t = ["all".to_string()])]
+    /// Only write the outputs of these categories: code, data, img, gfx, vtx, bin, ld or all
+    #[arg(long, num_args = 1.., default_values_t = ["all".to_string()])]
     modes: Vec<String>,
+
+    /// Regenerate the hand-written `hasm` files, overwriting any edits made to them
+    #[arg(long)]
+    force_hasm: bool,
     // #[arg(long)]
     // verbose: bool,
     // #[arg(long)]
@@ -24,8 +29,6 @@ pub struct SplitArgs {
 impl SplitArgs {
     // TODO: rename
     pub fn do_stuff(&self) -> Result<()> {
-        let _splat_yaml = yaml::load_yaml(&self.config)?;
-
-        Ok(())
+        split::split(&self.config, self.modes.clone(), self.force_hasm)
     }
 }
@@ -0,0 +1,30 @@
+use anyhow::{Result, bail};
+use clap::Args;
+use splat_core::validate;
+use std::path::PathBuf;
+
+#[derive(Debug, Clone, Args)]
+pub struct ValidateArgs {
+    #[arg(required = true)]
+    config: PathBuf,
+}
+
+impl ValidateArgs {
+    pub fn do_stuff(&self) -> Result<()> {
+        let diagnostics = validate::validate_config(&self.config)?;
+
+        for diagnostic in &diagnostics {
+            eprintln!("{diagnostic}");
+        }
+        if !diagnostics.is_empty() {
+            bail!(
+                "{} problem(s) found in {}",
+                diagnostics.len(),
+                self.config.display()
+            );
+        }
+
+        println!("{} is valid", self.config.display());
+        Ok(())
+    }
+}
@@ -0,0 +1,145 @@
+use std::fmt;
+
+use serde::{Serialize, Serializer};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[non_exhaustive]
+pub enum Compiler {
+    Gcc,
+    Sn64,
+    Ido,
+    Kmc,
+    Eegcc,
+    Psyq,
+    Mwccps2,
+    Mwccpsp,
+}
+
+impl Compiler {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_uppercase().as_str() {
+            "GCC" => Some(Self::Gcc),
+            "SN64" => Some(Self::Sn64),
+            "IDO" => Some(Self::Ido),
+            "KMC" => Some(Self::Kmc),
+            "EEGCC" => Some(Self::Eegcc),
+            "PSYQ" => Some(Self::Psyq),
+            "MWCCPS2" => Some(Self::Mwccps2),
+            "MWCCPSP" => Some(Self::Mwccpsp),
+            _ => None,
+        }
+    }
+
+    pub const fn name(&self) -> &'static str {
+        match self {
+            Self::Gcc => "GCC",
+            Self::Sn64 => "SN64",
+            Self::Ido => "IDO",
+            Self::Kmc => "KMC",
+            Self::Eegcc => "EEGCC",
+            Self::Psyq => "PSYQ",
+            Self::Mwccps2 => "MWCCPS2",
+            Self::Mwccpsp => "MWCCPSP",
+        }
+    }
+
+    pub const fn asm_function_macro(&self) -> &'static str {
+        match self {
+            Self::Sn64 => ".globl",
+            _ => "glabel",
+        }
+    }
+
+    pub const fn asm_function_alt_macro(&self) -> &'static str {
+        match self {
+            Self::Sn64 => ".globl",
+            _ => "alabel",
+        }
+    }
+
+    pub const fn asm_jtbl_label_macro(&self) -> &'static str {
+        match self {
+            Self::Sn64 => ".globl",
+            _ => "jlabel",
+        }
+    }
+
+    pub const fn asm_data_macro(&self) -> &'static str {
+        match self {
+            Self::Sn64 => ".globl",
+            _ => "dlabel",
+        }
+    }
+
+    pub const fn asm_end_label(&self) -> &'static str {
+        match self {
+            Self::Sn64 => ".end",
+            _ => "endlabel",
+        }
+    }
+
+    pub const fn asm_data_end_label(&self) -> &'static str {
+        match self {
+            Self::Sn64 => "",
+            _ => "enddlabel",
+        }
+    }
+
+    pub const fn asm_ehtable_label_macro(&self) -> &'static str {
+        "ehlabel"
+    }
+
+    pub const fn asm_nonmatching_label_macro(&self) -> &'static str {
+        "nonmatching"
+    }
+
+    pub const fn asm_inc_header(&self) -> &'static str {
+        match self {
+            Self::Ido | Self::Mwccps2 | Self::Mwccpsp => "",
+            Self::Sn64 => {
+                ".set noat      # allow manual use of $at\n.set noreorder # don't insert nops after branches\n\n"
+            }
+            _ => {
+                ".set noat      /* allow manual use of $at */\n.set noreorder /* don't insert nops after branches */\n\n"
+            }
+        }
+    }
+
+    pub const fn asm_emit_size_directive(&self) -> Option<bool> {
+        match self {
+            Self::Sn64 | Self::Ido => Some(false),
+            _ => None,
+        }
+    }
+
+    pub const fn c_newline(&self) -> &'static str {
+        match self {
+            Self::Sn64 => "\r\n",
+            _ => "\n",
+        }
+    }
+
+    /// Whether non-matching functions are pulled into C files with `INCLUDE_ASM`, rather than `#pragma GLOBAL_ASM`
+    pub const fn uses_include_asm(&self) -> bool {
+        !matches!(self, Self::Ido | Self::Mwccps2 | Self::Mwccpsp)
+    }
+
+    pub const fn align_on_branch_labels(&self) -> bool {
+        false
+    }
+}
+
+impl fmt::Display for Compiler {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl Serialize for Compiler {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(self.name())
+    }
+}
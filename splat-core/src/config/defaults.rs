@@ -0,0 +1,148 @@
+use std::fmt;
+
+use super::{
+    compiler::Compiler,
+    options::{Endianness, Platform},
+};
+
+/// Explains where the effective value of an option came from
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum OptionSource {
+    /// Explicitly set in the yaml
+    User,
+    /// Default for the selected `platform`
+    Platform(Platform),
+    /// Default for the selected `compiler`
+    Compiler(Compiler),
+    /// Computed from the value of another option
+    Derived(&'static str),
+    /// Same default regardless of platform and compiler
+    Default,
+}
+
+impl fmt::Display for OptionSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::User => write!(f, "set in yaml"),
+            Self::Platform(platform) => write!(f, "default for platform {platform}"),
+            Self::Compiler(compiler) => write!(f, "default for compiler {compiler}"),
+            Self::Derived(option) => write!(f, "derived from `{option}`"),
+            Self::Default => write!(f, "default"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct DefaultValue<T> {
+    pub(crate) value: T,
+    pub(crate) source: OptionSource,
+}
+
+impl<T> DefaultValue<T> {
+    fn platform(value: T, platform: &Platform) -> Self {
+        Self {
+            value,
+            source: OptionSource::Platform(platform.clone()),
+        }
+    }
+
+    fn compiler(value: T, compiler: Compiler) -> Self {
+        Self {
+            value,
+            source: OptionSource::Compiler(compiler),
+        }
+    }
+
+    fn default(value: T) -> Self {
+        Self {
+            value,
+            source: OptionSource::Default,
+        }
+    }
+}
+
+/// The defaults of every option that depends on the `platform` and `compiler`,
+/// computed before applying the user's overrides.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct OptionDefaults {
+    pub(crate) endianness: DefaultValue<Endianness>,
+    pub(crate) section_order: DefaultValue<Vec<String>>,
+    pub(crate) ld_bss_is_noload: DefaultValue<bool>,
+    pub(crate) string_encoding: DefaultValue<Option<String>>,
+    pub(crate) data_string_encoding: DefaultValue<Option<String>>,
+
+    pub(crate) c_newline: DefaultValue<String>,
+    pub(crate) asm_inc_header: DefaultValue<String>,
+    pub(crate) asm_function_macro: DefaultValue<String>,
+    pub(crate) asm_function_alt_macro: DefaultValue<String>,
+    pub(crate) asm_jtbl_label_macro: DefaultValue<String>,
+    pub(crate) asm_data_macro: DefaultValue<String>,
+    pub(crate) asm_end_label: DefaultValue<String>,
+    pub(crate) asm_data_end_label: DefaultValue<String>,
+    pub(crate) asm_ehtable_label_macro: DefaultValue<String>,
+    pub(crate) asm_nonmatching_label_macro: DefaultValue<String>,
+    pub(crate) asm_emit_size_directive: DefaultValue<Option<bool>>,
+    pub(crate) align_on_branch_labels: DefaultValue<bool>,
+}
+
+impl OptionDefaults {
+    pub(crate) fn new(platform: &Platform, compiler: Compiler) -> Self {
+        let endianness = match platform {
+            Platform::N64 => DefaultValue::platform(Endianness::Big, platform),
+            Platform::Psx | Platform::Ps2 | Platform::Psp => {
+                DefaultValue::platform(Endianness::Little, platform)
+            }
+            Platform::Unsupported(_) => DefaultValue::default(Endianness::Big),
+        };
+
+        let section_order = DefaultValue::default(strings(&[".text", ".data", ".rodata", ".bss"]));
+
+        let ld_bss_is_noload = match platform {
+            Platform::Psx => DefaultValue::platform(false, platform),
+            _ => DefaultValue::default(true),
+        };
+
+        let string_encoding = match platform {
+            Platform::N64 => Some("EUC-JP"),
+            Platform::Psx | Platform::Ps2 | Platform::Psp => Some("ASCII"),
+            Platform::Unsupported(_) => None,
+        };
+        let string_encoding = match string_encoding {
+            Some(encoding) => DefaultValue::platform(Some(encoding.to_string()), platform),
+            None => DefaultValue::default(None),
+        };
+
+        let from_compiler = |value: &str| DefaultValue::compiler(value.to_string(), compiler);
+
+        Self {
+            endianness,
+            section_order,
+            ld_bss_is_noload,
+            data_string_encoding: string_encoding.clone(),
+            string_encoding,
+
+            c_newline: from_compiler(compiler.c_newline()),
+            asm_inc_header: from_compiler(compiler.asm_inc_header()),
+            asm_function_macro: from_compiler(compiler.asm_function_macro()),
+            asm_function_alt_macro: from_compiler(compiler.asm_function_alt_macro()),
+            asm_jtbl_label_macro: from_compiler(compiler.asm_jtbl_label_macro()),
+            asm_data_macro: from_compiler(compiler.asm_data_macro()),
+            asm_end_label: from_compiler(compiler.asm_end_label()),
+            asm_data_end_label: from_compiler(compiler.asm_data_end_label()),
+            asm_ehtable_label_macro: from_compiler(compiler.asm_ehtable_label_macro()),
+            asm_nonmatching_label_macro: from_compiler(compiler.asm_nonmatching_label_macro()),
+            asm_emit_size_directive: DefaultValue::compiler(
+                compiler.asm_emit_size_directive(),
+                compiler,
+            ),
+            align_on_branch_labels: DefaultValue::compiler(
+                compiler.align_on_branch_labels(),
+                compiler,
+            ),
+        }
+    }
+}
+
+fn strings(values: &[&str]) -> Vec<String> {
+    values.iter().map(|s| s.to_string()).collect()
+}
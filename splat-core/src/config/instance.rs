@@ -1,18 +1,148 @@
-use spimdisasm::{context::Context as SpimdisasmContext, relocation::UserRelocs};
+use address_space::{AddressRange, Rom, RomVramRange, Size, Vram};
+use anyhow::{Context, Result, bail};
+use spimdisasm::{
+    config::{Endian, GlobalConfig},
+    context::{Context as SpimdisasmContext, ContextBuilder},
+    metadata::{SymbolMetadata, SymbolType},
+    relocation::{RelocReferencedSym, UserRelocs},
+    segments::{GlobalSegmentBuilder, OverlayCategoryName, OverlaySegmentBuilder},
+};
 
-use super::options::SplatOpts;
+use splat_segment_api::segment_trait::{SegmentGroup, SegmentTrait};
+
+use crate::{
+    relocs::Reloc,
+    segments::{ResolvedSegment, SegmentTypeImpl, SegmentTypeRegistry},
+    symbols::Symbol,
+};
+
+use super::options::{Endianness, SplatOpts};
 
 #[derive(Debug, Clone, PartialEq)]
 #[non_exhaustive]
 pub struct SplatInstance {
     pub(crate) options: SplatOpts,
     pub(crate) yaml_segments: (),
-    pub(crate) symbols: (),
+    pub(crate) symbols: Vec<Symbol>,
     pub(crate) spimdisasm_context: SpimdisasmContext,
     pub(crate) user_relocs: UserRelocs,
 }
 
 impl SplatInstance {
+    /// Sets up the disassembly context, with every top level segment that has a `vram_class` registered as
+    /// an overlay of that category and the rest as part of the global segment.
+    ///
+    /// Each symbol is registered in the segment named by its `segment` attribute, else the one containing its
+    /// `rom`, else the global segment when its VRAM falls inside of it. The global segment covers every global
+    /// segment, widened by `global_vram_start` and `global_vram_end` for symbols outside the ROM like hardware
+    /// registers. Symbols outside of all of them are only kept in `symbols` and reported with a warning.
+    ///
+    /// Relocations must point inside of a segment whose type outputs a `.text` section.
+    pub fn new(
+        options: SplatOpts,
+        registry: &SegmentTypeRegistry,
+        segments: &[ResolvedSegment],
+        symbols: Vec<Symbol>,
+        relocs: &[Reloc],
+    ) -> Result<Self> {
+        let global_config = GlobalConfig::new(match options.endianness {
+            Endianness::Big => Endian::Big,
+            Endianness::Little => Endian::Little,
+        });
+
+        let global_ranges = global_ranges(&options, segments)?;
+        let mut global_builder = GlobalSegmentBuilder::new(global_ranges);
+
+        let mut overlay_builders = Vec::new();
+        for segment in segments {
+            let Some(category) = segment.overlay_category_name() else {
+                continue;
+            };
+            let ranges = merged_ranges(&[segment])
+                .with_context(|| format!("Overlay `{}` has no VRAM", segment.name()))?;
+
+            let overlay_builder = OverlaySegmentBuilder::new(
+                ranges,
+                OverlayCategoryName::new(category),
+                segment.name(),
+            );
+            overlay_builders.push((segment, overlay_builder));
+        }
+
+        for symbol in &symbols {
+            let placement =
+                symbol_placement(symbol, segments, global_ranges.vram()).with_context(|| {
+                    format!(
+                        "{}:{}: Failed to place symbol `{}`",
+                        symbol.path.display(),
+                        symbol.line,
+                        symbol.name
+                    )
+                })?;
+
+            let name = symbol.name.clone();
+            let vram = Vram::new(symbol.vram);
+            let rom = symbol.rom.map(Rom::new);
+            let sym_type = symbol.sym_type.as_deref().and_then(symbol_type);
+            let metadata = match placement {
+                SymbolPlacement::Overlay(owner) => {
+                    let Some((_, builder)) = overlay_builders
+                        .iter_mut()
+                        .find(|(segment, _)| segment.name() == owner.name())
+                    else {
+                        bail!("Overlay `{}` is missing from the context", owner.name());
+                    };
+                    builder.add_user_symbol(name, vram, rom, sym_type)
+                }
+                SymbolPlacement::Global => global_builder.add_user_symbol(name, vram, rom, sym_type),
+                SymbolPlacement::Outside => {
+                    log::warn!(
+                        "{}:{}: Symbol `{}` at 0x{:08X} is outside of every segment, it will not be used by the disassembly (hint: widen the global segment with `global_vram_start` and `global_vram_end`)",
+                        symbol.path.display(),
+                        symbol.line,
+                        symbol.name,
+                        symbol.vram
+                    );
+                    continue;
+                }
+            }
+            .with_context(|| {
+                format!(
+                    "{}:{}: Failed to add symbol `{}`",
+                    symbol.path.display(),
+                    symbol.line,
+                    symbol.name
+                )
+            })?;
+            apply_attributes(metadata, symbol);
+        }
+
+        let mut user_relocs = UserRelocs::default();
+        for reloc in relocs {
+            reloc_segment(registry, segments, reloc)?;
+
+            let referenced =
+                RelocReferencedSym::SymName(reloc.symbol.as_str().into(), reloc.addend);
+            user_relocs.insert(
+                Rom::new(reloc.rom),
+                reloc.reloc_type.new_reloc_info(referenced),
+            );
+        }
+
+        let mut context_builder = ContextBuilder::new(global_builder.finish_symbols());
+        for (_, overlay_builder) in overlay_builders {
+            context_builder.add_overlay(overlay_builder.finish_symbols());
+        }
+
+        Ok(Self {
+            options,
+            yaml_segments: (),
+            symbols,
+            spimdisasm_context: context_builder.build(global_config),
+            user_relocs,
+        })
+    }
+
     pub fn options(&self) -> &SplatOpts {
         &self.options
     }
@@ -22,4 +152,327 @@ impl SplatInstance {
     pub fn user_relocs(&self) -> &UserRelocs {
         &self.user_relocs
     }
+    pub fn symbols(&self) -> &[Symbol] {
+        &self.symbols
+    }
+}
+
+/// The ROM and VRAM covered by the global segment of the disassembly context
+fn global_ranges(options: &SplatOpts, segments: &[ResolvedSegment]) -> Result<RomVramRange> {
+    let global_segments: Vec<_> = segments
+        .iter()
+        .filter(|s| s.vram_start().is_some() && s.overlay_category_name().is_none())
+        .collect();
+    let merged = merged_ranges(&global_segments);
+
+    let option_vram = |value: Option<u64>, name: &str| {
+        value
+            .map(|vram| {
+                u32::try_from(vram)
+                    .map(Vram::new)
+                    .with_context(|| format!("{name} 0x{vram:X} doesn't fit in 32 bits"))
+            })
+            .transpose()
+    };
+    let global_vram_start = option_vram(options.global_vram_start, "global_vram_start")?;
+    let global_vram_end = option_vram(options.global_vram_end, "global_vram_end")?;
+
+    let (rom, vram) = match merged {
+        Some(ranges) => (*ranges.rom(), *ranges.vram()),
+        None => {
+            // Without global segments, the options alone make up the global VRAM
+            let start = global_vram_start
+                .or(global_vram_end)
+                .unwrap_or(Vram::new(0));
+            let end = global_vram_end.unwrap_or(start);
+            (
+                AddressRange::new(Rom::new(0), Rom::new(0)),
+                AddressRange::new(start, start.max(end)),
+            )
+        }
+    };
+    let vram_start = global_vram_start.map_or(vram.start(), |start| start.min(vram.start()));
+    let vram_end = global_vram_end.map_or(vram.end(), |end| end.max(vram.end()));
+
+    Ok(RomVramRange::new(
+        rom,
+        AddressRange::new(vram_start, vram_end),
+    ))
+}
+
+/// Which segment of the disassembly context a symbol is registered in
+#[derive(Debug)]
+enum SymbolPlacement<'s, 'a> {
+    Overlay(&'s ResolvedSegment<'a>),
+    Global,
+    /// Outside of every segment, so unknown to the disassembly
+    Outside,
+}
+
+fn symbol_placement<'s, 'a>(
+    symbol: &Symbol,
+    segments: &'s [ResolvedSegment<'a>],
+    global_vram: &AddressRange<Vram>,
+) -> Result<SymbolPlacement<'s, 'a>> {
+    let owner = symbol_owner(symbol, segments)?;
+
+    Ok(match owner {
+        Some(owner) if owner.overlay_category_name().is_some() => SymbolPlacement::Overlay(owner),
+        _ if global_vram.in_range(Vram::new(symbol.vram)) => SymbolPlacement::Global,
+        _ => SymbolPlacement::Outside,
+    })
+}
+
+/// The top level segment a symbol belongs to, if it was told or can be found from its ROM address
+fn symbol_owner<'s, 'a>(
+    symbol: &Symbol,
+    segments: &'s [ResolvedSegment<'a>],
+) -> Result<Option<&'s ResolvedSegment<'a>>> {
+    if let Some(name) = &symbol.segment {
+        let Some(segment) = segments.iter().find(|s| &*s.name() == name) else {
+            bail!("There is no top level segment named `{name}`");
+        };
+        return Ok(Some(segment));
+    }
+
+    Ok(symbol.rom.and_then(|rom| {
+        segments
+            .iter()
+            .find(|s| s.rom().is_some_and(|range| range.in_range(Rom::new(rom))))
+    }))
+}
+
+/// The leaf segment a relocation applies to, which must output a `.text` section
+fn reloc_segment<'s, 'a>(
+    registry: &SegmentTypeRegistry,
+    segments: &'s [ResolvedSegment<'a>],
+    reloc: &Reloc,
+) -> Result<&'s ResolvedSegment<'a>> {
+    let segment = leaf_segment_at(segments, Rom::new(reloc.rom));
+    let segment_type = segment.map(|segment| registry.lookup(&segment.segment_type()));
+    let is_code = matches!(
+        segment_type,
+        Some(Ok(SegmentTypeImpl::Native(segment_type))) if segment_type.section_order.contains(&".text")
+    );
+
+    match segment {
+        Some(segment) if is_code => Ok(segment),
+        _ => bail!(
+            "{}:{}: rom:0x{:X} of the relocation is not inside of a code segment",
+            reloc.path.display(),
+            reloc.line,
+            reloc.rom
+        ),
+    }
+}
+
+/// The innermost segment containing `rom`
+fn leaf_segment_at<'s, 'a>(
+    segments: &'s [ResolvedSegment<'a>],
+    rom: Rom,
+) -> Option<&'s ResolvedSegment<'a>> {
+    let segment = segments
+        .iter()
+        .find(|s| s.rom().is_some_and(|range| range.in_range(rom)))?;
+
+    match segment.subsegments().is_empty() {
+        true => Some(segment),
+        false => leaf_segment_at(segment.subsegments(), rom),
+    }
+}
+
+/// Maps the `type` attribute of a symbol to the spimdisasm type, custom types are left for spimdisasm to guess
+fn symbol_type(sym_type: &str) -> Option<SymbolType> {
+    Some(match sym_type {
+        "func" => SymbolType::Function,
+        "label" => SymbolType::BranchLabel,
+        "jtbl" => SymbolType::Jumptable,
+        "jtbl_label" => SymbolType::JumptableLabel,
+        "ehtbl" => SymbolType::GccExceptTable,
+        "ehtbl_label" => SymbolType::GccExceptTableLabel,
+        "s8" | "u8" | "char" => SymbolType::Byte,
+        "s16" | "u16" => SymbolType::Short,
+        "s32" | "u32" => SymbolType::Word,
+        "s64" | "u64" => SymbolType::DWord,
+        "f32" | "float" => SymbolType::Float32,
+        "f64" | "double" => SymbolType::Float64,
+        "asciz" | "char*" => SymbolType::CString,
+        _ => return None,
+    })
+}
+
+fn apply_attributes(metadata: &mut SymbolMetadata, symbol: &Symbol) {
+    if let Some(size) = symbol.size {
+        metadata.set_user_declared_size(Size::new(size));
+    }
+    if let Some(name_end) = &symbol.name_end {
+        metadata.set_user_declared_name_end(name_end.clone());
+    }
+    if symbol.defined {
+        metadata.set_defined();
+    }
+    if let Some(allow_addend) = symbol.allow_addend {
+        metadata.set_allow_ref_with_addend(allow_addend);
+    }
+}
+
+/// The smallest ROM and VRAM ranges covering all the given segments
+fn merged_ranges(segments: &[&ResolvedSegment]) -> Option<RomVramRange> {
+    let mut rom: Option<(Rom, Rom)> = None;
+    let mut vram: Option<(Vram, Vram)> = None;
+
+    for segment in segments {
+        let (Some(segment_rom), Some(vram_start), Some(vram_end)) =
+            (segment.rom(), segment.vram_start(), segment.vram_end())
+        else {
+            continue;
+        };
+
+        rom = Some(match rom {
+            Some((start, end)) => (start.min(segment_rom.start()), end.max(segment_rom.end())),
+            None => (segment_rom.start(), segment_rom.end()),
+        });
+        vram = Some(match vram {
+            Some((start, end)) => (start.min(vram_start), end.max(vram_end)),
+            None => (vram_start, vram_end),
+        });
+    }
+
+    let (rom_start, rom_end) = rom?;
+    let (vram_start, vram_end) = vram?;
+    Some(RomVramRange::new(
+        AddressRange::new(rom_start, rom_end),
+        AddressRange::new(vram_start, vram_end),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::*;
+    use crate::{
+        relocs::parse_reloc_addrs,
+        segments::resolve_segments,
+        symbols::parse_symbol_addrs,
+        test_utils::{options, splat_yaml},
+    };
+
+    const YAML: &str = "
+vram_classes:
+  - { name: overlays, vram: 0x80100000 }
+segments:
+  - name: main
+    type: code
+    start: 0x1000
+    vram: 0x80000400
+    subsegments:
+      - [0x1000, asm, main]
+  - name: battle
+    type: code
+    start: 0x2000
+    vram_class: overlays
+    subsegments:
+      - [0x2000, asm, battle]
+      - [0x2800, data, battle]
+  - [0x3000]
+";
+
+    #[test]
+    fn test_symbol_placement() {
+        let yaml = splat_yaml(YAML);
+        let segments = resolve_segments(&yaml).unwrap();
+        let symbols = parse_symbol_addrs(
+            Path::new("symbol_addrs.txt"),
+            "
+func_80000400 = 0x80000400; // rom:0x1000
+func_80100010 = 0x80100010; // rom:0x2010
+D_80100800 = 0x80100800; // segment:battle
+D_80100900 = 0x80100900;
+SP_STATUS = 0xA4040010;
+osTvType = 0x80000300;
+",
+        )
+        .unwrap();
+
+        let placements = |options: &SplatOpts| {
+            let global = global_ranges(options, &segments).unwrap();
+            symbols
+                .iter()
+                .map(
+                    |symbol| match symbol_placement(symbol, &segments, global.vram()).unwrap() {
+                        SymbolPlacement::Overlay(owner) => owner.name().to_string(),
+                        SymbolPlacement::Global => "global".to_string(),
+                        SymbolPlacement::Outside => "outside".to_string(),
+                    },
+                )
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            placements(&options("platform: n64")),
+            [
+                "global", "battle", "battle", "outside", "outside", "outside"
+            ]
+        );
+        assert_eq!(
+            placements(&options(
+                "platform: n64\nglobal_vram_start: 0x80000000\nglobal_vram_end: 0xA4800000"
+            )),
+            ["global", "battle", "battle", "global", "global", "global"]
+        );
+
+        let err = global_ranges(
+            &options("platform: n64\nglobal_vram_end: 0x100000000"),
+            &segments,
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("global_vram_end"), "{err}");
+    }
+
+    #[test]
+    fn test_reloc_segments() {
+        let yaml = splat_yaml(YAML);
+        let segments = resolve_segments(&yaml).unwrap();
+        let registry = SegmentTypeRegistry::new(&yaml.options);
+        let relocs = parse_reloc_addrs(
+            Path::new("reloc_addrs.txt"),
+            "
+rom:0x1010 reloc:MIPS_HI16 symbol:D_80100000
+rom:0x2010 reloc:MIPS_LO16 symbol:D_80100800 addend:0x4
+rom:0x2810 reloc:MIPS_32 symbol:func_80100010
+rom:0x3010 reloc:MIPS_32 symbol:func_80100010
+",
+        )
+        .unwrap();
+
+        let segment = |reloc| reloc_segment(&registry, &segments, reloc);
+        assert_eq!(&*segment(&relocs[0]).unwrap().name(), "main");
+        assert_eq!(&*segment(&relocs[1]).unwrap().name(), "battle");
+        for reloc in &relocs[2..] {
+            let err = segment(reloc).unwrap_err().to_string();
+            assert!(err.contains("is not inside of a code segment"), "{err}");
+        }
+
+        let err = SplatInstance::new(
+            options("platform: n64"),
+            &registry,
+            &segments,
+            Vec::new(),
+            &relocs[..3],
+        )
+        .unwrap_err();
+        assert!(
+            err.to_string().starts_with("reloc_addrs.txt:4: rom:0x2810"),
+            "{err}"
+        );
+        SplatInstance::new(
+            options("platform: n64"),
+            &registry,
+            &segments,
+            Vec::new(),
+            &relocs[..2],
+        )
+        .unwrap();
+    }
 }
@@ -1,2 +1,4 @@
+pub mod compiler;
+pub mod defaults;
 pub mod instance;
 pub mod options;
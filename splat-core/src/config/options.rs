@@ -1,297 +1,954 @@
-use std::path::PathBuf;
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt::{self, Write},
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result, bail};
+use serde::{Deserialize, Deserializer, Serialize, Serializer, de::DeserializeOwned};
+use serde_yaml::Value;
+
+use super::{
+    compiler::Compiler,
+    defaults::{DefaultValue, OptionDefaults, OptionSource},
+};
 
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 #[non_exhaustive]
+pub enum Platform {
+    N64,
+    Psx,
+    Ps2,
+    Psp,
+    /// Any other platform, only accepted when `is_unsupported_platform` is set
+    Unsupported(String),
+}
+
+impl Platform {
+    pub fn name(&self) -> &str {
+        match self {
+            Self::N64 => "n64",
+            Self::Psx => "psx",
+            Self::Ps2 => "ps2",
+            Self::Psp => "psp",
+            Self::Unsupported(name) => name,
+        }
+    }
+}
+
+impl fmt::Display for Platform {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl Serialize for Platform {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(self.name())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Endianness {
+    Big,
+    Little,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IncludeAsmMacroStyle {
+    Default,
+    MaspsxHack,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SegmentSymbolsStyle {
+    Splat,
+    Makerom,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MipsAbi {
+    Numeric,
+    O32,
+    N32,
+    N64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GfxUcode {
+    F3d,
+    F3db,
+    F3dex,
+    F3dexb,
+    F3dex2,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[non_exhaustive]
 pub struct SplatOpts {
-    /*
-    # Debug / logging
-    verbose: bool
-    dump_symbols: bool
-    dump_symbols_references: bool
-    modes: List[str]
-
-    # Project configuration
-
-    # Determines the base path of the project. Everything is relative to this path
-    base_path: Path
-    # Determines the path to the target binary
-    target_path: Path
-    # Path to the final elf target
-    elf_path: Optional[Path]
-    # Determines the platform of the target binary
-    platform: str
-    # Determines the compiler used to compile the target binary
-    compiler: Compiler
-    # Determines the endianness of the target binary
-    endianness: Literal["big", "little"]
-    # Determines the default section order of the target binary
-    # this can be overridden per-segment
-    section_order: List[str]
-    # Determines the code that is inserted by default in generated .c files
-    generated_c_preamble: str
-    # Determines the code that is inserted by default in generated .s files
-    generated_s_preamble: str
-    # Determines any extra content to be added in the generated macro.inc file
-    generated_macro_inc_content: Optional[str]
-    # Determines if files related to assembly macros should be regenerated by splat
-    generate_asm_macros_files: bool
-    # Changes the definition of the generated `INCLUDE_ASM`.
-    # default: The default one.
-    # maspsx_hack: Use the maspsx hack workaround definition https://github.com/mkst/maspsx?tab=readme-ov-file#include_asm-reordering-workaround-hack
-    include_asm_macro_style: Literal["default", "maspsx_hack"]
-    # Directory to place the generated asm macros files.
-    generated_asm_macros_directory: Path
-    # Determines whether to use .o as the suffix for all binary files?... TODO document
-    use_o_as_suffix: bool
-    # the value of the $gp register to correctly calculate offset to %gp_rel relocs
-    gp: Optional[int]
-    # Checks and errors if there are any non consecutive segment types
-    check_consecutive_segment_types: bool
-    # Disable checks on `platform` option.
-    is_unsupported_platform: bool
-    # Allows to take precedence over the splat builtin platform segments via splat extension.
-    allow_segment_overrides: bool
-
-    # Paths
-    asset_path: Path
-    # Determines the path to the symbol addresses file(s)
-    # A symbol_addrs file is to be updated/curated manually and contains addresses of symbols
-    # as well as optional metadata such as rom address, type, and more
-    #
-    # It's possible to use more than one file by supplying a list instead of a string
-    symbol_addrs_paths: List[Path]
-    reloc_addrs_paths: List[Path]
-    # Determines the path to the project build directory
-    build_path: Path
-    # Determines the path to the source code directory
-    src_path: Path
-    */
+    // Debug / logging
+    #[serde(skip)]
+    pub(crate) verbose: bool,
+    pub(crate) dump_symbols: bool,
+    pub(crate) dump_symbols_references: bool,
+    #[serde(skip)]
+    pub(crate) modes: Vec<String>,
+    /// Regenerate `hasm` files even though they already exist
+    #[serde(skip)]
+    pub(crate) force_hasm: bool,
+
+    // Project configuration
+    /// Determines the base name of the project, used as the default name of the linker script
+    pub(crate) basename: String,
+    /// Determines the base path of the project. Everything is relative to this path
+    pub(crate) base_path: PathBuf,
+    /// Determines the path to the target binary
+    pub(crate) target_path: PathBuf,
+    /// Path to the final elf target
+    pub(crate) elf_path: Option<PathBuf>,
+    /// Determines the platform of the target binary
+    pub(crate) platform: Platform,
+    /// Determines the compiler used to compile the target binary
+    pub(crate) compiler: Compiler,
+    /// Determines the endianness of the target binary
+    pub(crate) endianness: Endianness,
+    /// Determines the default section order of the target binary.
+    /// This can be overridden per-segment
+    pub(crate) section_order: Vec<String>,
+    /// Determines the code that is inserted by default in generated .c files
+    pub(crate) generated_c_preamble: String,
+    /// Determines the code that is inserted by default in generated .s files
+    pub(crate) generated_s_preamble: String,
+    /// Determines any extra content to be added in the generated macro.inc file
+    pub(crate) generated_macro_inc_content: Option<String>,
+    /// Determines if files related to assembly macros should be regenerated by splat
+    pub(crate) generate_asm_macros_files: bool,
+    /// Changes the definition of the generated `INCLUDE_ASM`
+    pub(crate) include_asm_macro_style: IncludeAsmMacroStyle,
+    /// Directory to place the generated asm macros files
+    pub(crate) generated_asm_macros_directory: PathBuf,
+    /// Determines whether to use .o as the suffix for all binary files
+    #[serde(rename = "o_as_suffix")]
+    pub(crate) use_o_as_suffix: bool,
+    /// The value of the $gp register to correctly calculate offset to %gp_rel relocs
+    #[serde(rename = "gp_value")]
+    pub(crate) gp: Option<u64>,
+    /// Checks and errors if there are any non consecutive segment types
+    pub(crate) check_consecutive_segment_types: bool,
+    /// Disable checks on `platform` option
+    pub(crate) is_unsupported_platform: bool,
+    /// Allows to take precedence over the splat builtin platform segments via splat extension
+    pub(crate) allow_segment_overrides: bool,
+
+    // Paths
+    pub(crate) asset_path: PathBuf,
+    /// Determines the path to the symbol addresses file(s).
+    /// A symbol_addrs file is to be updated/curated manually and contains addresses of symbols
+    /// as well as optional metadata such as rom address, type, and more
+    #[serde(rename = "symbol_addrs_path")]
+    pub(crate) symbol_addrs_paths: Vec<PathBuf>,
+    #[serde(rename = "reloc_addrs_path")]
+    pub(crate) reloc_addrs_paths: Vec<PathBuf>,
+    /// Determines the path to the project build directory
+    pub(crate) build_path: PathBuf,
+    /// Determines the path to the source code directory
+    pub(crate) src_path: PathBuf,
     /// Determines the path to the asm code directory
     pub(crate) asm_path: PathBuf,
-    /*
-    # Determines the path to the asm data directory
-    data_path: Path
-    # Determines the path to the asm nonmatchings directory
-    nonmatchings_path: Path
-    # Determines the path to the asm matchings directory (used alongside `disassemble_all` to organize matching functions from nonmatching functions)
-    matchings_path: Path
-    # Determines the path to the cache file (used when supplied --use-cache via the CLI)
-    cache_path: Path
-    # Tells splat to consider `hasm` files to be relative to `src_path` instead of `asm_path`.
-    hasm_in_src_path: bool
-
-    # Determines whether to create an automatically-generated undefined functions file
-    # this file stores all functions that are referenced in the code but are not defined as seen by splat
-    create_undefined_funcs_auto: bool
-    # Determines the path to the undefined_funcs_auto file
-    undefined_funcs_auto_path: Path
-
-    # Determines whether to create an automatically-generated undefined symbols file
-    # this file stores all symbols that are referenced in the code but are not defined as seen by splat
-    create_undefined_syms_auto: bool
-    # Determines the path to the undefined_symbols_auto file
-    undefined_syms_auto_path: Path
-
-    # Determines the path in which to search for custom splat extensions
-    extensions_path: Optional[Path]
-
-    # Determines the path to library files that are to be linked into the target binary
-    lib_path: Path
-    # Determines the path to object files that are to be linked into the target binary
-    o_path: Path
-
-    # TODO document
-    elf_section_list_path: Optional[Path]
-
-    # Linker script
-    # Determines the default subalign value to be specified in the generated linker script
-    subalign: Optional[int]
-    # Determines whether to emit the subalign directive in the generated linker script
-    emit_subalign: bool
-    # The following option determines a list of sections for which automatic linker script entries should be added
-    auto_link_sections: List[str]
-    # Determines the desired path to the linker script that splat will generate
-    ld_script_path: Path
-    # Determines the desired path to the linker symbol header,
-    # which exposes externed definitions for all segment ram/rom start/end locations
-    ld_symbol_header_path: Optional[Path]
-    # Determines whether to add a discard section with a wildcard to the linker script
-    ld_discard_section: bool
-    # A list of sections to preserve during link time. It can be useful to preserve debugging sections
-    ld_sections_allowlist: List[str]
-    # A list of sections to discard during link time. It can be useful to avoid using the wildcard discard. Note that this option does not turn off `ld_discard_section`
-    ld_sections_denylist: List[str]
-    # Determines whether to add wildcards for section linking in the linker script (.rodata* for example)
-    ld_wildcard_sections: bool
-    # Determines whether to use `follows_vram` (segment option) and
-    # `vram_symbol` / `follows_classes` (vram_class options) to calculate vram addresses in the linker script.
-    # If disabled, this uses the plain integer values for vram addresses defined in the yaml.
-    ld_use_symbolic_vram_addresses: bool
-    # Ensures segments of vram classes with dependencies (`vram_symbol` / `follows_classes`) are written to the linker script AFTER the segments they depend on.
-    ld_sort_segments_by_vram_class_dependency: bool
-    # Change linker script generation to allow partially linking segments. Requires both `ld_partial_scripts_path` and `ld_partial_build_segments_path` to be set.
-    ld_partial_linking: bool
-    # Folder were each intermediary linker script will be written to.
-    ld_partial_scripts_path: Optional[Path]
-    # Folder where the built partially linked segments will be placed by the build system.
-    ld_partial_build_segments_path: Optional[Path]
-    # Generate a dependency file for every linker script generated. Dependency files will have the same path and name as the corresponding linker script, but changing the extension to `.d`. Requires `elf_path` to be set.
-    ld_dependencies: bool
-    # Emit an `-include` directive for each object file in the dependency file.
-    ld_dependencies_include: bool
-    # Legacy linker script generation does not impose the section_order specified in the yaml options or per-segment options.
-    ld_legacy_generation: bool
-    # If enabled, the end symbol for each segment will be placed before the alignment directive for the segment
-    segment_end_before_align: bool
-    # Controls the style of the auto-generated segment symbols in the linker script. Possible values: splat, makerom
-    segment_symbols_style: str
-    # Specifies the starting offset for rom address symbols in the linker script.
-    ld_rom_start: int
-    # The value passed to the FILL statement on each segment. `None` disables using FILL statements on the linker script. Defaults to a fill value of 0.
-    ld_fill_value: Optional[int]
-    # Allows to control if `bss` sections (and derivatived sections) will be put on a `NOLOAD` segment on the generated linker script or not.
-    ld_bss_is_noload: bool
-    # Aligns the start of the segment to the given value
-    ld_align_segment_start: Optional[int]
-    # Allows to toggle aligning the `*_VRAM_END` linker symbol for each segment.
-    ld_align_segment_vram_end: bool
-    # Allows to toggle aligning the `*_END` linker symbol for each section of each section.
-    ld_align_section_vram_end: bool
-    # If enabled, the generated linker script will have a linker symbol for each data file
-    ld_generate_symbol_per_data_segment: bool
-    # Sets the default option for the `bss_contains_common` attribute of all segments.
-    ld_bss_contains_common: bool
-    # Specify an expression to be used for the `_gp` symbol in the generated linker script instead of a hardcoded value.
-    ld_gp_expression: Optional[str]
-
-    ################################################################################
-    # C file options
-    ################################################################################
-    # Determines whether to create new c files if they don't exist
-    create_c_files: bool
-    # Determines whether to "auto-decompile" empty functions
-    auto_decompile_empty_functions: bool
-    # Determines whether to detect matched/unmatched functions in existing c files
-    # so we can avoid creating .s files for already-decompiled functions
-    do_c_func_detection: bool
-    # Determines the newline char(s) to be used in c files
-    c_newline: str
-
-    ################################################################################
-    # (Dis)assembly-related options
-    ################################################################################
-    # The following options determine the format that symbols should be named by default
-    symbol_name_format: str
-    # Same as above but for symbols with no rom address
-    symbol_name_format_no_rom: str
-    # Determines whether to detect and hint to the user about likely file splits when disassembling
-    find_file_boundaries: bool
-    # Determines whether to detect and hint to the user about possible rodata sections corresponding to a text section
-    pair_rodata_to_text: bool
-    # Determines whether to attempt to automatically migrate rodata into functions
-    # (only works in certain circumstances)
-    migrate_rodata_to_functions: bool
-    # Determines the header to be used in every asm file that's included from c files
-    asm_inc_header: str
-    # Determines the macro used to declare functions in asm files
-    asm_function_macro: str
-    # Determines the macro used to declare symbols in the middle of functions in asm files (which may be alternative entries)
-    asm_function_alt_macro: str
-    # Determines the macro used to declare jumptable labels in asm files
-    asm_jtbl_label_macro: str
-    # Determines the macro used to declare data symbols in asm files
-    asm_data_macro: str
-    # Determines the macro used at the end of a function, such as endlabel or .end
-    asm_end_label: str
-    # Determines the macro used at the end of a data symbol, such as enddlabel
-    asm_data_end_label: str
-    # Determines the macro used to declare ehtable labels in asm files
-    asm_ehtable_label_macro: str
-    # Determines the macro used to declare the given symbol is a non matching one.
-    asm_nonmatching_label_macro: str
-    # Toggles the .size directive emitted by the disassembler
-    asm_emit_size_directive: Optional[bool]
-    # Determines the number of characters to left align before the TODO finish documenting
-    mnemonic_ljust: int
-    # Determines whether to pad the rom address
-    rom_address_padding: bool
-    # Determines which ABI names to use for general purpose registers
-    mips_abi_gpr: str
-    # Determines which ABI names to use for floating point registers
-    # Valid values: 'numeric', 'o32', 'n32', 'n64'
-    # o32 is highly recommended, as it provides logically named registers for floating point instructions
-    # For more info, see https://gist.github.com/EllipticEllipsis/27eef11205c7a59d8ea85632bc49224d
-    mips_abi_float_regs: str
-    # Determines whether functions inside c files should have named registers
-    named_regs_for_c_funcs: bool
-    # Determines whether to add ".set gp=64" to asm/hasm files
-    add_set_gp_64: bool
-    # Generate .asmproc.d dependency files for each C file which still reference functions in assembly files
-    create_asm_dependencies: bool
-    # Global option for rodata string encoding. This can be overriden per segment
-    string_encoding: Optional[str]
-    # Global option for data string encoding. This can be overriden per segment
-    data_string_encoding: Optional[str]
-    # Global option for the rodata string guesser. 0 disables the guesser completely.
-    rodata_string_guesser_level: Optional[int]
-    # Global option for the data string guesser. 0 disables the guesser completely.
-    data_string_guesser_level: Optional[int]
-    # Tells the disassembler whether to create dummy and unreferenced data symbols after another symbol with non-zero user-declared size.
-    create_data_pads: Optional[bool]
-    # Tells the disassembler whether to create dummy and unreferenced rodata symbols after another symbol with non-zero user-declared size.
-    create_rodata_pads: Optional[bool]
-    # Tells the disassembler whether to create dummy and unreferenced bss/sbss symbols after another symbol with non-zero user-declared size.
-    create_bss_pads: Optional[bool]
-    # Global option for allowing data symbols using addends on symbol references. It can be overriden per symbol
-    allow_data_addends: bool
-    # Tells the disassembler to try disassembling functions with unknown instructions instead of falling back to disassembling as raw data
-    disasm_unknown: bool
-    # Tries to detect redundant and unreferenced functions ends and merge them together. This option is ignored if the compiler is not set to IDO.
-    detect_redundant_function_end: bool
-    # Don't skip disassembling already matched functions and migrated sections
-    disassemble_all: bool
-    # Emit a full `.s` file for each `c`/`cpp` segment besides the generated `nonmatchings` individual functions
-    make_full_disasm_for_code: bool
-    # Allow specifying that the global memory range may be larger than what was automatically detected.
-    # Useful for projects where splat is used in multiple individual files, meaning the expected global segment may not be properly detected because each instance of splat can't see the info from other files.
-    global_vram_start: Optional[int]
-    global_vram_end: Optional[int]
-    # For `c` segments (functions under the nonmatchings folder).
-    # If True then use the `%gp_rel` explicit relocation parameter on instructions that use the $gp register,
-    # otherwise strip the `%gp_rel` parameter entirely and convert those instructions into macro instructions that may not assemble to the original
-    # bytes. In the latter case, it is the user's responsability to provide the symbol's information to the assembler so it can assemble the
-    # instruction with the proper relocation.
-    use_gp_rel_macro_nonmatching: bool
-    # Does the same as `use_gp_rel_macro_nonmatching`, except it is only applied to `asm` and `hasm` segments.
-    use_gp_rel_macro: bool
-    # Allows emitting suggestions for where the rodata may start by examining the data section.
-    suggestion_rodata_section_start: bool
-
-    ################################################################################
-    # N64-specific options
-    ################################################################################
-    # Determines the encoding of the header
-    header_encoding: str
-    # Determines the type gfx ucode (used by gfx segments)
-    # Valid options are ['f3d', 'f3db', 'f3dex', 'f3dexb', 'f3dex2']
-    gfx_ucode: str
-    # Use named libultra symbols by default. Those will need to be added to a linker script manually by the user
-    libultra_symbols: bool
-    # Use named libultra symbols by default. Those will need to be added to a linker script manually by the user
-    ique_symbols: bool
-    # Use named hardware register symbols by default. Those will need to be added to a linker script manually by the user
-    hardware_regs: bool
-    # Append the image type to the output file extension
-    image_type_in_extension: bool
-
-    ################################################################################
-    # Compiler-specific options
-    ################################################################################
-    # Determines whether to use a legacy INCLUDE_ASM macro format in c files
-    # only applies to GCC/SN64
-    use_legacy_include_asm: bool
-    # Emit alignment directives in branch labels, as a way to workaround the short loop bug present in SN PS2 compilers
-    align_on_branch_labels: bool
-
-    */
+    /// Determines the path to the asm data directory
+    pub(crate) data_path: PathBuf,
+    /// Determines the path to the asm nonmatchings directory
+    pub(crate) nonmatchings_path: PathBuf,
+    /// Determines the path to the asm matchings directory (used alongside `disassemble_all` to organize matching functions from nonmatching functions)
+    pub(crate) matchings_path: PathBuf,
+    /// Determines the path to the cache file (used when supplied --use-cache via the CLI)
+    pub(crate) cache_path: PathBuf,
+    /// Tells splat to consider `hasm` files to be relative to `src_path` instead of `asm_path`
+    pub(crate) hasm_in_src_path: bool,
+
+    /// Determines whether to create an automatically-generated undefined functions file.
+    /// This file stores all functions that are referenced in the code but are not defined as seen by splat
+    pub(crate) create_undefined_funcs_auto: bool,
+    /// Determines the path to the undefined_funcs_auto file
+    pub(crate) undefined_funcs_auto_path: PathBuf,
+
+    /// Determines whether to create an automatically-generated undefined symbols file.
+    /// This file stores all symbols that are referenced in the code but are not defined as seen by splat
+    pub(crate) create_undefined_syms_auto: bool,
+    /// Determines the path to the undefined_symbols_auto file
+    pub(crate) undefined_syms_auto_path: PathBuf,
+
+    /// Determines the path in which to search for custom splat extensions
+    pub(crate) extensions_path: Option<PathBuf>,
+
+    /// Determines the path to library files that are to be linked into the target binary
+    pub(crate) lib_path: PathBuf,
+    /// Determines the path to object files that are to be linked into the target binary
+    pub(crate) o_path: PathBuf,
+
+    pub(crate) elf_section_list_path: Option<PathBuf>,
+
+    // Linker script
+    /// Determines the default subalign value to be specified in the generated linker script
+    pub(crate) subalign: Option<u64>,
+    /// Determines whether to emit the subalign directive in the generated linker script
+    pub(crate) emit_subalign: bool,
+    /// Determines a list of sections for which automatic linker script entries should be added
+    pub(crate) auto_link_sections: Vec<String>,
+    /// Determines the desired path to the linker script that splat will generate
+    pub(crate) ld_script_path: PathBuf,
+    /// Determines the desired path to the linker symbol header,
+    /// which exposes externed definitions for all segment ram/rom start/end locations
+    pub(crate) ld_symbol_header_path: Option<PathBuf>,
+    /// Determines whether to add a discard section with a wildcard to the linker script
+    pub(crate) ld_discard_section: bool,
+    /// A list of sections to preserve during link time. It can be useful to preserve debugging sections
+    pub(crate) ld_sections_allowlist: Vec<String>,
+    /// A list of sections to discard during link time. It can be useful to avoid using the wildcard discard.
+    /// Note that this option does not turn off `ld_discard_section`
+    pub(crate) ld_sections_denylist: Vec<String>,
+    /// Determines whether to add wildcards for section linking in the linker script (.rodata* for example)
+    pub(crate) ld_wildcard_sections: bool,
+    /// Determines whether to use `follows_vram` (segment option) and
+    /// `vram_symbol` / `follows_classes` (vram_class options) to calculate vram addresses in the linker script.
+    /// If disabled, this uses the plain integer values for vram addresses defined in the yaml.
+    pub(crate) ld_use_symbolic_vram_addresses: bool,
+    /// Ensures segments of vram classes with dependencies (`vram_symbol` / `follows_classes`) are written
+    /// to the linker script AFTER the segments they depend on.
+    pub(crate) ld_sort_segments_by_vram_class_dependency: bool,
+    /// Change linker script generation to allow partially linking segments.
+    /// Requires both `ld_partial_scripts_path` and `ld_partial_build_segments_path` to be set.
+    pub(crate) ld_partial_linking: bool,
+    /// Folder were each intermediary linker script will be written to
+    pub(crate) ld_partial_scripts_path: Option<PathBuf>,
+    /// Folder where the built partially linked segments will be placed by the build system
+    pub(crate) ld_partial_build_segments_path: Option<PathBuf>,
+    /// Generate a dependency file for every linker script generated. Dependency files will have the same path
+    /// and name as the corresponding linker script, but changing the extension to `.d`. Requires `elf_path` to be set.
+    pub(crate) ld_dependencies: bool,
+    /// Emit an `-include` directive for each object file in the dependency file
+    pub(crate) ld_dependencies_include: bool,
+    /// Legacy linker script generation does not impose the section_order specified in the yaml options or per-segment options
+    pub(crate) ld_legacy_generation: bool,
+    /// If enabled, the end symbol for each segment will be placed before the alignment directive for the segment
+    pub(crate) segment_end_before_align: bool,
+    /// Controls the style of the auto-generated segment symbols in the linker script
+    pub(crate) segment_symbols_style: SegmentSymbolsStyle,
+    /// Specifies the starting offset for rom address symbols in the linker script
+    pub(crate) ld_rom_start: u64,
+    /// The value passed to the FILL statement on each segment. `None` disables using FILL statements on the linker script
+    pub(crate) ld_fill_value: Option<u64>,
+    /// Allows to control if `bss` sections (and derivatived sections) will be put on a `NOLOAD` segment on the generated linker script or not
+    pub(crate) ld_bss_is_noload: bool,
+    /// Aligns the start of the segment to the given value
+    pub(crate) ld_align_segment_start: Option<u64>,
+    /// Allows to toggle aligning the `*_VRAM_END` linker symbol for each segment
+    pub(crate) ld_align_segment_vram_end: bool,
+    /// Allows to toggle aligning the `*_END` linker symbol for each section of each section
+    pub(crate) ld_align_section_vram_end: bool,
+    /// If enabled, the generated linker script will have a linker symbol for each data file
+    pub(crate) ld_generate_symbol_per_data_segment: bool,
+    /// Sets the default option for the `bss_contains_common` attribute of all segments
+    pub(crate) ld_bss_contains_common: bool,
+    /// Specify an expression to be used for the `_gp` symbol in the generated linker script instead of a hardcoded value
+    pub(crate) ld_gp_expression: Option<String>,
+
+    // C file options
+    /// Determines whether to create new c files if they don't exist
+    pub(crate) create_c_files: bool,
+    /// Determines whether to "auto-decompile" empty functions
+    pub(crate) auto_decompile_empty_functions: bool,
+    /// Determines whether to detect matched/unmatched functions in existing c files
+    /// so we can avoid creating .s files for already-decompiled functions
+    pub(crate) do_c_func_detection: bool,
+    /// Determines the newline char(s) to be used in c files
+    pub(crate) c_newline: String,
+
+    // (Dis)assembly-related options
+    /// Determines the format that symbols should be named by default
+    pub(crate) symbol_name_format: String,
+    /// Same as above but for symbols with no rom address
+    pub(crate) symbol_name_format_no_rom: String,
+    /// Determines whether to detect and hint to the user about likely file splits when disassembling
+    pub(crate) find_file_boundaries: bool,
+    /// Determines whether to detect and hint to the user about possible rodata sections corresponding to a text section
+    pub(crate) pair_rodata_to_text: bool,
+    /// Determines whether to attempt to automatically migrate rodata into functions
+    /// (only works in certain circumstances)
+    pub(crate) migrate_rodata_to_functions: bool,
+    /// Determines the header to be used in every asm file that's included from c files
+    pub(crate) asm_inc_header: String,
+    /// Determines the macro used to declare functions in asm files
+    pub(crate) asm_function_macro: String,
+    /// Determines the macro used to declare symbols in the middle of functions in asm files (which may be alternative entries)
+    pub(crate) asm_function_alt_macro: String,
+    /// Determines the macro used to declare jumptable labels in asm files
+    pub(crate) asm_jtbl_label_macro: String,
+    /// Determines the macro used to declare data symbols in asm files
+    pub(crate) asm_data_macro: String,
+    /// Determines the macro used at the end of a function, such as endlabel or .end
+    pub(crate) asm_end_label: String,
+    /// Determines the macro used at the end of a data symbol, such as enddlabel
+    pub(crate) asm_data_end_label: String,
+    /// Determines the macro used to declare ehtable labels in asm files
+    pub(crate) asm_ehtable_label_macro: String,
+    /// Determines the macro used to declare the given symbol is a non matching one
+    pub(crate) asm_nonmatching_label_macro: String,
+    /// Toggles the .size directive emitted by the disassembler
+    pub(crate) asm_emit_size_directive: Option<bool>,
+    /// Determines the number of characters to left align before the instruction operands
+    pub(crate) mnemonic_ljust: usize,
+    /// Determines whether to pad the rom address
+    pub(crate) rom_address_padding: bool,
+    /// Determines which ABI names to use for general purpose registers
+    pub(crate) mips_abi_gpr: MipsAbi,
+    /// Determines which ABI names to use for floating point registers.
+    /// o32 is highly recommended, as it provides logically named registers for floating point instructions
+    pub(crate) mips_abi_float_regs: MipsAbi,
+    /// Determines whether functions inside c files should have named registers
+    pub(crate) named_regs_for_c_funcs: bool,
+    /// Determines whether to add ".set gp=64" to asm/hasm files
+    pub(crate) add_set_gp_64: bool,
+    /// Generate .asmproc.d dependency files for each C file which still reference functions in assembly files
+    pub(crate) create_asm_dependencies: bool,
+    /// Global option for rodata string encoding. This can be overriden per segment
+    pub(crate) string_encoding: Option<String>,
+    /// Global option for data string encoding. This can be overriden per segment
+    pub(crate) data_string_encoding: Option<String>,
+    /// Global option for the rodata string guesser. 0 disables the guesser completely
+    pub(crate) rodata_string_guesser_level: Option<u8>,
+    /// Global option for the data string guesser. 0 disables the guesser completely
+    pub(crate) data_string_guesser_level: Option<u8>,
+    /// Tells the disassembler whether to create dummy and unreferenced data symbols after another symbol with non-zero user-declared size
+    pub(crate) create_data_pads: Option<bool>,
+    /// Tells the disassembler whether to create dummy and unreferenced rodata symbols after another symbol with non-zero user-declared size
+    pub(crate) create_rodata_pads: Option<bool>,
+    /// Tells the disassembler whether to create dummy and unreferenced bss/sbss symbols after another symbol with non-zero user-declared size
+    pub(crate) create_bss_pads: Option<bool>,
+    /// Global option for allowing data symbols using addends on symbol references. It can be overriden per symbol
+    pub(crate) allow_data_addends: bool,
+    /// Tells the disassembler to try disassembling functions with unknown instructions instead of falling back to disassembling as raw data
+    pub(crate) disasm_unknown: bool,
+    /// Tries to detect redundant and unreferenced functions ends and merge them together.
+    /// This option is ignored if the compiler is not set to IDO.
+    pub(crate) detect_redundant_function_end: bool,
+    /// Don't skip disassembling already matched functions and migrated sections
+    pub(crate) disassemble_all: bool,
+    /// Emit a full `.s` file for each `c`/`cpp` segment besides the generated `nonmatchings` individual functions
+    pub(crate) make_full_disasm_for_code: bool,
+    /// Allow specifying that the global memory range may be larger than what was automatically detected
+    pub(crate) global_vram_start: Option<u64>,
+    pub(crate) global_vram_end: Option<u64>,
+    /// For `c` segments (functions under the nonmatchings folder).
+    /// If true then use the `%gp_rel` explicit relocation parameter on instructions that use the $gp register,
+    /// otherwise strip the `%gp_rel` parameter entirely and convert those instructions into macro instructions
+    pub(crate) use_gp_rel_macro_nonmatching: bool,
+    /// Does the same as `use_gp_rel_macro_nonmatching`, except it is only applied to `asm` and `hasm` segments
+    pub(crate) use_gp_rel_macro: bool,
+    /// Allows emitting suggestions for where the rodata may start by examining the data section
+    pub(crate) suggestion_rodata_section_start: bool,
+
+    // N64-specific options
+    /// Determines the encoding of the header
+    pub(crate) header_encoding: String,
+    /// Determines the type gfx ucode (used by gfx segments)
+    pub(crate) gfx_ucode: GfxUcode,
+    /// Use named libultra symbols by default. Those will need to be added to a linker script manually by the user
+    pub(crate) libultra_symbols: bool,
+    /// Use named iQue symbols by default. Those will need to be added to a linker script manually by the user
+    pub(crate) ique_symbols: bool,
+    /// Use named hardware register symbols by default. Those will need to be added to a linker script manually by the user
+    pub(crate) hardware_regs: bool,
+    /// Append the image type to the output file extension
+    pub(crate) image_type_in_extension: bool,
+
+    // Compiler-specific options
+    /// Determines whether to use a legacy INCLUDE_ASM macro format in c files.
+    /// Only applies to GCC/SN64
+    pub(crate) use_legacy_include_asm: bool,
+    /// Emit alignment directives in branch labels, as a way to workaround the short loop bug present in SN PS2 compilers
+    pub(crate) align_on_branch_labels: bool,
+
+    /// Where the value of each option came from, keyed by its yaml name
+    #[serde(skip)]
+    pub(crate) sources: BTreeMap<String, OptionSource>,
+}
+
+impl SplatOpts {
+    pub fn parse(options: HashMap<String, Value>) -> Result<Self> {
+        let mut p = OptParser::new(options);
+
+        let is_unsupported_platform = p.parse_opt("is_unsupported_platform", false)?;
+        let platform = p.parse_platform(is_unsupported_platform)?;
+        let compiler = p.parse_compiler(Compiler::Ido)?;
+
+        let basename: String = p.parse_required_opt("basename")?;
+        let base_path: PathBuf = p.parse_required_opt("base_path")?;
+        let asm_path = p.parse_path(&base_path, "asm_path", "asm")?;
+
+        let defaults = OptionDefaults::new(&platform, compiler);
+
+        let opts = Self {
+            verbose: false,
+            dump_symbols: p.parse_opt("dump_symbols", false)?,
+            dump_symbols_references: p.parse_opt("dump_symbols_references", false)?,
+            modes: vec!["all".to_string()],
+            force_hasm: false,
+
+            target_path: p.parse_required_path(&base_path, "target_path")?,
+            elf_path: p.parse_optional_path(&base_path, "elf_path")?,
+            endianness: p.parse_default("endianness", defaults.endianness)?,
+            section_order: p.parse_default("section_order", defaults.section_order)?,
+            generated_c_preamble: p
+                .parse_opt("generated_c_preamble", "#include \"common.h\"".to_string())?,
+            generated_s_preamble: p.parse_opt("generated_s_preamble", String::new())?,
+            generated_macro_inc_content: p.parse_optional_opt("generated_macro_inc_content")?,
+            generate_asm_macros_files: p.parse_opt("generate_asm_macros_files", true)?,
+            include_asm_macro_style: p
+                .parse_opt("include_asm_macro_style", IncludeAsmMacroStyle::Default)?,
+            generated_asm_macros_directory: p.parse_path(
+                &base_path,
+                "generated_asm_macros_directory",
+                "include",
+            )?,
+            use_o_as_suffix: p.parse_opt("o_as_suffix", false)?,
+            gp: p.parse_optional_opt("gp_value")?,
+            check_consecutive_segment_types: p
+                .parse_opt("check_consecutive_segment_types", true)?,
+            is_unsupported_platform,
+            allow_segment_overrides: p.parse_opt("allow_segment_overrides", false)?,
+
+            asset_path: p.parse_path(&base_path, "asset_path", "assets")?,
+            symbol_addrs_paths: p.parse_path_list(
+                &base_path,
+                "symbol_addrs_path",
+                "symbol_addrs.txt",
+            )?,
+            reloc_addrs_paths: p.parse_path_list(
+                &base_path,
+                "reloc_addrs_path",
+                "reloc_addrs.txt",
+            )?,
+            build_path: p.parse_path(&base_path, "build_path", "build")?,
+            src_path: p.parse_path(&base_path, "src_path", "src")?,
+            data_path: p.parse_derived_path(&asm_path, "data_path", "data", "asm_path")?,
+            nonmatchings_path: p.parse_derived_path(
+                &asm_path,
+                "nonmatchings_path",
+                "nonmatchings",
+                "asm_path",
+            )?,
+            matchings_path: p.parse_derived_path(
+                &asm_path,
+                "matchings_path",
+                "matchings",
+                "asm_path",
+            )?,
+            cache_path: p.parse_path(&base_path, "cache_path", ".splache")?,
+            hasm_in_src_path: p.parse_opt("hasm_in_src_path", false)?,
+
+            create_undefined_funcs_auto: p.parse_opt("create_undefined_funcs_auto", true)?,
+            undefined_funcs_auto_path: p.parse_path(
+                &base_path,
+                "undefined_funcs_auto_path",
+                "undefined_funcs_auto.txt",
+            )?,
+            create_undefined_syms_auto: p.parse_opt("create_undefined_syms_auto", true)?,
+            undefined_syms_auto_path: p.parse_path(
+                &base_path,
+                "undefined_syms_auto_path",
+                "undefined_syms_auto.txt",
+            )?,
+
+            extensions_path: p.parse_optional_path(&base_path, "extensions_path")?,
+            lib_path: p.parse_path(&base_path, "lib_path", "lib")?,
+            o_path: p.parse_path(&base_path, "o_path", "o")?,
+            elf_section_list_path: p.parse_optional_path(&base_path, "elf_section_list_path")?,
+
+            subalign: p.parse_opt("subalign", Some(16))?,
+            emit_subalign: p.parse_opt("emit_subalign", true)?,
+            auto_link_sections: p
+                .parse_opt("auto_link_sections", strings(&[".data", ".rodata", ".bss"]))?,
+            ld_script_path: p.parse_derived_path(
+                &base_path,
+                "ld_script_path",
+                &format!("{basename}.ld"),
+                "basename",
+            )?,
+            ld_symbol_header_path: p.parse_optional_path(&base_path, "ld_symbol_header_path")?,
+            ld_discard_section: p.parse_opt("ld_discard_section", true)?,
+            ld_sections_allowlist: p.parse_opt("ld_sections_allowlist", Vec::new())?,
+            ld_sections_denylist: p.parse_opt("ld_sections_denylist", Vec::new())?,
+            ld_wildcard_sections: p.parse_opt("ld_wildcard_sections", false)?,
+            ld_use_symbolic_vram_addresses: p.parse_opt("ld_use_symbolic_vram_addresses", true)?,
+            ld_sort_segments_by_vram_class_dependency: p
+                .parse_opt("ld_sort_segments_by_vram_class_dependency", false)?,
+            ld_partial_linking: p.parse_opt("ld_partial_linking", false)?,
+            ld_partial_scripts_path: p
+                .parse_optional_path(&base_path, "ld_partial_scripts_path")?,
+            ld_partial_build_segments_path: p
+                .parse_optional_path(&base_path, "ld_partial_build_segments_path")?,
+            ld_dependencies: p.parse_opt("ld_dependencies", false)?,
+            ld_dependencies_include: p.parse_opt("ld_dependencies_include", false)?,
+            ld_legacy_generation: p.parse_opt("ld_legacy_generation", false)?,
+            segment_end_before_align: p.parse_opt("segment_end_before_align", false)?,
+            segment_symbols_style: p
+                .parse_opt("segment_symbols_style", SegmentSymbolsStyle::Splat)?,
+            ld_rom_start: p.parse_opt("ld_rom_start", 0)?,
+            ld_fill_value: p.parse_opt("ld_fill_value", Some(0))?,
+            ld_bss_is_noload: p.parse_default("ld_bss_is_noload", defaults.ld_bss_is_noload)?,
+            ld_align_segment_start: p.parse_optional_opt("ld_align_segment_start")?,
+            ld_align_segment_vram_end: p.parse_opt("ld_align_segment_vram_end", true)?,
+            ld_align_section_vram_end: p.parse_opt("ld_align_section_vram_end", true)?,
+            ld_generate_symbol_per_data_segment: p
+                .parse_opt("ld_generate_symbol_per_data_segment", true)?,
+            ld_bss_contains_common: p.parse_opt("ld_bss_contains_common", false)?,
+            ld_gp_expression: p.parse_optional_opt("ld_gp_expression")?,
+
+            create_c_files: p.parse_opt("create_c_files", true)?,
+            auto_decompile_empty_functions: p.parse_opt("auto_decompile_empty_functions", true)?,
+            do_c_func_detection: p.parse_opt("do_c_func_detection", true)?,
+            c_newline: p.parse_default("c_newline", defaults.c_newline)?,
+
+            symbol_name_format: p.parse_opt("symbol_name_format", "$VRAM".to_string())?,
+            symbol_name_format_no_rom: p
+                .parse_opt("symbol_name_format_no_rom", "$VRAM_$SEG".to_string())?,
+            find_file_boundaries: p.parse_opt("find_file_boundaries", true)?,
+            pair_rodata_to_text: p.parse_opt("pair_rodata_to_text", true)?,
+            migrate_rodata_to_functions: p.parse_opt("migrate_rodata_to_functions", true)?,
+            asm_inc_header: p.parse_default("asm_inc_header", defaults.asm_inc_header)?,
+            asm_function_macro: p
+                .parse_default("asm_function_macro", defaults.asm_function_macro)?,
+            asm_function_alt_macro: p
+                .parse_default("asm_function_alt_macro", defaults.asm_function_alt_macro)?,
+            asm_jtbl_label_macro: p
+                .parse_default("asm_jtbl_label_macro", defaults.asm_jtbl_label_macro)?,
+            asm_data_macro: p.parse_default("asm_data_macro", defaults.asm_data_macro)?,
+            asm_end_label: p.parse_default("asm_end_label", defaults.asm_end_label)?,
+            asm_data_end_label: p
+                .parse_default("asm_data_end_label", defaults.asm_data_end_label)?,
+            asm_ehtable_label_macro: p
+                .parse_default("asm_ehtable_label_macro", defaults.asm_ehtable_label_macro)?,
+            asm_nonmatching_label_macro: p.parse_default(
+                "asm_nonmatching_label_macro",
+                defaults.asm_nonmatching_label_macro,
+            )?,
+            asm_emit_size_directive: p
+                .parse_default("asm_emit_size_directive", defaults.asm_emit_size_directive)?,
+            mnemonic_ljust: p.parse_opt("mnemonic_ljust", 11)?,
+            rom_address_padding: p.parse_opt("rom_address_padding", false)?,
+            mips_abi_gpr: p.parse_opt("mips_abi_gpr", MipsAbi::O32)?,
+            mips_abi_float_regs: p.parse_opt("mips_abi_float_regs", MipsAbi::Numeric)?,
+            named_regs_for_c_funcs: p.parse_opt("named_regs_for_c_funcs", true)?,
+            add_set_gp_64: p.parse_opt("add_set_gp_64", true)?,
+            create_asm_dependencies: p.parse_opt("create_asm_dependencies", false)?,
+            string_encoding: p.parse_default("string_encoding", defaults.string_encoding)?,
+            data_string_encoding: p
+                .parse_default("data_string_encoding", defaults.data_string_encoding)?,
+            rodata_string_guesser_level: p.parse_optional_opt("rodata_string_guesser_level")?,
+            data_string_guesser_level: p.parse_optional_opt("data_string_guesser_level")?,
+            create_data_pads: p.parse_optional_opt("create_data_pads")?,
+            create_rodata_pads: p.parse_optional_opt("create_rodata_pads")?,
+            create_bss_pads: p.parse_optional_opt("create_bss_pads")?,
+            allow_data_addends: p.parse_opt("allow_data_addends", true)?,
+            disasm_unknown: p.parse_opt("disasm_unknown", false)?,
+            detect_redundant_function_end: p.parse_opt("detect_redundant_function_end", true)?,
+            disassemble_all: p.parse_opt("disassemble_all", false)?,
+            make_full_disasm_for_code: p.parse_opt("make_full_disasm_for_code", false)?,
+            global_vram_start: p.parse_optional_opt("global_vram_start")?,
+            global_vram_end: p.parse_optional_opt("global_vram_end")?,
+            use_gp_rel_macro_nonmatching: p.parse_opt("use_gp_rel_macro_nonmatching", true)?,
+            use_gp_rel_macro: p.parse_opt("use_gp_rel_macro", true)?,
+            suggestion_rodata_section_start: p
+                .parse_opt("suggestion_rodata_section_start", true)?,
+
+            header_encoding: p.parse_opt("header_encoding", "ASCII".to_string())?,
+            gfx_ucode: p.parse_opt("gfx_ucode", GfxUcode::F3dex2)?,
+            libultra_symbols: p.parse_opt("libultra_symbols", false)?,
+            ique_symbols: p.parse_opt("ique_symbols", false)?,
+            hardware_regs: p.parse_opt("hardware_regs", false)?,
+            image_type_in_extension: p.parse_opt("image_type_in_extension", false)?,
+
+            use_legacy_include_asm: p.parse_opt("use_legacy_include_asm", true)?,
+            align_on_branch_labels: p
+                .parse_default("align_on_branch_labels", defaults.align_on_branch_labels)?,
+
+            basename,
+            base_path,
+            platform,
+            compiler,
+            asm_path,
+
+            sources: BTreeMap::new(),
+        };
+
+        p.check_no_unread_opts()?;
+
+        Ok(Self {
+            sources: p.sources,
+            ..opts
+        })
+    }
+
+    /// Lists every option with its effective value and the reason that value was chosen
+    pub fn describe(&self) -> Result<String> {
+        let Value::Mapping(values) = serde_yaml::to_value(self)? else {
+            bail!("Options did not serialize into a mapping");
+        };
+
+        let mut out = String::new();
+        for (key, value) in values {
+            let key = key.as_str().context("Option name is not a string")?;
+            let source = self.sources.get(key).unwrap_or(&OptionSource::Default);
+            writeln!(out, "{key}: {}  # {source}", flow_value(&value)?)?;
+        }
+        Ok(out)
+    }
+
+    /// Makes every path relative to `config_dir`, the directory containing the yaml file,
+    /// since `base_path` is written relative to it.
+    pub(crate) fn resolve_relative_to(&mut self, config_dir: &Path) {
+        let paths = [
+            &mut self.base_path,
+            &mut self.target_path,
+            &mut self.generated_asm_macros_directory,
+            &mut self.asset_path,
+            &mut self.build_path,
+            &mut self.src_path,
+            &mut self.asm_path,
+            &mut self.data_path,
+            &mut self.nonmatchings_path,
+            &mut self.matchings_path,
+            &mut self.cache_path,
+            &mut self.undefined_funcs_auto_path,
+            &mut self.undefined_syms_auto_path,
+            &mut self.lib_path,
+            &mut self.o_path,
+            &mut self.ld_script_path,
+        ];
+        for path in paths {
+            *path = config_dir.join(&*path);
+        }
+
+        let optional_paths = [
+            &mut self.elf_path,
+            &mut self.extensions_path,
+            &mut self.elf_section_list_path,
+            &mut self.ld_symbol_header_path,
+            &mut self.ld_partial_scripts_path,
+            &mut self.ld_partial_build_segments_path,
+        ];
+        for path in optional_paths.into_iter().flatten() {
+            *path = config_dir.join(&*path);
+        }
+
+        for path in self
+            .symbol_addrs_paths
+            .iter_mut()
+            .chain(self.reloc_addrs_paths.iter_mut())
+        {
+            *path = config_dir.join(&*path);
+        }
+    }
+
+    /// Restricts the outputs to the given categories, such as `code`, `data` or `ld`
+    pub fn set_modes(&mut self, modes: Vec<String>) -> Result<()> {
+        if let Some(unknown) = modes.iter().find(|mode| !MODES.contains(&mode.as_str())) {
+            bail!(
+                "Unknown mode `{unknown}`, expected one of: {}",
+                MODES.join(", ")
+            );
+        }
+
+        self.modes = modes;
+        Ok(())
+    }
+
+    /// Overwrites the hand-edited `hasm` files instead of leaving the existing ones alone
+    pub fn set_force_hasm(&mut self, force_hasm: bool) {
+        self.force_hasm = force_hasm;
+    }
+
+    /// Whether outputs of the given category should be written
+    pub fn is_mode_active(&self, mode: &str) -> bool {
+        self.modes.iter().any(|m| m == mode || m == "all")
+    }
+
+    pub fn platform(&self) -> &Platform {
+        &self.platform
+    }
+    pub fn compiler(&self) -> Compiler {
+        self.compiler
+    }
+    pub fn base_path(&self) -> &Path {
+        &self.base_path
+    }
+    pub fn target_path(&self) -> &Path {
+        &self.target_path
+    }
+}
+
+impl<'de> Deserialize<'de> for SplatOpts {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let options = HashMap::<String, Value>::deserialize(deserializer)?;
+        SplatOpts::parse(options).map_err(|e| serde::de::Error::custom(format!("{e:#}")))
+    }
+}
+
+/// Output categories `--modes` can restrict splitting to
+pub const MODES: &[&str] = &["all", "code", "data", "img", "gfx", "vtx", "bin", "ld"];
+
+fn strings(values: &[&str]) -> Vec<String> {
+    values.iter().map(|s| s.to_string()).collect()
+}
+
+/// Renders a value on a single line, in yaml flow style
+fn flow_value(value: &Value) -> Result<String> {
+    Ok(match value {
+        Value::Sequence(values) => {
+            let values = values.iter().map(flow_value).collect::<Result<Vec<_>>>()?;
+            format!("[{}]", values.join(", "))
+        }
+        Value::String(s) if s.contains('\n') => format!("{s:?}"),
+        _ => serde_yaml::to_string(value)?.trim_end().to_string(),
+    })
+}
+
+/// Reads options out of the raw yaml mapping, keeping track of which ones were consumed
+/// so unknown keys can be reported afterwards.
+struct OptParser {
+    opts: HashMap<String, Value>,
+    sources: BTreeMap<String, OptionSource>,
+}
+
+impl OptParser {
+    fn new(opts: HashMap<String, Value>) -> Self {
+        Self {
+            opts,
+            sources: BTreeMap::new(),
+        }
+    }
+
+    fn take<T: DeserializeOwned>(&mut self, key: &str) -> Result<Option<T>> {
+        let Some(value) = self.opts.remove(key) else {
+            self.sources.insert(key.to_string(), OptionSource::Default);
+            return Ok(None);
+        };
+        self.sources.insert(key.to_string(), OptionSource::User);
+        serde_yaml::from_value(value)
+            .map(Some)
+            .with_context(|| format!("Invalid value for option `{key}`"))
+    }
+
+    fn parse_opt<T: DeserializeOwned>(&mut self, key: &str, default: T) -> Result<T> {
+        Ok(self.take(key)?.unwrap_or(default))
+    }
+
+    fn parse_default<T: DeserializeOwned>(
+        &mut self,
+        key: &str,
+        default: DefaultValue<T>,
+    ) -> Result<T> {
+        match self.take(key)? {
+            Some(value) => Ok(value),
+            None => {
+                self.sources.insert(key.to_string(), default.source);
+                Ok(default.value)
+            }
+        }
+    }
+
+    fn parse_optional_opt<T: DeserializeOwned>(&mut self, key: &str) -> Result<Option<T>> {
+        Ok(self.take::<Option<T>>(key)?.flatten())
+    }
+
+    fn parse_required_opt<T: DeserializeOwned>(&mut self, key: &str) -> Result<T> {
+        self.take(key)?
+            .with_context(|| format!("Missing required option `{key}`"))
+    }
+
+    fn parse_path(&mut self, base: &Path, key: &str, default: &str) -> Result<PathBuf> {
+        let path: PathBuf = self.parse_opt(key, PathBuf::from(default))?;
+        Ok(base.join(path))
+    }
+
+    /// Like `parse_path`, but the default is computed from the option `derived_from`
+    fn parse_derived_path(
+        &mut self,
+        base: &Path,
+        key: &str,
+        default: &str,
+        derived_from: &'static str,
+    ) -> Result<PathBuf> {
+        let path = self.parse_path(base, key, default)?;
+        if self.sources.get(key) == Some(&OptionSource::Default) {
+            self.sources
+                .insert(key.to_string(), OptionSource::Derived(derived_from));
+        }
+        Ok(path)
+    }
+
+    fn parse_required_path(&mut self, base: &Path, key: &str) -> Result<PathBuf> {
+        let path: PathBuf = self.parse_required_opt(key)?;
+        Ok(base.join(path))
+    }
+
+    fn parse_optional_path(&mut self, base: &Path, key: &str) -> Result<Option<PathBuf>> {
+        let path: Option<PathBuf> = self.parse_optional_opt(key)?;
+        Ok(path.map(|p| base.join(p)))
+    }
+
+    /// Accepts either a single path or a list of them
+    fn parse_path_list(&mut self, base: &Path, key: &str, default: &str) -> Result<Vec<PathBuf>> {
+        let source = match self.opts.contains_key(key) {
+            true => OptionSource::User,
+            false => OptionSource::Default,
+        };
+        self.sources.insert(key.to_string(), source);
+
+        let paths = match self.opts.remove(key) {
+            None => vec![PathBuf::from(default)],
+            Some(Value::String(s)) => vec![PathBuf::from(s)],
+            Some(value @ Value::Sequence(_)) => serde_yaml::from_value(value)
+                .with_context(|| format!("Invalid value for option `{key}`"))?,
+            Some(value) => bail!(
+                "Invalid value for option `{key}`: expected a path or a list of paths, got {value:?}"
+            ),
+        };
+        Ok(paths.into_iter().map(|p| base.join(p)).collect())
+    }
+
+    fn parse_platform(&mut self, is_unsupported_platform: bool) -> Result<Platform> {
+        let name: String = self.parse_required_opt("platform")?;
+        match name.as_str() {
+            "n64" => Ok(Platform::N64),
+            "psx" => Ok(Platform::Psx),
+            "ps2" => Ok(Platform::Ps2),
+            "psp" => Ok(Platform::Psp),
+            _ if is_unsupported_platform => Ok(Platform::Unsupported(name)),
+            _ => bail!(
+                "Invalid value for option `platform`: `{name}` is not one of n64, psx, ps2, psp. Set `is_unsupported_platform` to use it anyways"
+            ),
+        }
+    }
+
+    fn parse_compiler(&mut self, default: Compiler) -> Result<Compiler> {
+        match self.take::<String>("compiler")? {
+            None => Ok(default),
+            Some(name) => Compiler::from_name(&name).with_context(|| {
+                format!("Invalid value for option `compiler`: unknown compiler `{name}`")
+            }),
+        }
+    }
+
+    fn check_no_unread_opts(&self) -> Result<()> {
+        if self.opts.is_empty() {
+            return Ok(());
+        }
+        let mut unread: Vec<&str> = self.opts.keys().map(|k| k.as_str()).collect();
+        unread.sort_unstable();
+        bail!("Unknown option(s): {}", unread.join(", "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::try_options;
+
+    const N64: &str = "platform: n64\n";
+
+    #[test]
+    fn test_defaults() {
+        let opts = try_options(N64).unwrap();
+
+        assert_eq!(opts.compiler, Compiler::Ido);
+        assert_eq!(opts.endianness, Endianness::Big);
+        assert_eq!(opts.asm_path, PathBuf::from("./asm"));
+        assert_eq!(opts.nonmatchings_path, PathBuf::from("./asm/nonmatchings"));
+        assert_eq!(opts.ld_script_path, PathBuf::from("./test.ld"));
+        assert_eq!(opts.subalign, Some(16));
+        assert_eq!(opts.asm_function_macro, "glabel");
+        assert!(opts.ld_bss_is_noload);
+    }
+
+    #[test]
+    fn test_modes() {
+        let mut opts = try_options(N64).unwrap();
+        assert!(opts.is_mode_active("code"));
+
+        opts.set_modes(vec!["data".to_string(), "ld".to_string()])
+            .unwrap();
+        assert!(opts.is_mode_active("ld"));
+        assert!(!opts.is_mode_active("code"));
+
+        let err = opts.set_modes(vec!["cdoe".to_string()]).unwrap_err();
+        assert!(err.to_string().contains("`cdoe`"), "{err}");
+        assert!(opts.is_mode_active("data"));
+    }
+
+    #[test]
+    fn test_platform_defaults() {
+        let opts = try_options("platform: psx\ncompiler: PSYQ\nendianness: big\n").unwrap();
+
+        assert_eq!(opts.endianness, Endianness::Big);
+        assert!(!opts.ld_bss_is_noload);
+        assert_eq!(opts.section_order[0], ".text");
+        assert_eq!(opts.sources["endianness"], OptionSource::User);
+        assert_eq!(
+            opts.sources["ld_bss_is_noload"],
+            OptionSource::Platform(Platform::Psx)
+        );
+        assert_eq!(opts.sources["section_order"], OptionSource::Default);
+        assert_eq!(
+            opts.sources["asm_function_macro"],
+            OptionSource::Compiler(Compiler::Psyq)
+        );
+        assert_eq!(
+            opts.sources["nonmatchings_path"],
+            OptionSource::Derived("asm_path")
+        );
+    }
+
+    #[test]
+    fn test_describe() {
+        let opts = try_options(N64).unwrap();
+        let description = opts.describe().unwrap();
+
+        assert!(description.contains("platform: n64  # set in yaml\n"));
+        assert!(description.contains("endianness: big  # default for platform n64\n"));
+        assert!(description.contains("section_order: [.text, .data, .rodata, .bss]  # default\n"));
+        assert!(description.contains("o_as_suffix: false  # default\n"));
+    }
+
+    #[test]
+    fn test_null_disables_optional_default() {
+        let opts = try_options(&format!("{N64}subalign: null\nld_fill_value: ~\n")).unwrap();
+
+        assert_eq!(opts.subalign, None);
+        assert_eq!(opts.ld_fill_value, None);
+    }
+
+    #[test]
+    fn test_wrong_type() {
+        let err = try_options(&format!("{N64}create_c_files: 3\n")).unwrap_err();
+        assert!(format!("{err:#}").contains("create_c_files"));
+    }
+
+    #[test]
+    fn test_unknown_key() {
+        let err = try_options(&format!("{N64}not_an_option: true\n")).unwrap_err();
+        assert!(err.to_string().contains("not_an_option"));
+    }
 }
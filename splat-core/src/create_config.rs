@@ -0,0 +1,322 @@
+use std::path::Path;
+
+use anyhow::{Context, Result};
+
+use crate::{
+    config::options::Platform,
+    n64::{Cic, EntrypointInfo, N64Header},
+    rom::TargetRom,
+};
+
+/// Where the IPL3 copies the code following it from
+const N64_ENTRYPOINT_ROM: u32 = 0x1000;
+
+/// What could be learned about an N64 ROM to write its starter config
+#[derive(Debug, Clone)]
+pub struct N64RomInfo {
+    pub header: N64Header,
+    pub title: String,
+    pub header_encoding: &'static str,
+    pub cic: Option<Cic>,
+    /// Entrypoint the game actually starts at, after the IPL3 adjustments of its CIC
+    pub entrypoint: u32,
+    pub entrypoint_info: Option<EntrypointInfo>,
+    pub sha1: String,
+    pub rom_size: u32,
+}
+
+impl N64RomInfo {
+    pub fn from_rom(rom: &TargetRom) -> Result<Self> {
+        let header = N64Header::parse(&rom.bytes)?;
+        let header_encoding = header.guess_encoding();
+        let title = header.title(header_encoding)?;
+
+        let cic = Cic::detect(&rom.bytes);
+        let entrypoint = header
+            .entrypoint
+            .wrapping_sub(cic.map_or(0, |cic| cic.entrypoint_offset));
+        let entrypoint_info = rom
+            .bytes
+            .get(N64_ENTRYPOINT_ROM as usize..)
+            .and_then(|code| EntrypointInfo::parse(code, entrypoint));
+
+        let rom_size = u32::try_from(rom.bytes.len())
+            .with_context(|| format!("The ROM is too big: 0x{:X} bytes", rom.bytes.len()))?;
+
+        Ok(Self {
+            header,
+            title,
+            header_encoding,
+            cic,
+            entrypoint,
+            entrypoint_info,
+            sha1: rom.sha1(),
+            rom_size,
+        })
+    }
+
+    pub fn cic_name(&self) -> Option<&'static str> {
+        self.cic.map(|cic| cic.name(self.header.is_pal()))
+    }
+
+    /// Name for the config and the build outputs, derived from the title
+    pub fn basename(&self) -> Option<String> {
+        let basename: String = self
+            .title
+            .to_lowercase()
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+            .collect();
+        let basename = basename.trim_matches('_');
+
+        (!basename.is_empty()).then(|| basename.to_string())
+    }
+
+    /// A config with the options most projects start from and the segments found from the entrypoint
+    pub fn to_yaml(&self, basename: &str, target_path: &Path) -> String {
+        let mut yaml = format!(
+            r#"name: {name} ({country})
+sha1: {sha1}
+options:
+  basename: {basename}
+  target_path: {target_path}
+  elf_path: build/{basename}.elf
+  base_path: .
+  platform: n64
+  compiler: IDO
+
+  # asm_path: asm
+  # src_path: src
+  # build_path: build
+  # create_asm_dependencies: True
+
+  ld_script_path: {basename}.ld
+  ld_dependencies: True
+
+  find_file_boundaries: True
+  header_encoding: {header_encoding}
+
+  o_as_suffix: True
+  use_legacy_include_asm: False
+  mips_abi_float_regs: o32
+
+  asm_function_macro: glabel
+  asm_jtbl_label_macro: jlabel
+  asm_data_macro: dlabel
+
+  # section_order: [".text", ".data", ".rodata", ".bss"]
+  # auto_link_sections: [".data", ".rodata", ".bss"]
+
+  symbol_addrs_path:
+    - symbol_addrs.txt
+  reloc_addrs_path:
+    - reloc_addrs.txt
+
+  # undefined_funcs_auto_path: undefined_funcs_auto.txt
+  # undefined_syms_auto_path: undefined_syms_auto.txt
+
+  extensions_path: tools/splat_ext
+
+  # string_encoding: ASCII
+  # data_string_encoding: ASCII
+  rodata_string_guesser_level: 2
+  data_string_guesser_level: 2
+  # libultra_symbols: True
+  # hardware_regs: True
+  # gfx_ucode: # one of [f3d, f3db, f3dex, f3dexb, f3dex2]
+
+segments:
+  - name: header
+    type: header
+    start: 0x0
+
+  - name: boot
+    type: bin
+    start: 0x{header_size:X}
+"#,
+            name = self.title,
+            country = self.header.country(),
+            sha1 = self.sha1,
+            target_path = target_path.display(),
+            header_encoding = self.header_encoding,
+            header_size = N64Header::SIZE,
+        );
+
+        let main = self.entrypoint_info.as_ref().and_then(|info| {
+            let main_vram = info.main_vram?;
+            let main_rom = (N64_ENTRYPOINT_ROM + info.size).next_multiple_of(0x10);
+            (main_vram >= self.entrypoint && main_rom < self.rom_size)
+                .then_some((info, main_rom, main_vram))
+        });
+        let Some((info, main_rom, main_vram)) = main else {
+            yaml.push_str(&format!(
+                r#"
+  - name: entry
+    type: code
+    start: 0x{entry_rom:X}
+    vram: 0x{entrypoint:X}
+    subsegments:
+      - [0x{entry_rom:X}, asm]
+
+  - [0x{rom_size:X}]
+"#,
+                entry_rom = N64_ENTRYPOINT_ROM,
+                entrypoint = self.entrypoint,
+                rom_size = self.rom_size,
+            ));
+            return yaml;
+        };
+
+        yaml.push_str(&format!(
+            r#"
+  - name: entry
+    type: code
+    start: 0x{entry_rom:X}
+    vram: 0x{entrypoint:X}
+    subsegments:
+      - [0x{entry_rom:X}, hasm]
+
+  - name: main
+    type: code
+    start: 0x{main_rom:X}
+    vram: 0x{main_vram:X}
+    follows_vram: entry
+"#,
+            entry_rom = N64_ENTRYPOINT_ROM,
+            entrypoint = self.entrypoint,
+        ));
+
+        let bss = info
+            .bss_start
+            .zip(info.bss_size)
+            .filter(|(bss_start, _)| *bss_start >= main_vram);
+        let main_end = match bss {
+            Some((bss_start, bss_size)) => {
+                yaml.push_str(&format!(
+                    r#"    bss_size: 0x{bss_size:X}
+    subsegments:
+      - [0x{main_rom:X}, asm]
+      - {{ type: bss, vram: 0x{bss_start:X} }}
+"#
+                ));
+                Some(main_rom + (bss_start - main_vram))
+            }
+            None => {
+                yaml.push_str(&format!(
+                    r#"    # bss_size: Please fill out this value when you figure out the bss size
+    subsegments:
+      - [0x{main_rom:X}, asm]
+"#
+                ));
+                None
+            }
+        };
+
+        if let Some(main_end) = main_end.filter(|end| *end < self.rom_size) {
+            yaml.push_str(&format!(
+                r#"
+  - type: bin
+    start: 0x{main_end:X}
+    follows_vram: main
+"#
+            ));
+        }
+        yaml.push_str(&format!("\n  - [0x{:X}]\n", self.rom_size));
+
+        yaml
+    }
+}
+
+/// Reads the N64 ROM at `rom_path` and generates its starter config, returned along with the basename it uses
+pub fn create_n64_config(rom_path: &Path) -> Result<(N64RomInfo, String, String)> {
+    let bytes = std::fs::read(rom_path)
+        .with_context(|| format!("Failed to read the ROM {}", rom_path.display()))?;
+    let rom = TargetRom::new(bytes, &Platform::N64);
+    let info = N64RomInfo::from_rom(&rom)
+        .with_context(|| format!("{} is not a valid N64 ROM", rom_path.display()))?;
+
+    let basename = info
+        .basename()
+        .or_else(|| {
+            rom_path
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().into_owned())
+        })
+        .unwrap_or_else(|| "game".to_string());
+    let yaml = info.to_yaml(&basename, rom_path);
+
+    Ok((info, basename, yaml))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{segments::resolve_segments, yaml::SplatYaml};
+
+    const ENTRYPOINT: [u32; 12] = [
+        0x3C088003, // lui     $t0, 0x8003
+        0x25080000, // addiu   $t0, $t0, 0x0
+        0x3C090000, // lui     $t1, 0x0
+        0x25294000, // addiu   $t1, $t1, 0x4000
+        0x3C1D8004, // lui     $sp, 0x8004
+        0x27BD8000, // addiu   $sp, $sp, -0x8000
+        0x3C0A8000, // lui     $t2, 0x8000
+        0x254A0430, // addiu   $t2, $t2, 0x430
+        0x01400008, // jr      $t2
+        0x00000000, // nop
+        0x00000000, // nop
+        0x00000000, // nop
+    ];
+
+    fn test_rom() -> TargetRom {
+        let mut bytes = vec![0; 0x40000];
+        bytes[..4].copy_from_slice(&[0x80, 0x37, 0x12, 0x40]);
+        bytes[0x8..0xC].copy_from_slice(&0x80000400u32.to_be_bytes());
+        bytes[0x20..0x2B].copy_from_slice(b"SPLAT TEST!");
+        bytes[0x3B..0x3F].copy_from_slice(b"NSPE");
+        for (i, word) in ENTRYPOINT.iter().enumerate() {
+            let offset = 0x1000 + i * 4;
+            bytes[offset..offset + 4].copy_from_slice(&word.to_be_bytes());
+        }
+
+        TargetRom::new(bytes, &Platform::N64)
+    }
+
+    #[test]
+    fn test_n64_config() {
+        let info = N64RomInfo::from_rom(&test_rom()).unwrap();
+        assert_eq!(info.title, "SPLAT TEST!");
+        assert_eq!(info.basename().as_deref(), Some("splat_test"));
+        assert_eq!(info.header.country(), "North America");
+        assert_eq!(info.cic_name(), None);
+        assert_eq!(info.entrypoint, 0x80000400);
+
+        let yaml = info.to_yaml("splat_test", Path::new("baserom.z64"));
+        let splat_yaml: SplatYaml = serde_yaml::from_str(&yaml).unwrap();
+        assert_eq!(splat_yaml.sha1.as_deref(), Some(info.sha1.as_str()));
+
+        let segments = resolve_segments(&splat_yaml).unwrap();
+        let layout: Vec<_> = segments
+            .iter()
+            .map(|s| {
+                let rom = s.rom;
+                (
+                    s.name.as_ref(),
+                    rom.start().inner(),
+                    rom.end().inner(),
+                    s.vram_start.map(|v| v.inner()),
+                )
+            })
+            .collect();
+        assert_eq!(
+            layout,
+            [
+                ("header", 0x0, 0x40, None),
+                ("boot", 0x40, 0x1000, None),
+                ("entry", 0x1000, 0x1030, Some(0x80000400)),
+                ("main", 0x1030, 0x30C00, Some(0x80000430)),
+                ("30C00", 0x30C00, 0x40000, Some(0x80034000)),
+            ]
+        );
+    }
+}
@@ -6,7 +6,18 @@ use wasmtime_wasi::{
 };
 
 pub mod config;
+pub mod create_config;
+pub mod linker;
+pub mod n64;
+pub mod relocs;
+pub mod rom;
 pub mod sections;
+pub mod segments;
+pub mod split;
+pub mod symbols;
+#[cfg(test)]
+mod test_utils;
+pub mod validate;
 
 pub mod yaml;
 
@@ -0,0 +1,272 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{Result, bail};
+use serde_yaml::Value;
+
+use splat_segment_api::segment_trait::SegmentTrait;
+
+use crate::{
+    config::options::SplatOpts,
+    segments::{ResolvedSegment, SegmentTypeImpl, SegmentTypeRegistry},
+    yaml::YamlSegmentArgs,
+};
+
+use super::symbols::{section_cname, segment_cname};
+
+/// Sections of the segments that get their own symbols with `ld_generate_symbol_per_data_segment`
+const DATA_SECTIONS: &[&str] = &[".data", ".sdata", ".rodata", ".rdata"];
+
+/// Types whose object file also gets the `auto_link_sections` their siblings don't provide
+const AUTO_LINKED_TYPES: &[&str] = &["c", "cpp", "asm", "hasm"];
+
+/// Something placed inside the output section of a top level segment
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct LinkerEntry {
+    /// The `section_order` group the entry is placed in, `None` to follow the previous entry
+    pub(crate) section_order: Option<String>,
+    pub(crate) kind: LinkerEntryKind,
+    /// Prefix of the `_START`/`_END` symbols around the entry, like `asm_data_logo_data_s_DATA`
+    pub(crate) data_symbol: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum LinkerEntryKind {
+    /// An input section of an object file, like `build/src/main.c.o(.text)`
+    Object {
+        path: PathBuf,
+        section: String,
+        bss_contains_common: bool,
+    },
+    /// `pad` segments, skipping as many bytes
+    Pad(u32),
+    /// `linker_offset` segments, defining `<name>_OFFSET` at their position
+    Offset(String),
+}
+
+/// The entries of every leaf segment under `segment`, in yaml order
+pub(crate) fn segment_entries(
+    options: &SplatOpts,
+    registry: &SegmentTypeRegistry,
+    segment: &ResolvedSegment,
+) -> Result<Vec<LinkerEntry>> {
+    let mut entries = Vec::new();
+    collect_entries(
+        options,
+        registry,
+        segment,
+        std::slice::from_ref(segment),
+        &mut entries,
+    )?;
+
+    Ok(entries)
+}
+
+fn collect_entries(
+    options: &SplatOpts,
+    registry: &SegmentTypeRegistry,
+    segment: &ResolvedSegment,
+    siblings: &[ResolvedSegment],
+    entries: &mut Vec<LinkerEntry>,
+) -> Result<()> {
+    if !segment.subsegments().is_empty() {
+        for subsegment in segment.subsegments() {
+            collect_entries(
+                options,
+                registry,
+                subsegment,
+                segment.subsegments(),
+                entries,
+            )?;
+        }
+        return Ok(());
+    }
+
+    let segment_type = segment.segment_type();
+    let yaml = segment.yaml();
+    match segment_type.as_ref() {
+        "pad" => {
+            let size = segment.rom().map_or(0, |rom| rom.size().inner());
+            entries.push(LinkerEntry {
+                section_order: yaml.linker_section_order.clone(),
+                kind: LinkerEntryKind::Pad(size),
+                data_symbol: None,
+            });
+            return Ok(());
+        }
+        "linker_offset" => {
+            entries.push(LinkerEntry {
+                section_order: yaml.linker_section_order.clone(),
+                kind: LinkerEntryKind::Offset(segment.name().to_string()),
+                data_symbol: None,
+            });
+            return Ok(());
+        }
+        _ => {}
+    }
+
+    let native = match registry.lookup(&segment_type)? {
+        SegmentTypeImpl::Native(native) => native,
+        // TODO: ask the plugin once the segment interface exposes linker entries
+        SegmentTypeImpl::Plugin(path) => bail!(
+            "Segment `{}` uses the extension {}, but linker entries of extensions are not supported yet",
+            segment.name(),
+            path.display()
+        ),
+    };
+    let Some(own_section) = native.section_order.first() else {
+        return Ok(());
+    };
+
+    let Some((path, section)) = object_section(options, segment, siblings, own_section)? else {
+        return Ok(());
+    };
+    let bss_contains_common = segment.bss_contains_common(options);
+    let data_symbol = (options.ld_generate_symbol_per_data_segment
+        && DATA_SECTIONS.contains(own_section))
+    .then(|| data_symbol(options, &path, own_section));
+    entries.push(LinkerEntry {
+        section_order: Some(
+            yaml.linker_section_order
+                .clone()
+                .unwrap_or_else(|| own_section.to_string()),
+        ),
+        kind: LinkerEntryKind::Object {
+            path: path.clone(),
+            section: yaml.linker_section.clone().unwrap_or(section),
+            bss_contains_common,
+        },
+        data_symbol,
+    });
+
+    if !AUTO_LINKED_TYPES.contains(&segment_type.as_ref()) {
+        return Ok(());
+    }
+    for auto_section in &options.auto_link_sections {
+        // An explicit `.data` sibling of the same name already links that section
+        let has_sibling = siblings.iter().any(|sibling| {
+            sibling.name() == segment.name() && sibling.segment_type().as_ref() == auto_section
+        });
+        if has_sibling || auto_section == own_section {
+            continue;
+        }
+
+        entries.push(LinkerEntry {
+            section_order: Some(auto_section.clone()),
+            kind: LinkerEntryKind::Object {
+                path: path.clone(),
+                section: auto_section.clone(),
+                bss_contains_common,
+            },
+            data_symbol: None,
+        });
+    }
+
+    Ok(())
+}
+
+/// The object file of a leaf segment and the section of it to link.
+///
+/// Types starting with a dot, like `.rodata`, link that section of the object of their sibling of the same name
+fn object_section(
+    options: &SplatOpts,
+    segment: &ResolvedSegment,
+    siblings: &[ResolvedSegment],
+    own_section: &str,
+) -> Result<Option<(PathBuf, String)>> {
+    let segment_type = segment.segment_type();
+    let name = segment.name();
+
+    if segment_type.as_ref() == "lib" {
+        return Ok(lib_object(options, segment));
+    }
+
+    let source = match segment_type.strip_prefix('.') {
+        Some(_) => {
+            let sibling = siblings.iter().find(|sibling| {
+                sibling.name() == name
+                    && AUTO_LINKED_TYPES.contains(&sibling.segment_type().as_ref())
+            });
+            let Some(sibling) = sibling else {
+                bail!(
+                    "Segment `{name}` of type `{segment_type}` needs a c or asm sibling named `{name}` to link its section from"
+                );
+            };
+            out_path(options, sibling)
+        }
+        None => out_path(options, segment),
+    };
+
+    Ok(source.map(|source| (object_path(options, &source), own_section.to_string())))
+}
+
+/// The file a leaf segment is split to, which the build turns into the object file the linker script references
+pub(crate) fn out_path(options: &SplatOpts, segment: &ResolvedSegment) -> Option<PathBuf> {
+    let segment_type = segment.segment_type();
+    let name = segment.name();
+    let dir = segment.dir();
+
+    let path = match segment_type.as_ref() {
+        "hasm" if options.hasm_in_src_path => options.src_path.join(dir).join(format!("{name}.s")),
+        "asm" | "hasm" | "header" => options.asm_path.join(dir).join(format!("{name}.s")),
+        "c" | "cpp" => options
+            .src_path
+            .join(dir)
+            .join(format!("{name}.{segment_type}")),
+        "data" | "sdata" | "rodata" | "rdata" | "bss" | "sbss" => options
+            .data_path
+            .join(dir)
+            .join(format!("{name}.{segment_type}.s")),
+        // The `.incbin` wrapper, not the extracted `.bin` itself
+        "bin" | "textbin" | "databin" | "rodatabin" => {
+            options.data_path.join(dir).join(format!("{name}.s"))
+        }
+        _ => return None,
+    };
+
+    Some(path)
+}
+
+/// `build/src/main.c.o` for `src/main.c`, or `build/src/main.o` with `use_o_as_suffix`
+pub(crate) fn object_path(options: &SplatOpts, path: &Path) -> PathBuf {
+    let object = base_relative(options, &options.build_path).join(base_relative(options, path));
+
+    if options.use_o_as_suffix {
+        object.with_extension("o")
+    } else {
+        let mut object = object.into_os_string();
+        object.push(".o");
+        object.into()
+    }
+}
+
+/// `asm_data_logo_data_s_DATA` for the `.data` of `build/asm/data/logo.data.s.o`, unique to the file and section
+fn data_symbol(options: &SplatOpts, object: &Path, section: &str) -> String {
+    let build_path = base_relative(options, &options.build_path);
+    let source = object.strip_prefix(build_path).unwrap_or(object);
+
+    segment_cname(&source.with_extension("").to_string_lossy()) + &section_cname(section)
+}
+
+/// `[0x1000, lib, libultra, osSendMesg, .text]` links `lib/libultra.a:osSendMesg.o(.text)`
+fn lib_object(options: &SplatOpts, segment: &ResolvedSegment) -> Option<(PathBuf, String)> {
+    let Some(YamlSegmentArgs::List(args)) = &segment.yaml().args else {
+        return None;
+    };
+    let Some(Value::String(object)) = args.first() else {
+        return None;
+    };
+    let section = match args.get(1) {
+        Some(Value::String(section)) => section.clone(),
+        _ => ".text".to_string(),
+    };
+
+    let archive =
+        base_relative(options, &options.lib_path).join(format!("{}.a:{object}.o", segment.name()));
+
+    Some((archive, section))
+}
+
+/// Paths in the linker script are relative to `base_path`, where the linker runs from
+pub(crate) fn base_relative<'p>(options: &SplatOpts, path: &'p Path) -> &'p Path {
+    path.strip_prefix(&options.base_path).unwrap_or(path)
+}
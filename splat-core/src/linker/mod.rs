@@ -0,0 +1,554 @@
+mod entries;
+mod symbols;
+mod vram;
+mod writer;
+
+use std::{
+    collections::{BTreeSet, HashSet},
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result, bail};
+
+use splat_segment_api::segment_trait::SegmentTrait;
+
+use crate::{
+    config::options::SplatOpts,
+    segments::{ResolvedSegment, SegmentTypeRegistry, VramClassGraph},
+};
+
+pub(crate) use entries::object_path;
+use entries::{base_relative, segment_entries};
+use symbols::segment_cname;
+use vram::{class_vram, emission_order, segment_vram};
+use writer::LinkerWriter;
+
+/// A generated linker script, along with the symbols it defines
+#[derive(Debug, Clone)]
+pub struct LinkerScript {
+    pub script: String,
+    header_symbols: BTreeSet<String>,
+    /// Object files linked by the script, in order
+    dependencies: Vec<PathBuf>,
+    /// With `ld_partial_linking`, the script of every segment
+    pub partial_scripts: Vec<PartialScript>,
+}
+
+/// The script partially linking the objects of a single segment
+#[derive(Debug, Clone)]
+pub struct PartialScript {
+    /// Where the script goes, in `ld_partial_scripts_path`
+    pub path: PathBuf,
+    /// The object the script produces, in `ld_partial_build_segments_path`
+    pub object: PathBuf,
+    pub script: LinkerScript,
+}
+
+impl LinkerScript {
+    /// A C header declaring every symbol of the script, so code can reference segment bounds
+    pub fn symbol_header(&self) -> String {
+        let mut header = String::from(
+            "#ifndef _HEADER_SYMBOLS_H_\n#define _HEADER_SYMBOLS_H_\n\n#include \"common.h\"\n\n",
+        );
+        for symbol in &self.header_symbols {
+            header.push_str(&format!("extern u8 {symbol}[];\n"));
+        }
+        header.push_str("\n#endif\n");
+
+        header
+    }
+
+    /// A Make rule making `target` depend on every object the script links, along with an empty rule for each so
+    /// deleted objects don't break the build. With `include`, the dependency files of the objects are included too
+    pub fn dependency_file(&self, target: &Path, include: bool) -> String {
+        let mut output = format!("{}:", target.display());
+        for object in &self.dependencies {
+            output.push_str(&format!(" \\\n    {}", object.display()));
+        }
+        output.push('\n');
+
+        for object in &self.dependencies {
+            output.push_str(&format!("{}:\n", object.display()));
+        }
+        if include {
+            for object in &self.dependencies {
+                output.push_str(&format!(
+                    "-include {}\n",
+                    object.with_extension("d").display()
+                ));
+            }
+        }
+
+        output
+    }
+}
+
+/// Generates the GNU ld script placing every segment at its ROM and VRAM address.
+///
+/// Each vram_class gets a `<class>_CLASS_VRAM` symbol before its first segment, and a `<class>_CLASS_VRAM_END` symbol
+/// after its last one, holding the highest VRAM end of the segments in it.
+///
+/// With `ld_partial_linking`, each segment gets its own script combining its objects into
+/// `<ld_partial_build_segments_path>/<segment>.o`, which the main script then places
+pub fn linker_script(
+    options: &SplatOpts,
+    registry: &SegmentTypeRegistry,
+    vram_classes: &VramClassGraph,
+    segments: &[ResolvedSegment],
+) -> Result<LinkerScript> {
+    let partial_paths = match (
+        options.ld_partial_linking,
+        &options.ld_partial_scripts_path,
+        &options.ld_partial_build_segments_path,
+    ) {
+        (false, _, _) => None,
+        (true, Some(scripts_path), Some(build_segments_path)) => {
+            Some((scripts_path, build_segments_path))
+        }
+        (true, _, _) => bail!(
+            "ld_partial_linking requires both ld_partial_scripts_path and ld_partial_build_segments_path to be set"
+        ),
+    };
+
+    let mut writer = LinkerWriter::new(options);
+    let mut partial_scripts = Vec::new();
+    let mut written_classes = HashSet::new();
+    let order = emission_order(options, vram_classes, segments);
+    for (position, &i) in order.iter().enumerate() {
+        let segment = &segments[i];
+        let class = segment.vram_class();
+
+        if let Some(class) = class.as_deref().and_then(|name| vram_classes.get(name))
+            && written_classes.insert(class.name.as_str())
+        {
+            writer.write_class_vram(class, class_vram(options, class, segments));
+        }
+
+        let entries = segment_entries(options, registry, segment)?;
+        let vram = segment_vram(options, segment);
+        match partial_paths {
+            Some((scripts_path, build_segments_path)) => {
+                let seg_name = segment_cname(&segment.name());
+                let mut partial = LinkerWriter::new_partial(options);
+                partial.add_partial(segment, &entries);
+                let object =
+                    base_relative(options, build_segments_path).join(format!("{seg_name}.o"));
+
+                writer.add_referenced_partial(segment, &entries, vram.as_deref(), &object);
+                partial_scripts.push(PartialScript {
+                    path: scripts_path.join(format!("{seg_name}.ld")),
+                    object,
+                    script: partial.finish(),
+                });
+            }
+            None => writer.add(segment, &entries, vram.as_deref()),
+        }
+
+        let Some(class) = class else {
+            continue;
+        };
+        let in_class = |&j: &usize| segments[j].vram_class().as_ref() == Some(&class);
+        if !order[position + 1..].iter().any(in_class) {
+            let seg_names: Vec<_> = order[..=position]
+                .iter()
+                .filter(|j| in_class(j))
+                .map(|&j| segment_cname(&segments[j].name()))
+                .collect();
+            writer.write_max_vram_end(
+                &format!("{}_CLASS_VRAM_END", segment_cname(&class)),
+                &seg_names,
+            );
+        }
+    }
+
+    // Classes without segments still get their symbol, for the code referencing them
+    for class in vram_classes.dependency_order() {
+        if written_classes.insert(class.name.as_str()) {
+            writer.write_class_vram(class, class_vram(options, class, segments));
+        }
+    }
+
+    let mut script = writer.finish();
+    script.partial_scripts = partial_scripts;
+    Ok(script)
+}
+
+/// Writes the linker script to `ld_script_path`, its symbol header to `ld_symbol_header_path` if set, and the
+/// partial scripts to `ld_partial_scripts_path`.
+///
+/// With `ld_dependencies`, each script also gets a `.d` file next to it listing the objects it links
+pub fn write_linker_script(
+    options: &SplatOpts,
+    registry: &SegmentTypeRegistry,
+    vram_classes: &VramClassGraph,
+    segments: &[ResolvedSegment],
+) -> Result<()> {
+    let elf_path = match (options.ld_dependencies, &options.elf_path) {
+        (false, _) => None,
+        (true, Some(elf_path)) => Some(base_relative(options, elf_path)),
+        (true, None) => bail!("ld_dependencies requires elf_path to be set"),
+    };
+
+    let script = linker_script(options, registry, vram_classes, segments)?;
+    write_if_different(&options.ld_script_path, &script.script)?;
+    if let Some(elf_path) = elf_path {
+        write_if_different(
+            &options.ld_script_path.with_extension("d"),
+            &script.dependency_file(elf_path, options.ld_dependencies_include),
+        )?;
+    }
+
+    for partial in &script.partial_scripts {
+        write_if_different(&partial.path, &partial.script.script)?;
+        if elf_path.is_some() {
+            write_if_different(
+                &partial.path.with_extension("d"),
+                &partial
+                    .script
+                    .dependency_file(&partial.object, options.ld_dependencies_include),
+            )?;
+        }
+    }
+
+    if let Some(header_path) = &options.ld_symbol_header_path {
+        write_if_different(header_path, &script.symbol_header())?;
+    }
+
+    Ok(())
+}
+
+/// Leaves the file untouched when its contents wouldn't change, so builds don't relink for nothing
+pub(crate) fn write_if_different(path: &Path, contents: &str) -> Result<()> {
+    if fs::read_to_string(path).is_ok_and(|existing| existing == contents) {
+        return Ok(());
+    }
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, contents).with_context(|| format!("Failed to write {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{segments::resolve_segments, yaml::SplatYaml};
+
+    use super::*;
+
+    fn generate(yaml: &str) -> Result<LinkerScript> {
+        let splat_yaml: SplatYaml = serde_yaml::from_str(yaml).unwrap();
+        let registry = SegmentTypeRegistry::new(&splat_yaml.options);
+        let vram_classes =
+            VramClassGraph::new(splat_yaml.vram_classes.as_deref().unwrap_or_default()).unwrap();
+        let segments = resolve_segments(&splat_yaml).unwrap();
+
+        linker_script(&splat_yaml.options, &registry, &vram_classes, &segments)
+    }
+
+    fn script(yaml: &str) -> String {
+        generate(yaml).unwrap().script
+    }
+
+    const YAML: &str = "
+name: test
+options:
+  basename: test
+  base_path: .
+  target_path: test.z64
+  platform: n64
+  ld_sections_allowlist: [.mdebug]
+segments:
+  - [0x0, header, header]
+  - name: main
+    type: code
+    start: 0x1000
+    vram: 0x80000400
+    align: 16
+    subsegments:
+      - [0x1000, c, main]
+      - [0x1400, asm, os/thread]
+      - [0x1800, .rodata, main]
+      - { type: bss, vram: 0x80000C00, name: main }
+  - [0x1C00, bin, assets]
+  - [0x2000]
+";
+
+    #[test]
+    fn test_linker_script() {
+        let script = script(YAML);
+
+        assert!(
+            script.starts_with("SECTIONS\n{\n    __romPos = 0;\n"),
+            "{script}"
+        );
+        assert!(
+            script.contains(
+                "    header_ROM_START = __romPos;
+    header_VRAM = ADDR(.header);
+    .header : AT(header_ROM_START) SUBALIGN(16)
+    {
+        FILL(0x00000000);
+        header_TEXT_START = .;
+        . = ALIGN(., 16);
+        header_TEXT_END = .;
+        header_TEXT_SIZE = ABSOLUTE(header_TEXT_END - header_TEXT_START);
+        header_DATA_START = .;
+        asm_header_s_DATA_START = .;
+        build/asm/header.s.o(.data);
+        asm_header_s_DATA_END = .;
+"
+            ),
+            "{script}"
+        );
+        assert!(
+            script.contains(
+                "    .main 0x80000400 : AT(main_ROM_START) SUBALIGN(16)
+    {
+        FILL(0x00000000);
+        main_TEXT_START = .;
+        build/src/main.c.o(.text);
+        build/asm/os/thread.s.o(.text);
+        . = ALIGN(., 16);
+        main_TEXT_END = .;
+        main_TEXT_SIZE = ABSOLUTE(main_TEXT_END - main_TEXT_START);
+        main_DATA_START = .;
+        build/src/main.c.o(.data);
+        build/asm/os/thread.s.o(.data);
+"
+            ),
+            "{script}"
+        );
+        // The explicit `.rodata` sibling replaces the automatic one, keeping its place in the yaml
+        assert!(
+            script.contains(
+                "        main_RODATA_START = .;
+        build/asm/os/thread.s.o(.rodata);
+        src_main_c_RODATA_START = .;
+        build/src/main.c.o(.rodata);
+        src_main_c_RODATA_END = .;
+        . = ALIGN(., 16);"
+            ),
+            "{script}"
+        );
+        assert!(
+            script.contains(
+                "    __romPos += SIZEOF(.main);
+    __romPos = ALIGN(__romPos, 16);
+    main_ROM_END = __romPos;
+    main_bss_VRAM = ADDR(.main_bss);
+    .main_bss (NOLOAD) : SUBALIGN(16)
+    {
+        main_BSS_START = .;
+        build/src/main.c.o(.bss);
+        build/asm/os/thread.s.o(.bss);
+        build/asm/data/main.bss.s.o(.bss);
+"
+            ),
+            "{script}"
+        );
+        assert!(
+            script.contains(
+                "        asm_data_assets_s_DATA_START = .;\n        build/asm/data/assets.s.o(.data);\n        asm_data_assets_s_DATA_END = .;\n"
+            ),
+            "{script}"
+        );
+        assert!(
+            script.ends_with(
+                "    .mdebug 0 :
+    {
+        *(.mdebug);
+    }
+
+    /DISCARD/ :
+    {
+        *(*);
+    }
+}
+"
+            ),
+            "{script}"
+        );
+    }
+
+    #[test]
+    fn test_linker_script_options() {
+        let yaml = YAML.replace(
+            "  ld_sections_allowlist: [.mdebug]\n",
+            "  ld_wildcard_sections: true\n  ld_bss_is_noload: false\n  ld_discard_section: false\n  emit_subalign: false\n  ld_fill_value: null\n  o_as_suffix: true\n  ld_sections_denylist: [.comment]\n",
+        );
+        let script = script(&yaml);
+
+        assert!(script.contains("    .main 0x80000400 : AT(main_ROM_START)\n    {\n        main_TEXT_START = .;\n        build/src/main.o(.text*);\n"), "{script}");
+        assert!(!script.contains("NOLOAD"), "{script}");
+        assert!(
+            script
+                .contains("        build/asm/data/main.bss.o(.bss*);\n        main_BSS_END = .;\n"),
+            "{script}"
+        );
+        assert!(
+            script.ends_with("    /DISCARD/ :\n    {\n        *(.comment);\n    }\n}\n"),
+            "{script}"
+        );
+    }
+
+    #[test]
+    fn test_symbol_header() {
+        let yaml = YAML
+            .replace(
+                "  ld_sections_allowlist: [.mdebug]\n",
+                "  segment_symbols_style: makerom\n  ld_generate_symbol_per_data_segment: false\nvram_classes:\n  - { name: overlays, vram: 0x80100000 }\n",
+            )
+            .replace(
+                "  - [0x1C00, bin, assets]\n",
+                "  - { start: 0x1C00, type: bin, name: ovl_a, vram_class: overlays }\n  - { start: 0x1E00, type: bin, name: ovl_b, vram_class: overlays }\n",
+            );
+        let linker_script = generate(&yaml).unwrap();
+
+        assert!(
+            linker_script.script.contains(
+                "    _ovl_bSegmentEnd = .;\n\n    overlays_CLASS_VRAM_END = _ovl_aSegmentEnd;\n    overlays_CLASS_VRAM_END = MAX(overlays_CLASS_VRAM_END, _ovl_bSegmentEnd);\n"
+            ),
+            "{}",
+            linker_script.script
+        );
+
+        let header = linker_script.symbol_header();
+        assert!(
+            header.starts_with("#ifndef _HEADER_SYMBOLS_H_\n#define _HEADER_SYMBOLS_H_\n\n#include \"common.h\"\n\nextern u8 _headerSegmentBssEnd[];\n"),
+            "{header}"
+        );
+        for symbol in [
+            "_mainSegmentRomStart",
+            "_mainSegmentRomEnd",
+            "_mainSegmentStart",
+            "_mainSegmentEnd",
+            "_mainSegmentTextStart",
+            "_mainSegmentRoDataSize",
+            "_ovl_aSegmentBssStart",
+            "overlays_CLASS_VRAM_END",
+        ] {
+            assert!(
+                header.contains(&format!("extern u8 {symbol}[];\n")),
+                "{header}"
+            );
+        }
+        assert!(!header.contains("__romPos"), "{header}");
+        assert!(!header.contains("_DATA_START"), "{header}");
+        assert!(header.ends_with("[];\n\n#endif\n"), "{header}");
+    }
+
+    #[test]
+    fn test_vram_classes() {
+        let yaml = YAML
+            .replace(
+                "  ld_sections_allowlist: [.mdebug]\n",
+                "  ld_sort_segments_by_vram_class_dependency: true\nvram_classes:\n  - { name: overlays, vram: 0x80100000 }\n  - { name: effects, vram: 0x80200000, vram_symbol: effects_start, follows_classes: [overlays] }\n",
+            )
+            .replace(
+                "  - [0x1C00, bin, assets]\n",
+                "  - { start: 0x1C00, type: bin, name: effect, vram_class: effects }\n  - { start: 0x1D00, type: bin, name: ovl_a, vram_class: overlays }\n  - { start: 0x1E00, type: bin, name: ovl_b, vram_class: overlays }\n",
+            );
+        let sorted = script(&yaml);
+
+        assert!(
+            sorted.contains(
+                "    overlays_CLASS_VRAM = 0x80100000;\n\n    ovl_a_ROM_START = __romPos;\n"
+            ),
+            "{sorted}"
+        );
+        assert!(
+            sorted.contains(".ovl_b overlays_CLASS_VRAM : AT(ovl_b_ROM_START)"),
+            "{sorted}"
+        );
+        // `effect` comes first in the yaml, but its class starts after the overlays
+        assert!(
+            sorted.contains("    overlays_CLASS_VRAM_END = MAX(overlays_CLASS_VRAM_END, ovl_b_VRAM_END);\n\n    effects_CLASS_VRAM = ovl_a_VRAM_END;\n    effects_CLASS_VRAM = MAX(effects_CLASS_VRAM, ovl_b_VRAM_END);\n\n    effects_start = effects_CLASS_VRAM;\n\n    effect_ROM_START = __romPos;\n"),
+            "{sorted}"
+        );
+        assert!(
+            sorted.contains(".effect effects_CLASS_VRAM : AT(effect_ROM_START)"),
+            "{sorted}"
+        );
+        assert!(
+            sorted.contains("    effects_CLASS_VRAM_END = effect_VRAM_END;\n"),
+            "{sorted}"
+        );
+        assert!(sorted.contains(".main 0x80000400 :"), "{sorted}");
+
+        let unsorted =
+            script(&yaml.replace("  ld_sort_segments_by_vram_class_dependency: true\n", ""));
+        assert!(
+            unsorted.find(".effect ").unwrap() < unsorted.find(".ovl_a ").unwrap(),
+            "{unsorted}"
+        );
+
+        let hardcoded = script(&yaml.replace(
+            "  ld_sort_segments_by_vram_class_dependency: true\n",
+            "  ld_use_symbolic_vram_addresses: false\n",
+        ));
+        assert!(
+            hardcoded.contains("    effects_CLASS_VRAM = 0x80200000;\n"),
+            "{hardcoded}"
+        );
+        assert!(hardcoded.contains(".ovl_a 0x80100000 :"), "{hardcoded}");
+        assert!(hardcoded.contains(".effect 0x80200000 :"), "{hardcoded}");
+    }
+
+    #[test]
+    fn test_partial_linking() {
+        let yaml = YAML.replace(
+            "  ld_sections_allowlist: [.mdebug]\n",
+            "  ld_sections_allowlist: [.mdebug]\n  ld_partial_linking: true\n  ld_partial_scripts_path: linker_scripts/partial\n  ld_partial_build_segments_path: build/segments\n",
+        );
+        let linker_script = generate(&yaml).unwrap();
+
+        let script = &linker_script.script;
+        assert!(
+            script.contains(
+                "        main_TEXT_START = .;\n        build/segments/main.o(.text);\n        . = ALIGN(., 16);\n"
+            ),
+            "{script}"
+        );
+        assert!(
+            script.contains("        main_BSS_START = .;\n        build/segments/main.o(.bss);\n"),
+            "{script}"
+        );
+        assert!(!script.contains("build/src/main.c.o"), "{script}");
+
+        let paths: Vec<_> = linker_script
+            .partial_scripts
+            .iter()
+            .map(|partial| partial.path.clone())
+            .collect();
+        assert_eq!(
+            paths,
+            ["header", "main", "assets"]
+                .map(|name| PathBuf::from(format!("./linker_scripts/partial/{name}.ld")))
+        );
+
+        let main = &linker_script.partial_scripts[1].script.script;
+        assert!(main.starts_with("SECTIONS\n{\n    .text :\n    {\n        build/src/main.c.o(.text);\n        build/asm/os/thread.s.o(.text);\n    }\n\n    .data :\n"), "{main}");
+        assert!(!main.contains("__romPos"), "{main}");
+        assert!(main.contains("    .mdebug :\n"), "{main}");
+
+        assert_eq!(
+            linker_script.dependency_file(Path::new("build/test.elf"), false),
+            "build/test.elf: \\\n    build/segments/header.o \\\n    build/segments/main.o \\\n    build/segments/assets.o\nbuild/segments/header.o:\nbuild/segments/main.o:\nbuild/segments/assets.o:\n"
+        );
+        let partial = &linker_script.partial_scripts[1];
+        assert_eq!(
+            partial.script.dependency_file(&partial.object, true),
+            "build/segments/main.o: \\\n    build/src/main.c.o \\\n    build/asm/os/thread.s.o \\\n    build/asm/data/main.bss.s.o\n\
+             build/src/main.c.o:\nbuild/asm/os/thread.s.o:\nbuild/asm/data/main.bss.s.o:\n\
+             -include build/src/main.c.d\n-include build/asm/os/thread.s.d\n-include build/asm/data/main.bss.s.d\n"
+        );
+
+        let yaml = yaml.replace("  ld_partial_build_segments_path: build/segments\n", "");
+        let err = generate(&yaml).unwrap_err();
+        assert!(
+            err.to_string().contains("ld_partial_build_segments_path"),
+            "{err}"
+        );
+    }
+}
@@ -0,0 +1,115 @@
+use crate::config::options::SegmentSymbolsStyle;
+
+/// Names of the linker symbols bounding segments and their sections, following `segment_symbols_style`.
+///
+/// `seg_name` is the C identifier of the segment from `segment_cname`
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct SymbolNames {
+    style: SegmentSymbolsStyle,
+}
+
+impl SymbolNames {
+    pub(crate) fn new(style: SegmentSymbolsStyle) -> Self {
+        Self { style }
+    }
+
+    pub(crate) fn rom_start(&self, seg_name: &str) -> String {
+        match self.style {
+            SegmentSymbolsStyle::Splat => format!("{seg_name}_ROM_START"),
+            SegmentSymbolsStyle::Makerom => format!("_{seg_name}SegmentRomStart"),
+        }
+    }
+
+    pub(crate) fn rom_end(&self, seg_name: &str) -> String {
+        match self.style {
+            SegmentSymbolsStyle::Splat => format!("{seg_name}_ROM_END"),
+            SegmentSymbolsStyle::Makerom => format!("_{seg_name}SegmentRomEnd"),
+        }
+    }
+
+    pub(crate) fn vram_start(&self, seg_name: &str) -> String {
+        match self.style {
+            SegmentSymbolsStyle::Splat => format!("{seg_name}_VRAM"),
+            SegmentSymbolsStyle::Makerom => format!("_{seg_name}SegmentStart"),
+        }
+    }
+
+    pub(crate) fn vram_end(&self, seg_name: &str) -> String {
+        match self.style {
+            SegmentSymbolsStyle::Splat => format!("{seg_name}_VRAM_END"),
+            SegmentSymbolsStyle::Makerom => format!("_{seg_name}SegmentEnd"),
+        }
+    }
+
+    pub(crate) fn section_start(&self, seg_name: &str, section: &str) -> String {
+        self.section_symbol(seg_name, section, "START", "Start")
+    }
+
+    pub(crate) fn section_end(&self, seg_name: &str, section: &str) -> String {
+        self.section_symbol(seg_name, section, "END", "End")
+    }
+
+    pub(crate) fn section_size(&self, seg_name: &str, section: &str) -> String {
+        self.section_symbol(seg_name, section, "SIZE", "Size")
+    }
+
+    /// `main_TEXT_START`, or `_mainSegmentTextStart` with the makerom style
+    fn section_symbol(&self, seg_name: &str, section: &str, splat: &str, makerom: &str) -> String {
+        match self.style {
+            SegmentSymbolsStyle::Splat => format!("{seg_name}{}_{splat}", section_cname(section)),
+            SegmentSymbolsStyle::Makerom => {
+                let section = section.trim_start_matches('.');
+                let section = match section {
+                    "rodata" => "RoData".to_string(),
+                    _ => {
+                        let mut chars = section.chars();
+                        chars.next().map_or_else(String::new, |first| {
+                            first.to_ascii_uppercase().to_string() + chars.as_str()
+                        })
+                    }
+                };
+                format!("_{seg_name}Segment{}{makerom}", segment_cname(&section))
+            }
+        }
+    }
+}
+
+/// A name as a C identifier, used as the prefix of the linker symbols of segments
+pub(crate) fn segment_cname(name: &str) -> String {
+    let mut cname: String = name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    if cname.starts_with(|c: char| c.is_ascii_digit()) {
+        cname.insert(0, '_');
+    }
+
+    cname
+}
+
+/// `_TEXT` for `.text`, as in `main_TEXT_START`
+pub(crate) fn section_cname(section: &str) -> String {
+    segment_cname(&section.to_uppercase())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_symbol_names() {
+        let splat = SymbolNames::new(SegmentSymbolsStyle::Splat);
+        assert_eq!(splat.rom_start(&segment_cname("1A2B0")), "_1A2B0_ROM_START");
+        assert_eq!(splat.vram_end("main"), "main_VRAM_END");
+        assert_eq!(splat.section_size("main", ".rodata"), "main_RODATA_SIZE");
+
+        let makerom = SymbolNames::new(SegmentSymbolsStyle::Makerom);
+        assert_eq!(makerom.rom_end("boot"), "_bootSegmentRomEnd");
+        assert_eq!(makerom.vram_start("boot"), "_bootSegmentStart");
+        assert_eq!(
+            makerom.section_start("boot", ".rodata"),
+            "_bootSegmentRoDataStart"
+        );
+        assert_eq!(makerom.section_end("boot", ".bss"), "_bootSegmentBssEnd");
+    }
+}
@@ -0,0 +1,134 @@
+use splat_segment_api::segment_trait::SegmentTrait;
+
+use crate::{
+    config::options::SplatOpts,
+    segments::{ResolvedSegment, VramClassGraph},
+    yaml::VramClass,
+};
+
+use super::symbols::{SymbolNames, segment_cname};
+
+/// Where a vram_class starts in the linker script
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum ClassVram {
+    /// A hardcoded address or the `vram_symbol` of the class
+    Expression(String),
+    /// Right after the furthest of these segments, from the classes in `follows_classes`
+    AfterSegments(Vec<String>),
+}
+
+/// `<class>_CLASS_VRAM`, the start of the segments of the class
+pub(crate) fn class_vram_symbol(class: &str) -> String {
+    format!("{}_CLASS_VRAM", segment_cname(class))
+}
+
+/// The start of `class`, from `follows_classes` or `vram_symbol` with `ld_use_symbolic_vram_addresses`
+pub(crate) fn class_vram(
+    options: &SplatOpts,
+    class: &VramClass,
+    segments: &[ResolvedSegment],
+) -> ClassVram {
+    let hardcoded = ClassVram::Expression(format!("0x{:X}", class.vram));
+    if !options.ld_use_symbolic_vram_addresses {
+        return hardcoded;
+    }
+
+    if !class.follows_classes.is_empty() {
+        let followed: Vec<_> = segments
+            .iter()
+            .filter(|segment| {
+                segment
+                    .vram_class()
+                    .is_some_and(|name| class.follows_classes.iter().any(|f| **f == *name))
+            })
+            .map(|segment| segment_cname(&segment.name()))
+            .collect();
+        if !followed.is_empty() {
+            return ClassVram::AfterSegments(followed);
+        }
+    }
+
+    match &class.vram_symbol {
+        Some(vram_symbol) => ClassVram::Expression(vram_symbol.clone()),
+        None => hardcoded,
+    }
+}
+
+/// The address of the output section of a top level segment.
+///
+/// With `ld_use_symbolic_vram_addresses`, segments without a `vram` of their own start at their `vram_symbol`, the end
+/// of the segment in their `follows_vram` or the start of their vram_class, so they move along with what they follow
+pub(crate) fn segment_vram(options: &SplatOpts, segment: &ResolvedSegment) -> Option<String> {
+    let yaml = segment.yaml();
+    let hardcoded = segment
+        .vram_start()
+        .map(|vram| format!("0x{:X}", vram.inner()));
+    if !options.ld_use_symbolic_vram_addresses || yaml.vram.is_some() {
+        return hardcoded;
+    }
+
+    if let Some(vram_symbol) = &yaml.vram_symbol {
+        return Some(vram_symbol.clone());
+    }
+    if let Some(follows) = &yaml.follows_vram {
+        let names = SymbolNames::new(options.segment_symbols_style);
+        return Some(names.vram_end(&segment_cname(follows)));
+    }
+    if let Some(class) = segment.vram_class() {
+        return Some(class_vram_symbol(&class));
+    }
+
+    hardcoded
+}
+
+/// Indices of `segments` in the order they're written to the linker script.
+///
+/// That's the yaml order, unless `ld_sort_segments_by_vram_class_dependency` is set: then segments wait until every
+/// segment of the classes their class follows has been written
+pub(crate) fn emission_order(
+    options: &SplatOpts,
+    vram_classes: &VramClassGraph,
+    segments: &[ResolvedSegment],
+) -> Vec<usize> {
+    if !options.ld_sort_segments_by_vram_class_dependency {
+        return (0..segments.len()).collect();
+    }
+
+    let dependencies: Vec<Vec<usize>> = segments
+        .iter()
+        .map(|segment| {
+            let Some(class) = segment
+                .vram_class()
+                .and_then(|name| vram_classes.get(&name))
+            else {
+                return Vec::new();
+            };
+
+            segments
+                .iter()
+                .enumerate()
+                .filter(|(_, other)| {
+                    other
+                        .vram_class()
+                        .is_some_and(|name| class.follows_classes.iter().any(|f| **f == *name))
+                })
+                .map(|(i, _)| i)
+                .collect()
+        })
+        .collect();
+
+    let mut written = vec![false; segments.len()];
+    let mut order = Vec::with_capacity(segments.len());
+    while order.len() < segments.len() {
+        // The graph of the classes has no cycles, so there's always a segment ready
+        let next = (0..segments.len())
+            .find(|&i| !written[i] && dependencies[i].iter().all(|&dep| written[dep]))
+            .or_else(|| (0..segments.len()).find(|&i| !written[i]))
+            .unwrap();
+
+        written[next] = true;
+        order.push(next);
+    }
+
+    order
+}
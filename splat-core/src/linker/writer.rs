@@ -0,0 +1,430 @@
+use std::{
+    collections::BTreeSet,
+    fmt::Write as _,
+    path::{Path, PathBuf},
+};
+
+use splat_segment_api::segment_trait::SegmentTrait;
+
+use crate::{config::options::SplatOpts, segments::ResolvedSegment, yaml::VramClass};
+
+use super::{
+    LinkerScript,
+    entries::{LinkerEntry, LinkerEntryKind},
+    symbols::{SymbolNames, segment_cname},
+    vram::{ClassVram, class_vram_symbol},
+};
+
+/// Sections put in the `NOLOAD` output section of their segment with `ld_bss_is_noload`
+const NOLOAD_SECTIONS: &[&str] = &[".bss", ".sbss", ".scommon", "COMMON"];
+
+/// Builds a GNU ld script with one output section per top level segment, laid out like the ones of Python splat
+pub(crate) struct LinkerWriter<'o> {
+    options: &'o SplatOpts,
+    names: SymbolNames,
+    buffer: String,
+    indent: usize,
+    /// Symbols defined by the script, declared by the symbol header
+    header_symbols: BTreeSet<String>,
+    /// Object files linked by the script, in order
+    dependencies: Vec<PathBuf>,
+    /// Scripts of `ld_partial_linking` that combine the objects of a single segment, without placing it
+    is_partial: bool,
+}
+
+impl<'o> LinkerWriter<'o> {
+    pub(crate) fn new(options: &'o SplatOpts) -> Self {
+        let mut writer = Self {
+            options,
+            names: SymbolNames::new(options.segment_symbols_style),
+            buffer: String::new(),
+            indent: 0,
+            header_symbols: BTreeSet::new(),
+            dependencies: Vec::new(),
+            is_partial: false,
+        };
+
+        writer.writeln("SECTIONS");
+        writer.begin_block();
+        writer.writeln(&format!("__romPos = {};", options.ld_rom_start));
+        if let Some(gp) = options.gp {
+            writer.writeln(&format!("_gp = 0x{gp:X};"));
+        } else if let Some(gp_expression) = &options.ld_gp_expression {
+            writer.writeln(&format!("_gp = {gp_expression};"));
+        }
+
+        writer
+    }
+
+    pub(crate) fn new_partial(options: &'o SplatOpts) -> Self {
+        let mut writer = Self {
+            options,
+            names: SymbolNames::new(options.segment_symbols_style),
+            buffer: String::new(),
+            indent: 0,
+            header_symbols: BTreeSet::new(),
+            dependencies: Vec::new(),
+            is_partial: true,
+        };
+
+        writer.writeln("SECTIONS");
+        writer.begin_block();
+
+        writer
+    }
+
+    /// Adds one output section per section of the segment to a partial script, named like the section so the
+    /// main script can pick them from the partially linked object
+    pub(crate) fn add_partial(&mut self, segment: &ResolvedSegment, entries: &[LinkerEntry]) {
+        for (section, entries) in group_sections(self.section_order(segment), entries) {
+            if entries.is_empty() {
+                continue;
+            }
+
+            self.writeln(&format!("{section} :"));
+            self.begin_block();
+            for entry in entries {
+                self.write_entry(entry);
+            }
+            self.end_block();
+            self.writeln("");
+        }
+    }
+
+    /// Adds the output section of a top level segment linked from `object`, the result of its partial script
+    pub(crate) fn add_referenced_partial(
+        &mut self,
+        segment: &ResolvedSegment,
+        entries: &[LinkerEntry],
+        vram: Option<&str>,
+        object: &Path,
+    ) {
+        let partial_entries: Vec<_> = group_sections(self.section_order(segment), entries)
+            .into_iter()
+            .filter(|(_, entries)| !entries.is_empty())
+            .map(|(section, _)| LinkerEntry {
+                section_order: Some(section.clone()),
+                kind: LinkerEntryKind::Object {
+                    path: object.to_path_buf(),
+                    section,
+                    bss_contains_common: false,
+                },
+                data_symbol: None,
+            })
+            .collect();
+
+        self.add(segment, &partial_entries, vram);
+    }
+
+    /// Adds the output section of a top level segment at `vram`, with its entries grouped by `section_order`
+    pub(crate) fn add(
+        &mut self,
+        segment: &ResolvedSegment,
+        entries: &[LinkerEntry],
+        vram: Option<&str>,
+    ) {
+        let seg_name = segment_cname(&segment.name());
+        let sections = group_sections(self.section_order(segment), entries);
+
+        let is_noload =
+            |section: &str| self.options.ld_bss_is_noload && NOLOAD_SECTIONS.contains(&section);
+        let (noload, load): (Vec<_>, Vec<_>) = sections
+            .into_iter()
+            .partition(|(section, _)| is_noload(section));
+
+        self.begin_segment(segment, &seg_name, vram);
+        for (section, entries) in &load {
+            self.write_section(segment, &seg_name, section, entries);
+        }
+        self.end_block();
+        self.writeln(&format!("__romPos += SIZEOF(.{seg_name});"));
+
+        let align = segment.yaml().align;
+        if !self.options.segment_end_before_align
+            && let Some(align) = align
+        {
+            self.writeln(&format!("__romPos = ALIGN(__romPos, {align});"));
+        }
+        self.write_symbol(&self.names.rom_end(&seg_name), "__romPos");
+        if self.options.segment_end_before_align
+            && let Some(align) = align
+        {
+            self.writeln(&format!("__romPos = ALIGN(__romPos, {align});"));
+        }
+
+        if !noload.is_empty() {
+            let bss_name = format!("{seg_name}_bss");
+            self.write_symbol(
+                &self.names.vram_start(&bss_name),
+                &format!("ADDR(.{bss_name})"),
+            );
+            let mut line = format!(".{bss_name} (NOLOAD) :");
+            if let Some(subalign) = self.subalign(segment) {
+                write!(line, " SUBALIGN({subalign})").unwrap();
+            }
+            self.writeln(&line);
+            self.begin_block();
+            for (section, entries) in &noload {
+                self.write_section(segment, &seg_name, section, entries);
+            }
+            self.end_block();
+            self.write_symbol(&self.names.vram_end(&bss_name), ".");
+        }
+
+        if self.options.ld_align_segment_vram_end
+            && !self.options.segment_end_before_align
+            && let Some(subalign) = self.subalign(segment)
+        {
+            self.writeln(&format!(". = ALIGN(., {subalign});"));
+        }
+        self.write_symbol(&self.names.vram_end(&seg_name), ".");
+        if self.options.ld_align_segment_vram_end
+            && self.options.segment_end_before_align
+            && let Some(subalign) = self.subalign(segment)
+        {
+            self.writeln(&format!(". = ALIGN(., {subalign});"));
+        }
+        self.writeln("");
+    }
+
+    /// Closes the script with the allowlisted sections and the discarded ones
+    pub(crate) fn finish(mut self) -> LinkerScript {
+        let options = self.options;
+
+        if !options.ld_sections_allowlist.is_empty() {
+            // Partial scripts keep the sections around for the main one to place
+            let address = if self.is_partial { "" } else { " 0" };
+            for section in &options.ld_sections_allowlist {
+                self.writeln(&format!("{section}{address} :"));
+                self.begin_block();
+                self.writeln(&format!("*({section});"));
+                self.end_block();
+            }
+            self.writeln("");
+        }
+
+        if !options.ld_sections_denylist.is_empty() {
+            self.writeln("/DISCARD/ :");
+            self.begin_block();
+            for section in &options.ld_sections_denylist {
+                self.writeln(&format!("*({section});"));
+            }
+            self.end_block();
+        }
+
+        if options.ld_discard_section {
+            self.writeln("/DISCARD/ :");
+            self.begin_block();
+            self.writeln("*(*);");
+            self.end_block();
+        }
+
+        self.end_block();
+        LinkerScript {
+            script: self.buffer,
+            header_symbols: self.header_symbols,
+            dependencies: self.dependencies,
+            partial_scripts: Vec::new(),
+        }
+    }
+
+    fn section_order<'s>(&self, segment: &'s ResolvedSegment) -> &'s [String]
+    where
+        'o: 's,
+    {
+        segment
+            .yaml()
+            .section_order
+            .as_deref()
+            .unwrap_or(&self.options.section_order)
+    }
+
+    fn subalign(&self, segment: &ResolvedSegment) -> Option<u64> {
+        if !self.options.emit_subalign {
+            return None;
+        }
+        segment.yaml().subalign.or(self.options.subalign)
+    }
+
+    fn begin_segment(&mut self, segment: &ResolvedSegment, seg_name: &str, vram: Option<&str>) {
+        if let Some(align) = segment
+            .yaml()
+            .ld_align_segment_start
+            .or(self.options.ld_align_segment_start)
+        {
+            self.write_symbol("__romPos", &format!("ALIGN(__romPos, {align})"));
+            self.writeln(&format!(". = ALIGN(., {align});"));
+        }
+
+        let rom_start = self.names.rom_start(seg_name);
+        self.write_symbol(&rom_start, "__romPos");
+        self.write_symbol(
+            &self.names.vram_start(seg_name),
+            &format!("ADDR(.{seg_name})"),
+        );
+
+        let mut line = format!(".{seg_name}");
+        if let Some(vram) = vram {
+            write!(line, " {vram}").unwrap();
+        }
+        write!(line, " : AT({rom_start})").unwrap();
+        if let Some(subalign) = self.subalign(segment) {
+            write!(line, " SUBALIGN({subalign})").unwrap();
+        }
+        self.writeln(&line);
+        self.begin_block();
+
+        if let Some(fill) = segment.yaml().ld_fill_value.or(self.options.ld_fill_value) {
+            self.writeln(&format!("FILL(0x{fill:08X});"));
+        }
+    }
+
+    /// The `_START`/`_END`/`_SIZE` symbols of a section around its entries
+    fn write_section(
+        &mut self,
+        segment: &ResolvedSegment,
+        seg_name: &str,
+        section: &str,
+        entries: &[&LinkerEntry],
+    ) {
+        let start = self.names.section_start(seg_name, section);
+        let end = self.names.section_end(seg_name, section);
+
+        self.write_symbol(&start, ".");
+        for entry in entries {
+            self.write_entry(entry);
+        }
+        if self.options.ld_align_section_vram_end
+            && let Some(subalign) = self.subalign(segment)
+        {
+            self.writeln(&format!(". = ALIGN(., {subalign});"));
+        }
+        self.write_symbol(&end, ".");
+        self.write_symbol(
+            &self.names.section_size(seg_name, section),
+            &format!("ABSOLUTE({end} - {start})"),
+        );
+    }
+
+    fn write_entry(&mut self, entry: &LinkerEntry) {
+        if let Some(symbol) = &entry.data_symbol {
+            self.write_symbol(&format!("{symbol}_START"), ".");
+        }
+        match &entry.kind {
+            LinkerEntryKind::Object {
+                path,
+                section,
+                bss_contains_common,
+            } => {
+                if !self.dependencies.contains(path) {
+                    self.dependencies.push(path.clone());
+                }
+
+                let path = path.display();
+                let is_bss = matches!(section.as_str(), ".bss" | ".sbss");
+                if is_bss && *bss_contains_common {
+                    self.writeln(&format!("{path}({section} COMMON .scommon);"));
+                } else if self.options.ld_wildcard_sections {
+                    self.writeln(&format!("{path}({section}*);"));
+                } else {
+                    self.writeln(&format!("{path}({section});"));
+                }
+            }
+            LinkerEntryKind::Pad(size) => self.writeln(&format!(". += 0x{size:X};")),
+            LinkerEntryKind::Offset(name) => {
+                self.write_symbol(&format!("{}_OFFSET", segment_cname(name)), ".")
+            }
+        }
+        if let Some(symbol) = &entry.data_symbol {
+            self.write_symbol(&format!("{symbol}_END"), ".");
+        }
+    }
+
+    /// Defines `<class>_CLASS_VRAM`, along with the `vram_symbol` of the class when it follows other classes
+    pub(crate) fn write_class_vram(&mut self, class: &VramClass, vram: ClassVram) {
+        let symbol = class_vram_symbol(&class.name);
+        match vram {
+            ClassVram::Expression(expression) => {
+                self.write_symbol(&symbol, &expression);
+                self.writeln("");
+            }
+            ClassVram::AfterSegments(seg_names) => {
+                self.write_max_vram_end(&symbol, &seg_names);
+                if let Some(vram_symbol) = &class.vram_symbol {
+                    self.write_symbol(vram_symbol, &symbol);
+                    self.writeln("");
+                }
+            }
+        }
+    }
+
+    /// Makes `symbol` the highest of the VRAM ends of `seg_names`
+    pub(crate) fn write_max_vram_end(&mut self, symbol: &str, seg_names: &[String]) {
+        for (i, seg_name) in seg_names.iter().enumerate() {
+            let vram_end = self.names.vram_end(seg_name);
+            if i == 0 {
+                self.write_symbol(symbol, &vram_end);
+            } else {
+                self.write_symbol(symbol, &format!("MAX({symbol}, {vram_end})"));
+            }
+        }
+        self.writeln("");
+    }
+
+    fn write_symbol(&mut self, symbol: &str, value: &str) {
+        self.writeln(&format!("{symbol} = {value};"));
+        if symbol != "__romPos" {
+            self.header_symbols.insert(symbol.to_string());
+        }
+    }
+
+    fn begin_block(&mut self) {
+        self.writeln("{");
+        self.indent += 1;
+    }
+
+    fn end_block(&mut self) {
+        self.indent -= 1;
+        self.writeln("}");
+    }
+
+    fn writeln(&mut self, line: &str) {
+        if !line.is_empty() {
+            self.buffer.push_str(&"    ".repeat(self.indent));
+        }
+        self.buffer.push_str(line);
+        self.buffer.push('\n');
+    }
+}
+
+/// Entries grouped by the section they're placed in, following `section_order`.
+///
+/// Entries without a section of their own, like `pad`, go with the previous one, and sections missing from
+/// `section_order` come after the listed ones
+fn group_sections<'e>(
+    section_order: &[String],
+    entries: &'e [LinkerEntry],
+) -> Vec<(String, Vec<&'e LinkerEntry>)> {
+    let mut sections: Vec<(String, Vec<&LinkerEntry>)> = section_order
+        .iter()
+        .map(|section| (section.clone(), Vec::new()))
+        .collect();
+
+    let mut previous = None;
+    for entry in entries {
+        let section = entry
+            .section_order
+            .clone()
+            .or_else(|| previous.clone())
+            .or_else(|| section_order.first().cloned())
+            .unwrap_or_else(|| ".data".to_string());
+
+        match sections.iter_mut().find(|(name, _)| *name == section) {
+            Some((_, group)) => group.push(entry),
+            None => sections.push((section.clone(), vec![entry])),
+        }
+        previous = Some(section);
+    }
+
+    sections
+}
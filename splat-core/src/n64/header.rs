@@ -0,0 +1,110 @@
+use anyhow::{Context, Result, bail};
+use encoding_rs::Encoding;
+
+/// The header found at the start of every N64 ROM
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct N64Header {
+    pub pi_bsd_config: u32,
+    pub clock_rate: u32,
+    pub entrypoint: u32,
+    pub libultra_version: u32,
+    pub checksum1: u32,
+    pub checksum2: u32,
+    pub unknown1: [u8; 8],
+    pub title: [u8; 20],
+    pub unknown2: [u8; 7],
+    /// Media format, cartridge id and country code
+    pub game_code: [u8; 4],
+    pub revision: u8,
+}
+
+fn word(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_be_bytes(bytes[offset..offset + 4].try_into().unwrap())
+}
+
+impl N64Header {
+    pub const SIZE: usize = 0x40;
+
+    pub fn parse(bytes: &[u8]) -> Result<Self> {
+        let Some(bytes) = bytes.get(..Self::SIZE) else {
+            bail!(
+                "The ROM is too small to contain a header: 0x{:X} bytes",
+                bytes.len()
+            );
+        };
+
+        Ok(Self {
+            pi_bsd_config: word(bytes, 0x00),
+            clock_rate: word(bytes, 0x04),
+            entrypoint: word(bytes, 0x08),
+            libultra_version: word(bytes, 0x0C),
+            checksum1: word(bytes, 0x10),
+            checksum2: word(bytes, 0x14),
+            unknown1: bytes[0x18..0x20].try_into().unwrap(),
+            title: bytes[0x20..0x34].try_into().unwrap(),
+            unknown2: bytes[0x34..0x3B].try_into().unwrap(),
+            game_code: bytes[0x3B..0x3F].try_into().unwrap(),
+            revision: bytes[0x3F],
+        })
+    }
+
+    /// Decodes the internal name of the game, without its padding
+    pub fn title(&self, encoding: &str) -> Result<String> {
+        let encoding = Encoding::for_label(encoding.as_bytes())
+            .with_context(|| format!("Unknown header encoding `{encoding}`"))?;
+        let (title, _, had_errors) = encoding.decode(&self.title);
+        if had_errors {
+            bail!(
+                "The ROM title {:02X?} is not valid {}",
+                self.title,
+                encoding.name()
+            );
+        }
+
+        Ok(title.trim_end_matches(['\0', ' ']).to_string())
+    }
+
+    pub fn country_code(&self) -> u8 {
+        self.game_code[3]
+    }
+
+    pub fn country(&self) -> &'static str {
+        match self.country_code() {
+            b'7' => "Beta",
+            b'A' => "Asian (NTSC)",
+            b'B' => "Brazilian",
+            b'C' => "Chinese",
+            b'D' => "German",
+            b'E' => "North America",
+            b'F' => "French",
+            b'G' => "Gateway 64 (NTSC)",
+            b'H' => "Dutch",
+            b'I' => "Italian",
+            b'J' => "Japanese",
+            b'K' => "Korean",
+            b'L' => "Gateway 64 (PAL)",
+            b'N' => "Canadian",
+            b'P' => "European (basic spec.)",
+            b'S' => "Spanish",
+            b'U' => "Australian",
+            b'W' => "Scandinavian",
+            b'X' | b'Y' | b'Z' => "European",
+            _ => "Unknown",
+        }
+    }
+
+    pub fn is_pal(&self) -> bool {
+        matches!(
+            self.country_code(),
+            b'D' | b'F' | b'H' | b'I' | b'L' | b'P' | b'S' | b'U' | b'W' | b'X' | b'Y' | b'Z'
+        )
+    }
+
+    /// Encoding the title is most likely written in
+    pub fn guess_encoding(&self) -> &'static str {
+        match self.country_code() {
+            b'J' => "SHIFT_JIS",
+            _ => "ASCII",
+        }
+    }
+}
@@ -0,0 +1,5 @@
+mod header;
+mod rominfo;
+
+pub use header::N64Header;
+pub use rominfo::{Cic, EntrypointInfo};
@@ -0,0 +1,180 @@
+/// The lockout chip a cartridge was made for, identified from the checksum of its IPL3
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cic {
+    pub ntsc_name: &'static str,
+    pub pal_name: &'static str,
+    /// How far the IPL3 moves the entrypoint written in the header
+    pub entrypoint_offset: u32,
+}
+
+impl Cic {
+    const KNOWN: &[(u32, Cic)] = &[
+        (0x6170A4A1, Cic::new("6101", "7102", 0)),
+        (0x90BB6CB5, Cic::new("6102", "7101", 0)),
+        (0x0B050EE0, Cic::new("6103", "7103", 0x100000)),
+        (0x98BC2C86, Cic::new("6105", "7105", 0)),
+        (0xACC8580A, Cic::new("6106", "7106", 0x200000)),
+        (0x0E018159, Cic::new("8303", "8303", 0)),
+    ];
+
+    const fn new(ntsc_name: &'static str, pal_name: &'static str, entrypoint_offset: u32) -> Self {
+        Self {
+            ntsc_name,
+            pal_name,
+            entrypoint_offset,
+        }
+    }
+
+    /// Looks up the CIC from the IPL3, which lives between the header and 0x1000
+    pub fn detect(rom: &[u8]) -> Option<Self> {
+        let ipl3 = rom.get(0x40..0x1000)?;
+        let crc = crc32fast::hash(ipl3);
+
+        Self::KNOWN
+            .iter()
+            .find(|(known, _)| *known == crc)
+            .map(|(_, cic)| *cic)
+    }
+
+    pub fn name(&self, is_pal: bool) -> &'static str {
+        match is_pal {
+            true => self.pal_name,
+            false => self.ntsc_name,
+        }
+    }
+}
+
+/// What the boot loader at the entrypoint sets up before jumping into the game
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EntrypointInfo {
+    /// Size of the entrypoint function, including the delay slot of its final jump
+    pub size: u32,
+    pub main_vram: Option<u32>,
+    pub bss_start: Option<u32>,
+    pub bss_size: Option<u32>,
+    pub stack_top: Option<u32>,
+}
+
+impl EntrypointInfo {
+    /// Limit on how far to look for the jump into the game
+    const MAX_SIZE: usize = 0x100;
+
+    const REG_SP: u32 = 29;
+
+    /// Follows the `lui`/`addiu` pairs of the entrypoint code until its final `jr` or `j`.
+    ///
+    /// The usual libultra entrypoint clears the bss with the first two addresses it loads, then sets the
+    /// stack pointer and jumps to the game's first function.
+    pub fn parse(code: &[u8], vram: u32) -> Option<Self> {
+        let mut upper: [Option<u32>; 32] = [None; 32];
+        let mut loaded: Vec<(u32, u32)> = Vec::new();
+
+        let code = &code[..code.len().min(Self::MAX_SIZE)];
+        for (i, word) in code.chunks_exact(4).enumerate() {
+            let word = u32::from_be_bytes(word.try_into().unwrap());
+            let opcode = word >> 26;
+            let rs = (word >> 21) & 0x1F;
+            let rt = (word >> 16) & 0x1F;
+            let imm = word & 0xFFFF;
+
+            let main_vram = match opcode {
+                // lui
+                0x0F => {
+                    upper[rt as usize] = Some(imm << 16);
+                    continue;
+                }
+                // addiu, addi, ori
+                0x09 | 0x08 | 0x0D if rs == rt => {
+                    if let Some(upper) = upper[rt as usize].take() {
+                        let value = match opcode {
+                            0x0D => upper | imm,
+                            _ => upper.wrapping_add(imm as i16 as u32),
+                        };
+                        loaded.push((rt, value));
+                    }
+                    continue;
+                }
+                // jr
+                0x00 if word & 0x3F == 0x08 => loaded
+                    .iter()
+                    .rev()
+                    .find(|(reg, _)| *reg == rs)
+                    .map(|(_, value)| *value),
+                // j
+                0x02 => Some((vram & 0xF000_0000) | ((word & 0x03FF_FFFF) << 2)),
+                _ => continue,
+            };
+
+            let stack_top = loaded
+                .iter()
+                .rev()
+                .find(|(reg, _)| *reg == Self::REG_SP)
+                .map(|(_, value)| *value);
+            let mut bss = loaded
+                .iter()
+                .filter(|(reg, value)| *reg != Self::REG_SP && Some(*value) != main_vram)
+                .map(|(_, value)| *value);
+            let (bss_start, bss_size) = match (bss.next(), bss.next()) {
+                (Some(start), Some(size)) => (Some(start), Some(size)),
+                _ => (None, None),
+            };
+
+            return Some(Self {
+                size: (i as u32 + 2) * 4,
+                main_vram,
+                bss_start,
+                bss_size,
+                stack_top,
+            });
+        }
+
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ENTRYPOINT: [u32; 16] = [
+        0x3C088003, // lui     $t0, 0x8003
+        0x25081230, // addiu   $t0, $t0, 0x1230
+        0x3C090001, // lui     $t1, 0x1
+        0x25292340, // addiu   $t1, $t1, 0x2340
+        0x2129FFF8, // addi    $t1, $t1, -0x8
+        0xAD000000, // sw      $zero, 0x0($t0)
+        0xAD000004, // sw      $zero, 0x4($t0)
+        0x21080008, // addi    $t0, $t0, 0x8
+        0x1520FFFB, // bnez    $t1, .clear
+        0x00000000, // nop
+        0x3C1D8004, // lui     $sp, 0x8004
+        0x27BD5000, // addiu   $sp, $sp, 0x5000
+        0x3C0A8000, // lui     $t2, 0x8000
+        0x254A0460, // addiu   $t2, $t2, 0x460
+        0x01400008, // jr      $t2
+        0x00000000, // nop
+    ];
+
+    #[test]
+    fn test_entrypoint() {
+        let code: Vec<u8> = ENTRYPOINT.iter().flat_map(|w| w.to_be_bytes()).collect();
+        let info = EntrypointInfo::parse(&code, 0x80000400).unwrap();
+
+        assert_eq!(
+            info,
+            EntrypointInfo {
+                size: 0x40,
+                main_vram: Some(0x80000460),
+                bss_start: Some(0x80031230),
+                bss_size: Some(0x12340),
+                stack_top: Some(0x80045000),
+            }
+        );
+    }
+
+    #[test]
+    fn test_unknown_cic() {
+        assert_eq!(Cic::detect(&[0; 0x1000]), None);
+        assert_eq!(Cic::detect(&[0; 0x40]), None);
+    }
+}
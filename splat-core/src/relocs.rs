@@ -0,0 +1,199 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result, bail};
+use spimdisasm::relocation::RelocationType;
+
+use crate::symbols::parse_int;
+
+/// A relocation forced on an instruction or word by a reloc_addrs file:
+///
+/// `rom:0x1234 reloc:MIPS_HI16 symbol:foo addend:0x10`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Reloc {
+    pub rom: u32,
+    pub reloc_type: RelocationType,
+    pub symbol: String,
+    pub addend: i64,
+
+    /// Where the relocation was declared, for error messages
+    pub path: PathBuf,
+    pub line: usize,
+}
+
+/// Reads every reloc_addrs file that exists, in order, rejecting ROM addresses relocated more than once
+pub fn load_reloc_addrs(paths: &[PathBuf]) -> Result<Vec<Reloc>> {
+    let mut relocs = Vec::new();
+
+    for path in paths.iter().filter(|path| path.exists()) {
+        let source = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        relocs.extend(parse_reloc_addrs(path, &source)?);
+    }
+    check_duplicates(&relocs)?;
+
+    Ok(relocs)
+}
+
+pub fn parse_reloc_addrs(path: &Path, source: &str) -> Result<Vec<Reloc>> {
+    let mut relocs = Vec::new();
+
+    for (i, line) in source.lines().enumerate() {
+        let line = line.split_once("//").map_or(line, |(line, _)| line).trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        let reloc = parse_line(line, path, i + 1)
+            .with_context(|| format!("{}:{}: `{line}`", path.display(), i + 1))?;
+        relocs.push(reloc);
+    }
+
+    Ok(relocs)
+}
+
+fn parse_line(line: &str, path: &Path, line_number: usize) -> Result<Reloc> {
+    let mut rom = None;
+    let mut reloc_type = None;
+    let mut symbol = None;
+    let mut addend = 0;
+
+    for attribute in line.split_whitespace() {
+        let Some((key, value)) = attribute.split_once(':') else {
+            bail!("Attribute `{attribute}` should be of the form `attribute:value`");
+        };
+
+        match key {
+            "rom" => rom = Some(parse_int(value)?),
+            "reloc" => {
+                reloc_type = Some(
+                    parse_reloc_type(value)
+                        .with_context(|| format!("Unknown relocation type `{value}`"))?,
+                )
+            }
+            "symbol" => symbol = Some(value.to_string()),
+            "addend" => addend = parse_addend(value)?,
+            _ => bail!("Unknown attribute `{key}`"),
+        }
+    }
+
+    Ok(Reloc {
+        rom: rom.context("Missing the required `rom` attribute")?,
+        reloc_type: reloc_type.context("Missing the required `reloc` attribute")?,
+        symbol: symbol.context("Missing the required `symbol` attribute")?,
+        addend,
+        path: path.to_path_buf(),
+        line: line_number,
+    })
+}
+
+/// Accepts the relocation names both with and without their `R_` prefix, like `MIPS_HI16`
+fn parse_reloc_type(name: &str) -> Option<RelocationType> {
+    Some(match name.strip_prefix("R_").unwrap_or(name) {
+        "MIPS_NONE" => RelocationType::R_MIPS_NONE,
+        "MIPS_16" => RelocationType::R_MIPS_16,
+        "MIPS_32" => RelocationType::R_MIPS_32,
+        "MIPS_REL32" => RelocationType::R_MIPS_REL32,
+        "MIPS_26" => RelocationType::R_MIPS_26,
+        "MIPS_HI16" => RelocationType::R_MIPS_HI16,
+        "MIPS_LO16" => RelocationType::R_MIPS_LO16,
+        "MIPS_GPREL16" => RelocationType::R_MIPS_GPREL16,
+        "MIPS_LITERAL" => RelocationType::R_MIPS_LITERAL,
+        "MIPS_GOT16" => RelocationType::R_MIPS_GOT16,
+        "MIPS_PC16" => RelocationType::R_MIPS_PC16,
+        "MIPS_CALL16" => RelocationType::R_MIPS_CALL16,
+        "MIPS_GPREL32" => RelocationType::R_MIPS_GPREL32,
+        "CUSTOM_CONSTANT_HI" => RelocationType::R_CUSTOM_CONSTANT_HI,
+        "CUSTOM_CONSTANT_LO" => RelocationType::R_CUSTOM_CONSTANT_LO,
+        _ => return None,
+    })
+}
+
+fn parse_addend(value: &str) -> Result<i64> {
+    match value.strip_prefix('-') {
+        Some(magnitude) => Ok(-i64::from(parse_int(magnitude)?)),
+        None => Ok(i64::from(parse_int(value)?)),
+    }
+}
+
+fn check_duplicates(relocs: &[Reloc]) -> Result<()> {
+    let mut seen: HashMap<u32, &Reloc> = HashMap::new();
+
+    for reloc in relocs {
+        if let Some(previous) = seen.insert(reloc.rom, reloc) {
+            bail!(
+                "{}:{}: Duplicated relocation for rom:0x{:X}, first declared at {}:{}",
+                reloc.path.display(),
+                reloc.line,
+                reloc.rom,
+                previous.path.display(),
+                previous.line
+            );
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(source: &str) -> Result<Vec<Reloc>> {
+        let relocs = parse_reloc_addrs(Path::new("reloc_addrs.txt"), source)?;
+        check_duplicates(&relocs)?;
+        Ok(relocs)
+    }
+
+    #[test]
+    fn test_reloc_addrs() {
+        let relocs = parse(
+            r#"
+// Wrongly paired %hi/%lo
+rom:0x1234 reloc:MIPS_HI16 symbol:D_80100000 addend:0x10
+rom:0x1240 reloc:R_MIPS_LO16 symbol:D_80100000 addend:-0x10 // fixed up
+rom:0x2000 reloc:MIPS_32 symbol:jtbl_80001234
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(relocs.len(), 3);
+        assert_eq!(relocs[0].rom, 0x1234);
+        assert_eq!(relocs[0].reloc_type, RelocationType::R_MIPS_HI16);
+        assert_eq!(relocs[0].symbol, "D_80100000");
+        assert_eq!(relocs[0].addend, 0x10);
+        assert_eq!(relocs[0].line, 3);
+        assert_eq!(relocs[1].reloc_type, RelocationType::R_MIPS_LO16);
+        assert_eq!(relocs[1].addend, -0x10);
+        assert_eq!(relocs[2].addend, 0);
+    }
+
+    #[test]
+    fn test_invalid_reloc_addrs() {
+        for (source, message) in [
+            (
+                "reloc:MIPS_26 symbol:foo",
+                "Missing the required `rom` attribute",
+            ),
+            (
+                "rom:0x10 reloc:MIPS_HI32 symbol:foo",
+                "Unknown relocation type `MIPS_HI32`",
+            ),
+            ("rom:0x10 reloc:MIPS_26 symbol foo", "`symbol` should be"),
+            (
+                "rom:0x10 symbol:foo",
+                "Missing the required `reloc` attribute",
+            ),
+            (
+                "rom:0x10 reloc:MIPS_26 symbol:foo\nrom:0x10 reloc:MIPS_26 symbol:bar",
+                "reloc_addrs.txt:2: Duplicated relocation for rom:0x10, first declared at reloc_addrs.txt:1",
+            ),
+        ] {
+            let err = format!("{:#}", parse(source).unwrap_err());
+            assert!(err.starts_with("reloc_addrs.txt:"), "{err}");
+            assert!(err.contains(message), "{err}");
+        }
+    }
+}
@@ -0,0 +1,152 @@
+use std::fmt;
+
+use anyhow::{Context, Result, bail};
+use sha1::{Digest, Sha1};
+
+use crate::{config::options::Platform, yaml::SplatYaml};
+
+/// Byte orders N64 ROM dumps are found in, detected from the first word of the header
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ByteOrder {
+    /// `.z64`, the native big-endian order
+    BigEndian,
+    /// `.v64`, every pair of bytes swapped
+    ByteSwapped,
+    /// `.n64`, every word stored little-endian
+    LittleEndian,
+}
+
+impl ByteOrder {
+    pub fn detect(bytes: &[u8]) -> Option<Self> {
+        match bytes.get(..4)? {
+            [0x80, 0x37, 0x12, 0x40] => Some(Self::BigEndian),
+            [0x37, 0x80, 0x40, 0x12] => Some(Self::ByteSwapped),
+            [0x40, 0x12, 0x37, 0x80] => Some(Self::LittleEndian),
+            _ => None,
+        }
+    }
+
+    /// Rearranges `bytes` in place from this byte order into big-endian
+    pub fn to_big_endian(self, bytes: &mut [u8]) {
+        match self {
+            Self::BigEndian => {}
+            Self::ByteSwapped => bytes.chunks_exact_mut(2).for_each(|pair| pair.swap(0, 1)),
+            Self::LittleEndian => bytes.chunks_exact_mut(4).for_each(|word| word.reverse()),
+        }
+    }
+}
+
+impl fmt::Display for ByteOrder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::BigEndian => write!(f, "big-endian (.z64)"),
+            Self::ByteSwapped => write!(f, "byte-swapped (.v64)"),
+            Self::LittleEndian => write!(f, "little-endian (.n64)"),
+        }
+    }
+}
+
+/// The binary being split, already converted to big-endian for N64 ROMs
+#[derive(Debug, Clone)]
+pub struct TargetRom {
+    pub bytes: Vec<u8>,
+    /// Byte order the file was stored in, only detected for N64 ROMs
+    pub byte_order: Option<ByteOrder>,
+}
+
+impl TargetRom {
+    pub fn new(mut bytes: Vec<u8>, platform: &Platform) -> Self {
+        let byte_order = match platform {
+            Platform::N64 => ByteOrder::detect(&bytes),
+            _ => None,
+        };
+        if let Some(byte_order) = byte_order {
+            byte_order.to_big_endian(&mut bytes);
+        }
+
+        Self { bytes, byte_order }
+    }
+
+    /// Reads `target_path` and checks it against the `sha1` of the config, if there is one
+    pub fn load(splat_yaml: &SplatYaml) -> Result<Self> {
+        let path = &splat_yaml.options.target_path;
+        let bytes = std::fs::read(path)
+            .with_context(|| format!("Failed to read the target binary {}", path.display()))?;
+
+        let rom = Self::new(bytes, &splat_yaml.options.platform);
+        if let Some(expected) = &splat_yaml.sha1 {
+            rom.verify_sha1(expected)
+                .with_context(|| format!("{} is not the expected binary", path.display()))?;
+        }
+
+        Ok(rom)
+    }
+
+    pub fn sha1(&self) -> String {
+        format!("{:x}", Sha1::digest(&self.bytes))
+    }
+
+    pub fn verify_sha1(&self, expected: &str) -> Result<()> {
+        let actual = self.sha1();
+        if actual.eq_ignore_ascii_case(expected.trim()) {
+            return Ok(());
+        }
+
+        match self.byte_order {
+            None => bail!(
+                "SHA-1 mismatch: expected {expected}, got {actual}. Make sure the yaml refers to the right version of the binary"
+            ),
+            Some(ByteOrder::BigEndian) => bail!(
+                "SHA-1 mismatch: expected {expected}, got {actual} for a ROM detected as {}. Make sure the yaml refers to the right version of the binary",
+                ByteOrder::BigEndian
+            ),
+            Some(byte_order) => bail!(
+                "SHA-1 mismatch: expected {expected}, got {actual} after converting the ROM from {byte_order} to big-endian. The `sha1` in the yaml should be the hash of the big-endian (.z64) ROM"
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const Z64: [u8; 8] = [0x80, 0x37, 0x12, 0x40, 0x00, 0x00, 0x00, 0x0F];
+
+    #[test]
+    fn test_byte_orders() {
+        let v64 = vec![0x37, 0x80, 0x40, 0x12, 0x00, 0x00, 0x0F, 0x00];
+        let n64 = vec![0x40, 0x12, 0x37, 0x80, 0x0F, 0x00, 0x00, 0x00];
+
+        for (bytes, byte_order) in [
+            (Z64.to_vec(), ByteOrder::BigEndian),
+            (v64, ByteOrder::ByteSwapped),
+            (n64, ByteOrder::LittleEndian),
+        ] {
+            let rom = TargetRom::new(bytes, &Platform::N64);
+            assert_eq!(rom.byte_order, Some(byte_order));
+            assert_eq!(rom.bytes, Z64);
+        }
+
+        let psx = TargetRom::new(vec![0x37, 0x80, 0x40, 0x12], &Platform::Psx);
+        assert_eq!(psx.byte_order, None);
+        assert_eq!(psx.bytes, [0x37, 0x80, 0x40, 0x12]);
+    }
+
+    #[test]
+    fn test_verify_sha1() {
+        let rom = TargetRom::new(Z64.to_vec(), &Platform::N64);
+        let sha1 = rom.sha1();
+        assert_eq!(sha1.len(), 40);
+        rom.verify_sha1(&sha1.to_uppercase()).unwrap();
+
+        let other = TargetRom::new([0x80, 0x37, 0x12, 0x40].to_vec(), &Platform::N64);
+        let err = other.verify_sha1(&sha1).unwrap_err().to_string();
+        assert!(err.contains("big-endian (.z64)"), "{err}");
+
+        let swapped = TargetRom::new(vec![0x37, 0x80, 0x40, 0x12], &Platform::N64);
+        let err = swapped.verify_sha1(&sha1).unwrap_err().to_string();
+        assert!(err.contains("byte-swapped (.v64)"));
+        assert!(err.contains(&swapped.sha1()));
+    }
+}
@@ -0,0 +1,113 @@
+use std::fmt::Write;
+
+use crate::config::{compiler::Compiler, options::SplatOpts};
+
+/// The lines every generated `.s` file starts with, so it assembles on its own under GNU as.
+///
+/// `section` is the output section of the file, like `.text` or `.rodata`. Only text files get the
+/// `asm_inc_header` directives, as they're the only ones with instructions. Compilers like IDO default
+/// it to empty, leaving those directives to the project's `macro.inc`.
+pub(crate) fn asm_file_header(options: &SplatOpts, section: &str) -> String {
+    let mut header = String::from(".include \"macro.inc\"\n\n");
+
+    let is_text = section == ".text";
+    let directives = options.asm_inc_header.trim_end();
+    if is_text && (!directives.is_empty() || options.add_set_gp_64) {
+        if !directives.is_empty() {
+            writeln!(header, "{directives}").unwrap();
+        }
+        if options.add_set_gp_64 {
+            let comment = match options.compiler {
+                Compiler::Sn64 => "# allow use of 64-bit general purpose registers",
+                _ => "/* allow use of 64-bit general purpose registers */",
+            };
+            writeln!(header, ".set gp=64     {comment}").unwrap();
+        }
+        header.push('\n');
+    }
+
+    if !options.generated_s_preamble.is_empty() {
+        writeln!(header, "{}\n", options.generated_s_preamble).unwrap();
+    }
+
+    match section_flags(section) {
+        Some(flags) => writeln!(header, ".section {section}, \"{flags}\"\n").unwrap(),
+        None => writeln!(header, ".section {section}\n").unwrap(),
+    }
+
+    header
+}
+
+/// The section of the `.s` file of a segment type, like `.rodata` for `rodata` and `.rodata`
+pub(crate) fn section_name(section_type: &str) -> String {
+    format!(
+        ".{}",
+        section_type.strip_prefix('.').unwrap_or(section_type)
+    )
+}
+
+fn section_flags(section: &str) -> Option<&'static str> {
+    Some(match section {
+        ".text" => "ax",
+        ".data" | ".sdata" | ".bss" | ".sbss" => "wa",
+        ".rodata" | ".rdata" => "a",
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::options;
+
+    #[test]
+    fn test_asm_file_header() {
+        let opts = options("platform: n64\ncompiler: GCC");
+        assert_eq!(
+            asm_file_header(&opts, ".text"),
+            ".include \"macro.inc\"\n\n\
+             .set noat      /* allow manual use of $at */\n\
+             .set noreorder /* don't insert nops after branches */\n\
+             .set gp=64     /* allow use of 64-bit general purpose registers */\n\n\
+             .section .text, \"ax\"\n\n"
+        );
+
+        let opts = options(
+            "platform: n64\ncompiler: SN64\nadd_set_gp_64: false\ngenerated_s_preamble: .set mips3",
+        );
+        assert_eq!(
+            asm_file_header(&opts, ".text"),
+            ".include \"macro.inc\"\n\n\
+             .set noat      # allow manual use of $at\n\
+             .set noreorder # don't insert nops after branches\n\n\
+             .set mips3\n\n\
+             .section .text, \"ax\"\n\n"
+        );
+        assert_eq!(
+            asm_file_header(&opts, &section_name("rdata")),
+            ".include \"macro.inc\"\n\n.set mips3\n\n.section .rdata, \"a\"\n\n"
+        );
+
+        let opts = options("platform: n64\ncompiler: IDO\nadd_set_gp_64: false");
+        assert_eq!(
+            asm_file_header(&opts, ".text"),
+            ".include \"macro.inc\"\n\n.section .text, \"ax\"\n\n"
+        );
+
+        let opts = options("platform: n64\ncompiler: GCC\nasm_inc_header: \"\"");
+        assert_eq!(
+            asm_file_header(&opts, ".text"),
+            ".include \"macro.inc\"\n\n\
+             .set gp=64     /* allow use of 64-bit general purpose registers */\n\n\
+             .section .text, \"ax\"\n\n"
+        );
+
+        let opts = options(
+            "platform: n64\nadd_set_gp_64: false\nasm_inc_header: \".set noat\\n.set noreorder\\n.set fp=32\\n\"",
+        );
+        assert_eq!(
+            asm_file_header(&opts, ".text"),
+            ".include \"macro.inc\"\n\n.set noat\n.set noreorder\n.set fp=32\n\n.section .text, \"ax\"\n\n"
+        );
+    }
+}
@@ -1,4 +1,7 @@
-use std::sync::Arc;
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+};
 
 use address_space::{AddressRange, Rom, RomVramRange, Size, Vram};
 use anyhow::{Context, Result};
@@ -17,6 +20,7 @@ pub struct CommonSegAsm {
     name: Arc<str>,
     section_type: Arc<str>,
     address: RomVramRange,
+    dir: PathBuf,
 
     spimdisasm_section: ExecutableSection,
 }
@@ -48,6 +52,7 @@ impl CommonSegAsm {
         rom: u32,
         vram_start: u32,
         most_parent: &impl SegmentGroup,
+        dir: PathBuf,
         // TODO: figure out these two
         args: &(),
         yaml: &(),
@@ -60,6 +65,7 @@ impl CommonSegAsm {
             rom,
             vram_start,
             most_parent,
+            dir,
             args,
             yaml,
         )
@@ -73,6 +79,7 @@ impl CommonSegAsm {
         rom: u32,
         vram_start: u32,
         most_parent: &impl SegmentGroup,
+        dir: PathBuf,
         // TODO: figure out these two
         _args: &(),
         _yaml: &(),
@@ -109,16 +116,23 @@ impl CommonSegAsm {
             name,
             section_type,
             address,
+            dir,
 
             spimdisasm_section,
         })
     }
 
+    /// Directory of the outputs, relative to the configured output paths
+    pub(crate) fn dir(&self) -> &Path {
+        &self.dir
+    }
+
     pub fn post_process(self, splat_instance: &mut SplatInstance) -> Result<CommonSegAsmProcessed> {
         let Self {
             name,
             section_type,
             address,
+            dir,
             spimdisasm_section,
         } = self;
 
@@ -127,6 +141,7 @@ impl CommonSegAsm {
             name,
             section_type,
             address,
+            &dir,
             spimdisasm_section,
         )
     }
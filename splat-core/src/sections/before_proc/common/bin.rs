@@ -0,0 +1,83 @@
+use std::{path::PathBuf, sync::Arc};
+
+use address_space::{AddressRange, Rom, Size, Vram};
+use anyhow::Result;
+
+use splat_segment_api::section_trait::SectionTrait;
+
+use crate::{config::instance::SplatInstance, sections::processed::common::CommonSegBinProcessed};
+
+/// Raw bytes copied as is from the ROM, placed in the output section of their type by an `.incbin`
+#[derive(Debug, Clone, PartialEq, PartialOrd, Hash)]
+pub struct CommonSegBin {
+    name: Arc<str>,
+    section_type: Arc<str>,
+    rom: AddressRange<Rom>,
+    vram_start: Option<Vram>,
+    dir: PathBuf,
+
+    bytes: Vec<u8>,
+}
+
+impl SectionTrait for CommonSegBin {
+    fn name(&self) -> Arc<str> {
+        Arc::clone(&self.name)
+    }
+
+    fn section_type(&self) -> Arc<str> {
+        Arc::clone(&self.section_type)
+    }
+
+    fn rom(&self) -> Option<AddressRange<Rom>> {
+        Some(self.rom)
+    }
+
+    fn vram(&self) -> Option<AddressRange<Vram>> {
+        let vram_start = self.vram_start?;
+        let size = Size::new(self.bytes.len() as u32);
+
+        Some(AddressRange::new(vram_start, vram_start.add_size(&size)))
+    }
+}
+
+impl CommonSegBin {
+    pub fn new(
+        name: impl Into<Arc<str>>,
+        section_type: impl Into<Arc<str>>,
+        raw_bytes: &[u8],
+        rom: AddressRange<Rom>,
+        vram_start: Option<Vram>,
+        dir: PathBuf,
+    ) -> Self {
+        Self {
+            name: name.into(),
+            section_type: section_type.into(),
+            rom,
+            vram_start,
+            dir,
+
+            bytes: raw_bytes.to_vec(),
+        }
+    }
+
+    pub fn post_process(self, splat_instance: &mut SplatInstance) -> Result<CommonSegBinProcessed> {
+        let Self {
+            name,
+            section_type,
+            rom,
+            vram_start,
+            dir,
+            bytes,
+        } = self;
+
+        Ok(CommonSegBinProcessed::new(
+            splat_instance,
+            name,
+            section_type,
+            rom,
+            vram_start,
+            &dir,
+            bytes,
+        ))
+    }
+}
@@ -0,0 +1,106 @@
+use std::{path::PathBuf, sync::Arc};
+
+use address_space::{AddressRange, Rom, Size, Vram};
+use anyhow::{Context, Result};
+use spimdisasm::{
+    sections::before_proc::{NoloadSection, NoloadSectionSettings},
+    segments::{OverlayCategoryName, ParentSegmentInfo},
+};
+
+use splat_segment_api::{section_trait::SectionTrait, segment_trait::SegmentGroup};
+
+use crate::{config::instance::SplatInstance, sections::processed::common::CommonSegBssProcessed};
+
+/// A section that only takes up VRAM, like `.bss`
+#[derive(Debug, Clone, PartialEq, PartialOrd, Hash)]
+pub struct CommonSegBss {
+    name: Arc<str>,
+    section_type: Arc<str>,
+    vram: AddressRange<Vram>,
+    dir: PathBuf,
+
+    spimdisasm_section: NoloadSection,
+}
+
+impl SectionTrait for CommonSegBss {
+    fn name(&self) -> Arc<str> {
+        Arc::clone(&self.name)
+    }
+
+    fn section_type(&self) -> Arc<str> {
+        Arc::clone(&self.section_type)
+    }
+
+    fn rom(&self) -> Option<AddressRange<Rom>> {
+        None
+    }
+
+    fn vram(&self) -> Option<AddressRange<Vram>> {
+        Some(self.vram)
+    }
+}
+
+impl CommonSegBss {
+    pub fn new(
+        splat_instance: &mut SplatInstance,
+        name: impl Into<Arc<str>>,
+        section_type: impl Into<Arc<str>>,
+        vram_start: u32,
+        bss_size: u32,
+        most_parent: &impl SegmentGroup,
+        dir: PathBuf,
+    ) -> Result<Self> {
+        let name = name.into();
+
+        // TODO: tweak settings
+        let mut section_settings = NoloadSectionSettings::new(None);
+        if let Some(create_pads) = splat_instance.options.create_bss_pads {
+            section_settings = section_settings.with_create_pads(create_pads);
+        }
+        let parent_segment_info = ParentSegmentInfo::new(
+            most_parent.rom().context("Missing Rom")?.start(),
+            most_parent.vram_start().context("Missing Vram")?,
+            most_parent
+                .overlay_category_name()
+                .map(OverlayCategoryName::new),
+        );
+
+        let vram_start = Vram::new(vram_start);
+        let vram = AddressRange::new(vram_start, vram_start.add_size(&Size::new(bss_size)));
+
+        let spimdisasm_section = splat_instance.spimdisasm_context.create_section_bss(
+            &section_settings,
+            Arc::clone(&name),
+            vram,
+            parent_segment_info,
+        )?;
+
+        Ok(Self {
+            name,
+            section_type: section_type.into(),
+            vram,
+            dir,
+
+            spimdisasm_section,
+        })
+    }
+
+    pub fn post_process(self, splat_instance: &mut SplatInstance) -> Result<CommonSegBssProcessed> {
+        let Self {
+            name,
+            section_type,
+            vram,
+            dir,
+            spimdisasm_section,
+        } = self;
+
+        CommonSegBssProcessed::new(
+            splat_instance,
+            name,
+            section_type,
+            vram,
+            &dir,
+            spimdisasm_section,
+        )
+    }
+}
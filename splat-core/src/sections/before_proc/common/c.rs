@@ -0,0 +1,84 @@
+use std::{path::PathBuf, sync::Arc};
+
+use address_space::{AddressRange, Rom, Vram};
+use anyhow::Result;
+
+use splat_segment_api::{section_trait::SectionTrait, segment_trait::SegmentGroup};
+
+use crate::{config::instance::SplatInstance, sections::processed::common::CommonSegCProcessed};
+
+use super::CommonSegAsm;
+
+/// The text of a C or C++ file that hasn't been fully decompiled yet
+#[derive(Debug, Clone, PartialEq, PartialOrd, Hash)]
+pub struct CommonSegC {
+    asm: CommonSegAsm,
+    /// Extra arguments of the yaml entry, passed to the compiler when building this file only
+    compiler_flags: Vec<String>,
+}
+
+impl SectionTrait for CommonSegC {
+    fn name(&self) -> Arc<str> {
+        self.asm.name()
+    }
+
+    fn section_type(&self) -> Arc<str> {
+        self.asm.section_type()
+    }
+
+    fn rom(&self) -> Option<AddressRange<Rom>> {
+        self.asm.rom()
+    }
+
+    fn vram(&self) -> Option<AddressRange<Vram>> {
+        self.asm.vram()
+    }
+}
+
+impl CommonSegC {
+    pub fn new(
+        splat_instance: &mut SplatInstance,
+        name: impl Into<Arc<str>>,
+        section_type: impl Into<Arc<str>>,
+        raw_bytes: &[u8],
+        rom: u32,
+        vram_start: u32,
+        most_parent: &impl SegmentGroup,
+        dir: PathBuf,
+        compiler_flags: Vec<String>,
+    ) -> Result<Self> {
+        let asm = CommonSegAsm::new(
+            splat_instance,
+            name,
+            section_type,
+            raw_bytes,
+            rom,
+            vram_start,
+            most_parent,
+            dir,
+            &(),
+            &(),
+        )?;
+
+        Ok(Self {
+            asm,
+            compiler_flags,
+        })
+    }
+
+    pub fn post_process(self, splat_instance: &mut SplatInstance) -> Result<CommonSegCProcessed> {
+        let Self {
+            asm,
+            compiler_flags,
+        } = self;
+
+        let dir = asm.dir().to_path_buf();
+        let asm = asm.post_process(splat_instance)?;
+        Ok(CommonSegCProcessed::new(
+            splat_instance,
+            asm,
+            &dir,
+            compiler_flags,
+        ))
+    }
+}
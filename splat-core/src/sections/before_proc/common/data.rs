@@ -1,4 +1,4 @@
-use std::sync::Arc;
+use std::{path::PathBuf, sync::Arc};
 
 use address_space::{AddressRange, Rom, RomVramRange, Size, Vram};
 use anyhow::{Context, Result};
@@ -16,6 +16,7 @@ pub struct CommonSegData {
     name: Arc<str>,
     section_type: Arc<str>,
     address: RomVramRange,
+    dir: PathBuf,
 
     spimdisasm_section: DataSection,
 }
@@ -47,6 +48,7 @@ impl CommonSegData {
         rom: u32,
         vram_start: u32,
         most_parent: &impl SegmentGroup,
+        dir: PathBuf,
         // TODO: figure out these two
         args: &(),
         yaml: &(),
@@ -59,6 +61,7 @@ impl CommonSegData {
             rom,
             vram_start,
             most_parent,
+            dir,
             args,
             yaml,
         )
@@ -72,6 +75,7 @@ impl CommonSegData {
         rom: u32,
         vram_start: u32,
         most_parent: &impl SegmentGroup,
+        dir: PathBuf,
         // TODO: figure out these two
         _args: &(),
         _yaml: &(),
@@ -107,6 +111,7 @@ impl CommonSegData {
             name,
             section_type,
             address,
+            dir,
 
             spimdisasm_section,
         })
@@ -120,6 +125,7 @@ impl CommonSegData {
             name,
             section_type,
             address,
+            dir,
             spimdisasm_section,
         } = self;
 
@@ -128,6 +134,7 @@ impl CommonSegData {
             name,
             section_type,
             address,
+            &dir,
             spimdisasm_section,
         )
     }
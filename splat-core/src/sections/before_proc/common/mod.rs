@@ -1,5 +1,13 @@
 mod asm;
+mod bin;
+mod bss;
+mod c;
 mod data;
+mod rodata;
 
 pub use asm::CommonSegAsm;
+pub use bin::CommonSegBin;
+pub use bss::CommonSegBss;
+pub use c::CommonSegC;
 pub use data::CommonSegData;
+pub use rodata::CommonSegRodata;
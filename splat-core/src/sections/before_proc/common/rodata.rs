@@ -0,0 +1,165 @@
+use std::{path::PathBuf, sync::Arc};
+
+use address_space::{AddressRange, Rom, RomVramRange, Size, Vram};
+use anyhow::{Context, Result, bail};
+use spimdisasm::{
+    sections::before_proc::{DataSection, DataSectionSettings, StringGuesserFlags},
+    segments::{OverlayCategoryName, ParentSegmentInfo},
+    str_decoding::Encoding,
+};
+
+use splat_segment_api::{section_trait::SectionTrait, segment_trait::SegmentGroup};
+
+use crate::{
+    config::instance::SplatInstance, sections::processed::common::CommonSegRodataProcessed,
+};
+
+#[derive(Debug, Clone, PartialEq, PartialOrd, Hash)]
+pub struct CommonSegRodata {
+    name: Arc<str>,
+    section_type: Arc<str>,
+    address: RomVramRange,
+    dir: PathBuf,
+
+    spimdisasm_section: DataSection,
+}
+
+impl SectionTrait for CommonSegRodata {
+    fn name(&self) -> Arc<str> {
+        Arc::clone(&self.name)
+    }
+
+    fn section_type(&self) -> Arc<str> {
+        Arc::clone(&self.section_type)
+    }
+
+    fn rom(&self) -> Option<AddressRange<Rom>> {
+        Some(*self.address.rom())
+    }
+
+    fn vram(&self) -> Option<AddressRange<Vram>> {
+        Some(*self.address.vram())
+    }
+}
+
+impl CommonSegRodata {
+    pub fn new(
+        splat_instance: &mut SplatInstance,
+        name: impl Into<Arc<str>>,
+        section_type: impl Into<Arc<str>>,
+        raw_bytes: &[u8],
+        rom: u32,
+        vram_start: u32,
+        most_parent: &impl SegmentGroup,
+        dir: PathBuf,
+    ) -> Result<Self> {
+        let name = name.into();
+
+        // TODO: tweak settings
+        let mut section_settings = DataSectionSettings::new(None);
+        if let Some(level) = splat_instance.options.rodata_string_guesser_level {
+            section_settings =
+                section_settings.with_string_guesser_flags(string_guesser_flags(level));
+        }
+        if let Some(encoding) = &splat_instance.options.string_encoding {
+            section_settings = section_settings.with_encoding(string_encoding(encoding)?);
+        }
+        let parent_segment_info = ParentSegmentInfo::new(
+            most_parent.rom().context("Missing Rom")?.start(),
+            most_parent.vram_start().context("Missing Vram")?,
+            most_parent
+                .overlay_category_name()
+                .map(OverlayCategoryName::new),
+        );
+
+        let address = RomVramRange::new_size(
+            Rom::new(rom),
+            Vram::new(vram_start),
+            Size::new(raw_bytes.len() as u32),
+            0, // no alignment restrictions for rodata
+        )
+        .context("Invalid address")?;
+
+        let spimdisasm_section = splat_instance.spimdisasm_context.create_section_rodata(
+            &section_settings,
+            Arc::clone(&name),
+            raw_bytes.into(),
+            address.rom().start(),
+            address.vram().start(),
+            parent_segment_info,
+        )?;
+
+        Ok(Self {
+            name,
+            section_type: section_type.into(),
+            address,
+            dir,
+
+            spimdisasm_section,
+        })
+    }
+
+    pub fn post_process(
+        self,
+        splat_instance: &mut SplatInstance,
+    ) -> Result<CommonSegRodataProcessed> {
+        let Self {
+            name,
+            section_type,
+            address,
+            dir,
+            spimdisasm_section,
+        } = self;
+
+        CommonSegRodataProcessed::new(
+            splat_instance,
+            name,
+            section_type,
+            address,
+            &dir,
+            spimdisasm_section,
+        )
+    }
+}
+
+/// Each `*_string_guesser_level` enables what the previous one did and one more kind of guess
+fn string_guesser_flags(level: u8) -> StringGuesserFlags {
+    let mut flags = StringGuesserFlags::empty();
+    if level >= 1 {
+        flags = flags | StringGuesserFlags::BASIC;
+    }
+    if level >= 2 {
+        flags = flags | StringGuesserFlags::MULTIPLE_REFERENCES;
+    }
+    if level >= 3 {
+        flags = flags | StringGuesserFlags::EMPTY_STRINGS;
+    }
+    if level >= 4 {
+        flags = flags | StringGuesserFlags::IGNORE_DETECTED_TYPE;
+    }
+    flags
+}
+
+fn string_encoding(name: &str) -> Result<Encoding> {
+    Ok(match name.to_ascii_uppercase().replace('_', "-").as_str() {
+        "ASCII" => Encoding::Ascii,
+        "SHIFT-JIS" | "SJIS" => Encoding::ShiftJis,
+        "EUC-JP" => Encoding::EucJp,
+        "EUC-CN" | "GB2312" => Encoding::EucCn,
+        "UTF-8" | "UTF8" => Encoding::Utf8,
+        _ => bail!("Unsupported string encoding `{name}`"),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_string_encoding() {
+        assert_eq!(string_encoding("ASCII").unwrap(), Encoding::Ascii);
+        assert_eq!(string_encoding("shift_jis").unwrap(), Encoding::ShiftJis);
+        assert_eq!(string_encoding("EUC-JP").unwrap(), Encoding::EucJp);
+        assert!(string_encoding("latin1").is_err());
+    }
+}
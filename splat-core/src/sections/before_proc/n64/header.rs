@@ -0,0 +1,72 @@
+use std::{path::PathBuf, sync::Arc};
+
+use address_space::{AddressRange, Rom, Vram};
+use anyhow::Result;
+
+use splat_segment_api::section_trait::SectionTrait;
+
+use crate::{
+    config::instance::SplatInstance, n64::N64Header,
+    sections::processed::n64::N64SegHeaderProcessed,
+};
+
+/// The header at the start of N64 ROMs
+#[derive(Debug, Clone, PartialEq, PartialOrd, Hash)]
+pub struct N64SegHeader {
+    name: Arc<str>,
+    section_type: Arc<str>,
+    rom: AddressRange<Rom>,
+    dir: PathBuf,
+
+    bytes: Vec<u8>,
+}
+
+impl SectionTrait for N64SegHeader {
+    fn name(&self) -> Arc<str> {
+        Arc::clone(&self.name)
+    }
+
+    fn section_type(&self) -> Arc<str> {
+        Arc::clone(&self.section_type)
+    }
+
+    fn rom(&self) -> Option<AddressRange<Rom>> {
+        Some(self.rom)
+    }
+
+    fn vram(&self) -> Option<AddressRange<Vram>> {
+        None
+    }
+}
+
+impl N64SegHeader {
+    pub fn new(
+        name: impl Into<Arc<str>>,
+        section_type: impl Into<Arc<str>>,
+        raw_bytes: &[u8],
+        rom: AddressRange<Rom>,
+        dir: PathBuf,
+    ) -> Self {
+        Self {
+            name: name.into(),
+            section_type: section_type.into(),
+            rom,
+            dir,
+
+            bytes: raw_bytes.to_vec(),
+        }
+    }
+
+    pub fn post_process(self, splat_instance: &mut SplatInstance) -> Result<N64SegHeaderProcessed> {
+        let Self {
+            name,
+            section_type,
+            rom,
+            dir,
+            bytes,
+        } = self;
+        let header = N64Header::parse(&bytes)?;
+
+        N64SegHeaderProcessed::new(splat_instance, name, section_type, rom, &dir, header)
+    }
+}
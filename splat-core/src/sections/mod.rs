@@ -1,2 +1,166 @@
+use std::sync::Arc;
+
+use address_space::{AddressRange, Rom, Vram};
+use anyhow::Result;
+
+use splat_segment_api::section_trait::SectionTrait;
+
+use crate::config::instance::SplatInstance;
+
+use before_proc::{
+    common::{
+        CommonSegAsm, CommonSegBin, CommonSegBss, CommonSegC, CommonSegData, CommonSegRodata,
+    },
+    n64::N64SegHeader,
+};
+use processed::{
+    common::{
+        CommonSegAsmProcessed, CommonSegBinProcessed, CommonSegBssProcessed, CommonSegCProcessed,
+        CommonSegDataProcessed, CommonSegRodataProcessed,
+    },
+    n64::N64SegHeaderProcessed,
+};
+
+mod asm_header;
 pub mod before_proc;
 pub mod processed;
+
+/// Any of the native sections, before processing
+#[derive(Debug, Clone, PartialEq, PartialOrd, Hash)]
+pub enum Section {
+    Asm(CommonSegAsm),
+    C(CommonSegC),
+    Data(CommonSegData),
+    Rodata(CommonSegRodata),
+    Bss(CommonSegBss),
+    Bin(CommonSegBin),
+    N64Header(N64SegHeader),
+}
+
+impl Section {
+    pub fn post_process(self, splat_instance: &mut SplatInstance) -> Result<SectionProcessed> {
+        Ok(match self {
+            Self::Asm(section) => SectionProcessed::Asm(section.post_process(splat_instance)?),
+            Self::C(section) => SectionProcessed::C(section.post_process(splat_instance)?),
+            Self::Data(section) => SectionProcessed::Data(section.post_process(splat_instance)?),
+            Self::Rodata(section) => {
+                SectionProcessed::Rodata(section.post_process(splat_instance)?)
+            }
+            Self::Bss(section) => SectionProcessed::Bss(section.post_process(splat_instance)?),
+            Self::Bin(section) => SectionProcessed::Bin(section.post_process(splat_instance)?),
+            Self::N64Header(section) => {
+                SectionProcessed::N64Header(section.post_process(splat_instance)?)
+            }
+        })
+    }
+
+    fn inner(&self) -> &dyn SectionTrait {
+        match self {
+            Self::Asm(section) => section,
+            Self::C(section) => section,
+            Self::Data(section) => section,
+            Self::Rodata(section) => section,
+            Self::Bss(section) => section,
+            Self::Bin(section) => section,
+            Self::N64Header(section) => section,
+        }
+    }
+}
+
+impl SectionTrait for Section {
+    fn name(&self) -> Arc<str> {
+        self.inner().name()
+    }
+
+    fn section_type(&self) -> Arc<str> {
+        self.inner().section_type()
+    }
+
+    fn rom(&self) -> Option<AddressRange<Rom>> {
+        self.inner().rom()
+    }
+
+    fn vram(&self) -> Option<AddressRange<Vram>> {
+        self.inner().vram()
+    }
+}
+
+/// Any of the native sections, after processing
+#[derive(Debug, Clone, PartialEq, PartialOrd, Hash)]
+pub enum SectionProcessed {
+    Asm(CommonSegAsmProcessed),
+    C(CommonSegCProcessed),
+    Data(CommonSegDataProcessed),
+    Rodata(CommonSegRodataProcessed),
+    Bss(CommonSegBssProcessed),
+    Bin(CommonSegBinProcessed),
+    N64Header(N64SegHeaderProcessed),
+}
+
+impl SectionProcessed {
+    /// Writes the outputs of the section. `sibling` is the section with the same name in the same group, which
+    /// text and rodata sections use to migrate rodata into functions
+    pub fn split(
+        &self,
+        splat_instance: &SplatInstance,
+        sibling: Option<&SectionProcessed>,
+    ) -> Result<()> {
+        match (self, sibling) {
+            (Self::Asm(section), Some(Self::Rodata(rodata))) => {
+                section.split(splat_instance, Some(rodata))
+            }
+            (Self::Asm(section), _) => section.split(splat_instance, None),
+            (Self::C(section), Some(Self::Rodata(rodata))) => {
+                section.split(splat_instance, Some(rodata))
+            }
+            (Self::C(section), _) => section.split(splat_instance, None),
+            (Self::Rodata(section), Some(Self::Asm(text))) => {
+                section.split(splat_instance, Some(text))
+            }
+            // The C file includes the rodata its functions don't use, the rest is migrated to the functions
+            (Self::Rodata(_), Some(Self::C(_)))
+                if splat_instance.options.migrate_rodata_to_functions =>
+            {
+                Ok(())
+            }
+            (Self::Rodata(section), Some(Self::C(text))) => {
+                section.split(splat_instance, Some(text.asm()))
+            }
+            (Self::Rodata(section), _) => section.split(splat_instance, None),
+            (Self::Data(section), _) => section.split(splat_instance),
+            (Self::Bss(section), _) => section.split(splat_instance),
+            (Self::Bin(section), _) => section.split(splat_instance),
+            (Self::N64Header(section), _) => section.split(splat_instance),
+        }
+    }
+
+    fn inner(&self) -> &dyn SectionTrait {
+        match self {
+            Self::Asm(section) => section,
+            Self::C(section) => section,
+            Self::Data(section) => section,
+            Self::Rodata(section) => section,
+            Self::Bss(section) => section,
+            Self::Bin(section) => section,
+            Self::N64Header(section) => section,
+        }
+    }
+}
+
+impl SectionTrait for SectionProcessed {
+    fn name(&self) -> Arc<str> {
+        self.inner().name()
+    }
+
+    fn section_type(&self) -> Arc<str> {
+        self.inner().section_type()
+    }
+
+    fn rom(&self) -> Option<AddressRange<Rom>> {
+        self.inner().rom()
+    }
+
+    fn vram(&self) -> Option<AddressRange<Vram>> {
+        self.inner().vram()
+    }
+}
@@ -1,7 +1,7 @@
 use std::{
     fs,
     io::{BufWriter, Write},
-    path::PathBuf,
+    path::{Path, PathBuf},
     sync::Arc,
 };
 
@@ -9,13 +9,18 @@ use address_space::{AddressRange, Rom, RomVramRange, Vram};
 use anyhow::{Context, Result};
 use spimdisasm::{
     rabbitizer::InstructionDisplayFlags,
-    sections::{before_proc::ExecutableSection, processed::ExecutableSectionProcessed},
+    sections::{
+        before_proc::ExecutableSection,
+        processed::{ExecutableSectionProcessed, FuncRodataPairing},
+    },
     symbols::display::{FunctionDisplaySettings, SymDataDisplaySettings},
 };
 
 use splat_segment_api::section_trait::SectionTrait;
 
-use crate::config::instance::SplatInstance;
+use crate::{config::instance::SplatInstance, sections::asm_header::asm_file_header};
+
+use super::CommonSegRodataProcessed;
 
 #[derive(Debug, Clone, PartialEq, PartialOrd, Hash)]
 pub struct CommonSegAsmProcessed {
@@ -51,6 +56,7 @@ impl CommonSegAsmProcessed {
         name: Arc<str>,
         section_type: Arc<str>,
         address: RomVramRange,
+        dir: &Path,
         spimdisasm_section: ExecutableSection,
     ) -> Result<Self> {
         let spimdisasm_processed = spimdisasm_section.post_process(
@@ -58,9 +64,13 @@ impl CommonSegAsmProcessed {
             &splat_instance.user_relocs,
         )?;
 
-        // TODO: self.dir in the middle
-        // options.opts.asm_path / self.dir / f"{self.name}.s"
-        let path = splat_instance.options.asm_path.join(format!("{}.s", name));
+        let options = &splat_instance.options;
+        let out_dir = if section_type.as_ref() == "hasm" && options.hasm_in_src_path {
+            &options.src_path
+        } else {
+            &options.asm_path
+        };
+        let path = out_dir.join(dir).join(format!("{name}.s"));
 
         Ok(Self {
             name,
@@ -72,24 +82,121 @@ impl CommonSegAsmProcessed {
         })
     }
 
-    pub fn split(&self, splat_instance: &SplatInstance) -> Result<()> {
+    pub(crate) fn spimdisasm_section(&self) -> &ExecutableSectionProcessed {
+        &self.spimdisasm_section
+    }
+
+    /// Writes the functions, each followed by its rodata when `migrate_rodata_to_functions` is set and the
+    /// section has a `rodata` sibling. Existing `hasm` files are kept unless `--force-hasm` is given
+    pub fn split(
+        &self,
+        splat_instance: &SplatInstance,
+        rodata: Option<&CommonSegRodataProcessed>,
+    ) -> Result<()> {
+        // `hasm` files are edited by hand once created
+        if self.section_type.as_ref() == "hasm"
+            && self.path.exists()
+            && !splat_instance.options.force_hasm
+        {
+            return Ok(());
+        }
+
         fs::create_dir_all(self.path.parent().context("unable to get parent dir?")?)?;
 
         let mut writer = BufWriter::new(fs::File::create(&self.path)?);
-        // TODO: get_asm_file_header
+        write!(
+            writer,
+            "{}",
+            asm_file_header(&splat_instance.options, ".text")
+        )?;
 
         let instr_display_flags = InstructionDisplayFlags::new_gnu_as();
-        let func_settings = FunctionDisplaySettings::new(instr_display_flags);
+        let func_settings = FunctionDisplaySettings::new(instr_display_flags)
+            .with_jtbl_label_macro(splat_instance.options.asm_jtbl_label_macro.clone());
         let data_settings = SymDataDisplaySettings::new();
-        for sym in self.spimdisasm_section.symbols() {
-            let sym_display = sym.display(
-                &splat_instance.spimdisasm_context,
-                &func_settings,
-                &data_settings,
-            )?;
+        let context = &splat_instance.spimdisasm_context;
+        let functions = self.spimdisasm_section.symbols();
+
+        let rodata = rodata.filter(|_| splat_instance.options.migrate_rodata_to_functions);
+        let Some(rodata) = rodata else {
+            for sym in functions {
+                let sym_display = sym.display(context, &func_settings, &data_settings)?;
+                writeln!(writer, "{}", sym_display)?;
+            }
+            return Ok(());
+        };
+
+        let rodata_symbols = rodata.spimdisasm_section().data_symbols();
+        let pairings = FuncRodataPairing::pair_sections(
+            context,
+            Some(&self.spimdisasm_section),
+            Some(rodata.spimdisasm_section()),
+        );
+        for pairing in pairings {
+            let FuncRodataPairing::SingleFunction {
+                function_index,
+                rodata_indices,
+                late_rodata_indices,
+            } = pairing
+            else {
+                continue;
+            };
+
+            let migrated: Vec<_> = rodata_indices.iter().chain(&late_rodata_indices).collect();
+            if !migrated.is_empty() {
+                writeln!(writer, ".section .rodata")?;
+                for index in migrated {
+                    let sym_display = rodata_symbols[*index].display(context, &data_settings)?;
+                    writeln!(writer, "{}", sym_display)?;
+                }
+                writeln!(writer, ".section .text")?;
+            }
+
+            let sym_display =
+                functions[function_index].display(context, &func_settings, &data_settings)?;
             writeln!(writer, "{}", sym_display)?;
         }
 
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::test_utils::{project, split_project};
+
+    #[test]
+    fn test_hasm_files() {
+        let dir = project(
+            "hasm",
+            "hasm_in_src_path: true",
+            "
+segments:
+  - name: entry
+    type: code
+    start: 0x0
+    vram: 0x80000400
+    subsegments:
+      - [0x0, hasm, entry]
+  - [0x10]
+",
+            &[0; 0x10],
+        );
+        let hasm_path = dir.join("src/entry.s");
+
+        split_project(&dir, false);
+        let hasm = std::fs::read_to_string(&hasm_path).unwrap();
+        assert!(hasm.starts_with(".include \"macro.inc\"\n"), "{hasm}");
+        assert!(!dir.join("asm/entry.s").exists());
+
+        std::fs::write(&hasm_path, "# edited by hand\n").unwrap();
+        split_project(&dir, false);
+        assert_eq!(
+            std::fs::read_to_string(&hasm_path).unwrap(),
+            "# edited by hand\n"
+        );
+
+        split_project(&dir, true);
+        assert_eq!(std::fs::read_to_string(&hasm_path).unwrap(), hasm);
+    }
+}
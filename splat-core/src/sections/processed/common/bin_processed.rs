@@ -0,0 +1,159 @@
+use std::{
+    fs,
+    io::{BufWriter, Write},
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use address_space::{AddressRange, Rom, Size, Vram};
+use anyhow::{Context, Result};
+
+use splat_segment_api::section_trait::SectionTrait;
+
+use crate::{config::instance::SplatInstance, sections::asm_header::asm_file_header};
+
+#[derive(Debug, Clone, PartialEq, PartialOrd, Hash)]
+pub struct CommonSegBinProcessed {
+    name: Arc<str>,
+    section_type: Arc<str>,
+    rom: AddressRange<Rom>,
+    vram_start: Option<Vram>,
+    /// The extracted bytes
+    bin_path: PathBuf,
+    /// The assembly file including `bin_path`
+    path: PathBuf,
+
+    bytes: Vec<u8>,
+}
+
+impl SectionTrait for CommonSegBinProcessed {
+    fn name(&self) -> Arc<str> {
+        Arc::clone(&self.name)
+    }
+
+    fn section_type(&self) -> Arc<str> {
+        Arc::clone(&self.section_type)
+    }
+
+    fn rom(&self) -> Option<AddressRange<Rom>> {
+        Some(self.rom)
+    }
+
+    fn vram(&self) -> Option<AddressRange<Vram>> {
+        let vram_start = self.vram_start?;
+        let size = Size::new(self.bytes.len() as u32);
+
+        Some(AddressRange::new(vram_start, vram_start.add_size(&size)))
+    }
+}
+
+impl CommonSegBinProcessed {
+    pub(crate) fn new(
+        splat_instance: &SplatInstance,
+        name: Arc<str>,
+        section_type: Arc<str>,
+        rom: AddressRange<Rom>,
+        vram_start: Option<Vram>,
+        dir: &Path,
+        bytes: Vec<u8>,
+    ) -> Self {
+        let options = &splat_instance.options;
+        let bin_path = options.asset_path.join(dir).join(format!("{name}.bin"));
+        let path = options.data_path.join(dir).join(format!("{name}.s"));
+
+        Self {
+            name,
+            section_type,
+            rom,
+            vram_start,
+            bin_path,
+            path,
+
+            bytes,
+        }
+    }
+
+    pub fn bin_path(&self) -> &Path {
+        &self.bin_path
+    }
+
+    /// Writes the bytes to `asset_path`, and the assembly placing them in the section of the segment type
+    pub fn split(&self, splat_instance: &SplatInstance) -> Result<()> {
+        fs::create_dir_all(
+            self.bin_path
+                .parent()
+                .context("unable to get parent dir?")?,
+        )?;
+        fs::write(&self.bin_path, &self.bytes)
+            .with_context(|| format!("Failed to write {}", self.bin_path.display()))?;
+
+        fs::create_dir_all(self.path.parent().context("unable to get parent dir?")?)?;
+        let mut writer = BufWriter::new(fs::File::create(&self.path)?);
+
+        let include_path = self
+            .bin_path
+            .strip_prefix(&splat_instance.options.base_path)
+            .unwrap_or(&self.bin_path);
+        write!(
+            writer,
+            "{}",
+            asm_file_header(&splat_instance.options, bin_section(&self.section_type))
+        )?;
+        writeln!(writer, ".incbin \"{}\"", include_path.display())?;
+
+        Ok(())
+    }
+}
+
+/// The section the bytes are placed in, `.data` unless the type says otherwise
+fn bin_section(section_type: &str) -> &'static str {
+    match section_type {
+        "textbin" => ".text",
+        "rodatabin" => ".rodata",
+        _ => ".data",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_utils::{project, split_project};
+
+    #[test]
+    fn test_bin_files() {
+        let rom: Vec<u8> = (0..0x20).collect();
+        let dir = project(
+            "bin",
+            "",
+            "
+segments:
+  - name: main
+    type: code
+    start: 0x0
+    vram: 0x80000400
+    subsegments:
+      - [0x0, bin, table]
+      - [0x10, rodatabin, strings]
+  - [0x20]
+",
+            &rom,
+        );
+        split_project(&dir, false);
+
+        assert_eq!(
+            std::fs::read(dir.join("assets/table.bin")).unwrap(),
+            &rom[..0x10]
+        );
+        assert_eq!(
+            std::fs::read(dir.join("assets/strings.bin")).unwrap(),
+            &rom[0x10..]
+        );
+        assert_eq!(
+            std::fs::read_to_string(dir.join("asm/data/table.s")).unwrap(),
+            ".include \"macro.inc\"\n\n.section .data, \"wa\"\n\n.incbin \"assets/table.bin\"\n"
+        );
+        assert_eq!(
+            std::fs::read_to_string(dir.join("asm/data/strings.s")).unwrap(),
+            ".include \"macro.inc\"\n\n.section .rodata, \"a\"\n\n.incbin \"assets/strings.bin\"\n"
+        );
+    }
+}
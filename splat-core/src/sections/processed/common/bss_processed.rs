@@ -0,0 +1,138 @@
+use std::{
+    fs,
+    io::{BufWriter, Write},
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use address_space::{AddressRange, Rom, Vram};
+use anyhow::{Context, Result};
+use spimdisasm::{
+    sections::{before_proc::NoloadSection, processed::NoloadSectionProcessed},
+    symbols::display::SymNoloadDisplaySettings,
+};
+
+use splat_segment_api::section_trait::SectionTrait;
+
+use crate::{
+    config::instance::SplatInstance,
+    sections::asm_header::{asm_file_header, section_name},
+};
+
+#[derive(Debug, Clone, PartialEq, PartialOrd, Hash)]
+pub struct CommonSegBssProcessed {
+    name: Arc<str>,
+    section_type: Arc<str>,
+    vram: AddressRange<Vram>,
+    path: PathBuf,
+
+    spimdisasm_section: NoloadSectionProcessed,
+}
+
+impl SectionTrait for CommonSegBssProcessed {
+    fn name(&self) -> Arc<str> {
+        Arc::clone(&self.name)
+    }
+
+    fn section_type(&self) -> Arc<str> {
+        Arc::clone(&self.section_type)
+    }
+
+    fn rom(&self) -> Option<AddressRange<Rom>> {
+        None
+    }
+
+    fn vram(&self) -> Option<AddressRange<Vram>> {
+        Some(self.vram)
+    }
+}
+
+impl CommonSegBssProcessed {
+    pub(crate) fn new(
+        splat_instance: &mut SplatInstance,
+        name: Arc<str>,
+        section_type: Arc<str>,
+        vram: AddressRange<Vram>,
+        dir: &Path,
+        spimdisasm_section: NoloadSection,
+    ) -> Result<Self> {
+        let spimdisasm_processed =
+            spimdisasm_section.post_process(&mut splat_instance.spimdisasm_context)?;
+
+        let extension = section_type.strip_prefix('.').unwrap_or(&section_type);
+        let path = splat_instance
+            .options
+            .data_path
+            .join(dir)
+            .join(format!("{name}.{extension}.s"));
+
+        Ok(Self {
+            name,
+            section_type,
+            vram,
+            path,
+
+            spimdisasm_section: spimdisasm_processed,
+        })
+    }
+
+    pub fn split(&self, splat_instance: &SplatInstance) -> Result<()> {
+        fs::create_dir_all(self.path.parent().context("unable to get parent dir?")?)?;
+
+        let mut writer = BufWriter::new(fs::File::create(&self.path)?);
+        write!(
+            writer,
+            "{}",
+            asm_file_header(&splat_instance.options, &section_name(&self.section_type))
+        )?;
+
+        // Every symbol is emitted as a `.space` directive of its size
+        let noload_settings = SymNoloadDisplaySettings::new();
+        for sym in self.spimdisasm_section.noload_symbols() {
+            let sym_display = sym.display(&splat_instance.spimdisasm_context, &noload_settings)?;
+            writeln!(writer, "{}", sym_display)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_utils::{project, split_project};
+
+    #[test]
+    fn test_bss_file() {
+        let dir = project(
+            "bss",
+            "",
+            "
+segments:
+  - name: main
+    type: code
+    start: 0x0
+    vram: 0x80000400
+    bss_size: 0x40
+    bss_contains_common: true
+    subsegments:
+      - [0x0, bin, main]
+      - { type: bss, vram: 0x80000420, name: main }
+  - [0x20]
+",
+            &[0; 0x20],
+        );
+        split_project(&dir, false);
+
+        let bss = std::fs::read_to_string(dir.join("asm/data/main.bss.s")).unwrap();
+        assert!(
+            bss.starts_with(".include \"macro.inc\"\n\n.section .bss, \"wa\"\n\n"),
+            "{bss}"
+        );
+
+        let script = std::fs::read_to_string(dir.join("test.ld")).unwrap();
+        assert!(
+            script.contains("build/asm/data/main.bss.s.o(.bss COMMON .scommon);"),
+            "{script}"
+        );
+    }
+}
@@ -0,0 +1,297 @@
+use std::collections::HashSet;
+
+/// Macros including a symbol still in asm, with the symbol name as their last argument
+const INCLUDE_MACROS: &[&str] = &["INCLUDE_ASM", "INCLUDE_RODATA"];
+
+/// Keywords that start a brace block at the top level which isn't a function body
+const NON_FUNCTION_KEYWORDS: &[&str] = &["struct", "union", "enum", "typedef", "extern"];
+
+/// What an existing C file already provides for its segment
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub(crate) struct CFileSymbols {
+    /// Functions with a body written in C
+    pub(crate) defined: HashSet<String>,
+    /// Symbols included from asm with `INCLUDE_ASM`, `INCLUDE_RODATA` or IDO's `GLOBAL_ASM`
+    pub(crate) included: HashSet<String>,
+}
+
+impl CFileSymbols {
+    pub(crate) fn parse(source: &str) -> Self {
+        let source = strip_comments(source);
+
+        Self {
+            defined: defined_functions(&source),
+            included: included_symbols(&source),
+        }
+    }
+
+    /// The functions written in C that `is_function` doesn't know, sorted by name
+    pub(crate) fn missing_functions(&self, is_function: impl Fn(&str) -> bool) -> Vec<&str> {
+        let mut missing: Vec<_> = self
+            .defined
+            .iter()
+            .map(String::as_str)
+            .filter(|name| !is_function(name))
+            .collect();
+        missing.sort();
+        missing
+    }
+}
+
+/// Replaces comments with spaces, leaving string and character literals untouched
+fn strip_comments(source: &str) -> String {
+    let mut stripped = String::with_capacity(source.len());
+    let mut chars = source.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '/' if chars.peek() == Some(&'/') => {
+                for c in chars.by_ref() {
+                    if c == '\n' {
+                        stripped.push('\n');
+                        break;
+                    }
+                }
+            }
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+                let mut previous = ' ';
+                for c in chars.by_ref() {
+                    if previous == '*' && c == '/' {
+                        break;
+                    }
+                    // Keep the line structure so preprocessor lines stay on their own
+                    if c == '\n' {
+                        stripped.push('\n');
+                    }
+                    previous = c;
+                }
+                stripped.push(' ');
+            }
+            '"' | '\'' => {
+                stripped.push(c);
+                let mut escaped = false;
+                for inner in chars.by_ref() {
+                    stripped.push(inner);
+                    if escaped {
+                        escaped = false;
+                    } else if inner == '\\' {
+                        escaped = true;
+                    } else if inner == c {
+                        break;
+                    }
+                }
+            }
+            _ => stripped.push(c),
+        }
+    }
+
+    stripped
+}
+
+/// Names of the functions whose body opens at the top level of the file
+fn defined_functions(source: &str) -> HashSet<String> {
+    let mut defined = HashSet::new();
+    let mut depth = 0usize;
+    let mut declaration = String::new();
+    let mut literal = None;
+    let mut escaped = false;
+
+    for line in source.lines() {
+        if depth == 0 && line.trim_start().starts_with('#') {
+            continue;
+        }
+
+        for c in line.chars().chain(std::iter::once('\n')) {
+            // Braces inside string and character literals don't open or close anything
+            if let Some(quote) = literal {
+                if escaped {
+                    escaped = false;
+                } else if c == '\\' {
+                    escaped = true;
+                } else if c == quote {
+                    literal = None;
+                }
+                continue;
+            }
+
+            match c {
+                '"' | '\'' => literal = Some(c),
+                '{' => {
+                    if depth == 0
+                        && let Some(name) = function_name(&declaration)
+                    {
+                        defined.insert(name.to_string());
+                    }
+                    depth += 1;
+                    declaration.clear();
+                }
+                '}' => {
+                    depth = depth.saturating_sub(1);
+                    declaration.clear();
+                }
+                ';' if depth == 0 => declaration.clear(),
+                _ if depth == 0 => declaration.push(c),
+                _ => {}
+            }
+        }
+    }
+
+    defined
+}
+
+/// The name of the function declared by `s32 func_80001234(s32 arg0)`, if that's what the declaration is
+fn function_name(declaration: &str) -> Option<&str> {
+    let declaration = declaration.trim();
+    let (head, _) = declaration.split_once('(')?;
+    if head.contains('=') || !declaration.ends_with(')') {
+        return None;
+    }
+
+    let first_word = head.split_whitespace().next();
+    if first_word.is_none_or(|first| NON_FUNCTION_KEYWORDS.contains(&first)) {
+        return None;
+    }
+
+    let name = head
+        .rsplit(|c: char| c.is_whitespace() || c == '*')
+        .next()?;
+    let is_identifier = !name.is_empty()
+        && !name.starts_with(|c: char| c.is_ascii_digit())
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+
+    // A declaration needs a return type before its name
+    (is_identifier && head.trim_end() != name).then_some(name)
+}
+
+fn included_symbols(source: &str) -> HashSet<String> {
+    let mut included = HashSet::new();
+
+    for macro_name in INCLUDE_MACROS {
+        for arguments in macro_arguments(source, macro_name) {
+            if let Some(name) = arguments.rsplit(',').next().map(str::trim)
+                && !name.is_empty()
+            {
+                included.insert(name.to_string());
+            }
+        }
+    }
+
+    // IDO: `GLOBAL_ASM("asm/nonmatchings/main/func_80000400.s")`
+    for arguments in macro_arguments(source, "GLOBAL_ASM") {
+        let path = arguments.trim().trim_matches('"');
+        if let Some(name) = path
+            .rsplit('/')
+            .next()
+            .and_then(|file| file.strip_suffix(".s"))
+        {
+            included.insert(name.to_string());
+        }
+    }
+
+    included
+}
+
+/// The text between the parentheses of every use of `macro_name`
+fn macro_arguments<'s>(source: &'s str, macro_name: &'s str) -> impl Iterator<Item = &'s str> {
+    source
+        .match_indices(macro_name)
+        .filter_map(move |(start, _)| {
+            let preceded_by_identifier = source[..start]
+                .chars()
+                .next_back()
+                .is_some_and(|c| c.is_ascii_alphanumeric() || c == '_');
+            if preceded_by_identifier {
+                return None;
+            }
+
+            let rest = source[start + macro_name.len()..].trim_start();
+            let arguments = rest.strip_prefix('(')?;
+            let end = arguments.find(')')?;
+            Some(&arguments[..end])
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_c_file_symbols() {
+        let symbols = CFileSymbols::parse(
+            r#"#include "common.h"
+#define MAX(a, b) ((a) > (b) ? (a) : (b))
+
+typedef struct Foo {
+    s32 x;
+} Foo;
+
+s32 D_80001000[] = { 1, 2, 3 };
+extern s32 func_80000500(s32 arg0);
+
+INCLUDE_ASM(const s32, "asm/nonmatchings/main", func_80000400);
+
+// void func_80000600(void) {}
+static s32* func_80000700(Foo* foo, s32 arg1)
+{
+    if (foo->x) {
+        return "}";
+    }
+    return NULL;
+}
+
+/* INCLUDE_ASM("asm/nonmatchings/main", func_80000800); */
+INCLUDE_ASM("asm/nonmatchings/main", func_80000900);
+INCLUDE_RODATA("asm/nonmatchings/main", D_80001100);
+
+void func_80000A00(void) {
+}
+
+#pragma GLOBAL_ASM("asm/nonmatchings/main/func_80000B00.s")
+"#,
+        );
+
+        let mut defined: Vec<_> = symbols.defined.iter().map(String::as_str).collect();
+        defined.sort();
+        assert_eq!(defined, ["func_80000700", "func_80000A00"]);
+
+        let mut included: Vec<_> = symbols.included.iter().map(String::as_str).collect();
+        included.sort();
+        assert_eq!(
+            included,
+            [
+                "D_80001100",
+                "func_80000400",
+                "func_80000900",
+                "func_80000B00"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_missing_functions() {
+        let symbols = CFileSymbols::parse(
+            r#"#include "common.h"
+
+void func_80000400(void) {
+}
+
+INCLUDE_ASM("asm/nonmatchings/main", func_80000408);
+
+s32 renamed_func(s32 arg0) {
+    return arg0 + 1;
+}
+
+void func_80000500(void) {
+}
+"#,
+        );
+        let functions = ["func_80000400", "func_80000408", "func_80000410"];
+
+        assert_eq!(
+            symbols.missing_functions(|name| functions.contains(&name)),
+            ["func_80000500", "renamed_func"]
+        );
+        assert!(symbols.missing_functions(|_| true).is_empty());
+    }
+}
@@ -0,0 +1,503 @@
+use std::{
+    collections::HashSet,
+    fs,
+    io::{BufWriter, Write},
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use address_space::{AddressRange, Rom, Vram};
+use anyhow::{Context, Result};
+use spimdisasm::{
+    rabbitizer::InstructionDisplayFlags,
+    sections::processed::FuncRodataPairing,
+    symbols::display::{FunctionDisplaySettings, SymDataDisplaySettings},
+};
+
+use splat_segment_api::section_trait::SectionTrait;
+
+use crate::{
+    config::{instance::SplatInstance, options::SplatOpts},
+    linker::{object_path, write_if_different},
+};
+
+use super::{CommonSegAsmProcessed, CommonSegRodataProcessed, c_file::CFileSymbols};
+
+#[derive(Debug, Clone, PartialEq, PartialOrd, Hash)]
+pub struct CommonSegCProcessed {
+    asm: CommonSegAsmProcessed,
+    /// The `.c`/`.cpp` file, only created if it doesn't exist yet
+    c_path: PathBuf,
+    /// Directory holding one `.s` file per function still in asm
+    asm_out_dir: PathBuf,
+    /// Extra arguments of the yaml entry, passed to the compiler when building this file only
+    compiler_flags: Vec<String>,
+}
+
+impl SectionTrait for CommonSegCProcessed {
+    fn name(&self) -> Arc<str> {
+        self.asm.name()
+    }
+
+    fn section_type(&self) -> Arc<str> {
+        self.asm.section_type()
+    }
+
+    fn rom(&self) -> Option<AddressRange<Rom>> {
+        self.asm.rom()
+    }
+
+    fn vram(&self) -> Option<AddressRange<Vram>> {
+        self.asm.vram()
+    }
+}
+
+impl CommonSegCProcessed {
+    pub(crate) fn new(
+        splat_instance: &SplatInstance,
+        asm: CommonSegAsmProcessed,
+        dir: &Path,
+        compiler_flags: Vec<String>,
+    ) -> Self {
+        let options = &splat_instance.options;
+        let name = asm.name();
+        let extension = if asm.section_type().as_ref() == "cpp" {
+            "cpp"
+        } else {
+            "c"
+        };
+
+        let c_path = options
+            .src_path
+            .join(dir)
+            .join(format!("{name}.{extension}"));
+        let asm_out_dir = options.nonmatchings_path.join(dir).join(name.as_ref());
+
+        Self {
+            asm,
+            c_path,
+            asm_out_dir,
+            compiler_flags,
+        }
+    }
+
+    pub(crate) fn asm(&self) -> &CommonSegAsmProcessed {
+        &self.asm
+    }
+
+    pub fn c_path(&self) -> &Path {
+        &self.c_path
+    }
+
+    /// Writes every function to its own file under the nonmatchings directory, along with the rodata it uses when
+    /// `migrate_rodata_to_functions` is set, then creates the C file including them if there's none yet.
+    ///
+    /// With `do_c_func_detection`, only the symbols an existing C file still includes from asm are written
+    pub fn split(
+        &self,
+        splat_instance: &SplatInstance,
+        rodata: Option<&CommonSegRodataProcessed>,
+    ) -> Result<()> {
+        let options = &splat_instance.options;
+        if options.make_full_disasm_for_code {
+            self.asm.split(splat_instance, rodata)?;
+        }
+
+        fs::create_dir_all(&self.asm_out_dir)?;
+
+        let instr_display_flags = InstructionDisplayFlags::new_gnu_as();
+        let func_settings = FunctionDisplaySettings::new(instr_display_flags)
+            .with_jtbl_label_macro(options.asm_jtbl_label_macro.clone());
+        let data_settings = SymDataDisplaySettings::new();
+        let context = &splat_instance.spimdisasm_context;
+        let functions = self.asm.spimdisasm_section().symbols();
+
+        let rodata = rodata.filter(|_| options.migrate_rodata_to_functions);
+        let rodata_section = rodata.map(|rodata| rodata.spimdisasm_section());
+        let rodata_symbols = rodata_section.map_or(&[][..], |section| section.data_symbols());
+
+        let asm_dir = self
+            .asm_out_dir
+            .strip_prefix(&options.base_path)
+            .unwrap_or(&self.asm_out_dir);
+        let mut c_lines = Vec::new();
+        let mut asm_paths = Vec::new();
+
+        let existing = self.existing_c_file(splat_instance)?;
+        let needs_asm = |name: &str| {
+            existing
+                .as_ref()
+                .is_none_or(|existing| options.disassemble_all || existing.included.contains(name))
+        };
+
+        let pairings = FuncRodataPairing::pair_sections(
+            context,
+            Some(self.asm.spimdisasm_section()),
+            rodata_section,
+        );
+        for pairing in pairings {
+            match pairing {
+                FuncRodataPairing::SingleFunction {
+                    function_index,
+                    rodata_indices,
+                    late_rodata_indices,
+                } => {
+                    let function = &functions[function_index];
+                    let name = function.name(context);
+                    c_lines.push(include_macro_line(
+                        options,
+                        "INCLUDE_ASM",
+                        &self.asm_out_dir,
+                        &name,
+                    ));
+                    if !needs_asm(&name) {
+                        continue;
+                    }
+                    asm_paths.push(asm_dir.join(format!("{name}.s")));
+
+                    let mut writer = self.create_asm_file(splat_instance, &name)?;
+
+                    if !rodata_indices.is_empty() {
+                        writeln!(writer, ".section .rodata")?;
+                        for index in rodata_indices {
+                            let sym_display =
+                                rodata_symbols[index].display(context, &data_settings)?;
+                            writeln!(writer, "{}", sym_display)?;
+                        }
+                    }
+                    if !late_rodata_indices.is_empty() {
+                        writeln!(writer, ".section .late_rodata")?;
+                        for index in late_rodata_indices {
+                            let sym_display =
+                                rodata_symbols[index].display(context, &data_settings)?;
+                            writeln!(writer, "{}", sym_display)?;
+                        }
+                    }
+                    writeln!(writer, ".section .text")?;
+                    let sym_display = function.display(context, &func_settings, &data_settings)?;
+                    writeln!(writer, "{}", sym_display)?;
+                }
+                FuncRodataPairing::SingleRodata { rodata_index } => {
+                    let sym = &rodata_symbols[rodata_index];
+                    let name = sym.name(context);
+                    c_lines.push(include_macro_line(
+                        options,
+                        "INCLUDE_RODATA",
+                        &self.asm_out_dir,
+                        &name,
+                    ));
+                    if !needs_asm(&name) {
+                        continue;
+                    }
+                    asm_paths.push(asm_dir.join(format!("{name}.s")));
+
+                    let mut writer = self.create_asm_file(splat_instance, &name)?;
+                    writeln!(writer, ".section .rodata")?;
+                    writeln!(writer, "{}", sym.display(context, &data_settings)?)?;
+                }
+            }
+        }
+
+        if options.create_c_files && !self.c_path.exists() {
+            fs::create_dir_all(self.c_path.parent().context("unable to get parent dir?")?)?;
+            fs::write(
+                &self.c_path,
+                c_file_contents(&options.generated_c_preamble, &c_lines, &options.c_newline),
+            )?;
+            log::info!("Wrote {} to {}", self.asm.name(), self.c_path.display());
+        }
+
+        if options.create_asm_dependencies && !asm_paths.is_empty() {
+            self.write_asm_dependencies(splat_instance, &asm_paths)?;
+        }
+        self.write_compiler_flags(splat_instance)?;
+
+        Ok(())
+    }
+
+    /// Writes `build/src/main.asmproc.d`, making the object of the C file depend on the asm files it includes
+    fn write_asm_dependencies(
+        &self,
+        splat_instance: &SplatInstance,
+        asm_paths: &[PathBuf],
+    ) -> Result<()> {
+        let object = object_path(&splat_instance.options, &self.c_path);
+        let dependencies_path = self.build_file(splat_instance, "asmproc.d");
+
+        write_if_different(&dependencies_path, &asm_dependencies(&object, asm_paths))
+    }
+
+    /// Writes `build/src/main.flags` with the compiler flags of the yaml entry for the build to pick up, removing
+    /// it once the entry has none
+    fn write_compiler_flags(&self, splat_instance: &SplatInstance) -> Result<()> {
+        let flags_path = self.build_file(splat_instance, "flags");
+        if !self.compiler_flags.is_empty() {
+            return write_if_different(
+                &flags_path,
+                &format!("{}\n", self.compiler_flags.join(" ")),
+            );
+        }
+
+        if flags_path.exists() {
+            fs::remove_file(&flags_path)
+                .with_context(|| format!("Failed to remove {}", flags_path.display()))?;
+        }
+        Ok(())
+    }
+
+    /// The file of the build directory with the path of the C file and the given extension
+    fn build_file(&self, splat_instance: &SplatInstance, extension: &str) -> PathBuf {
+        let options = &splat_instance.options;
+        let c_path = self
+            .c_path
+            .strip_prefix(&options.base_path)
+            .unwrap_or(&self.c_path);
+
+        options.build_path.join(c_path).with_extension(extension)
+    }
+
+    /// The symbols of the C file when `do_c_func_detection` is set and the file exists, pointing out the functions
+    /// it defines that the segment doesn't have
+    fn existing_c_file(&self, splat_instance: &SplatInstance) -> Result<Option<CFileSymbols>> {
+        if !splat_instance.options.do_c_func_detection || !self.c_path.exists() {
+            return Ok(None);
+        }
+
+        let source = fs::read_to_string(&self.c_path)
+            .with_context(|| format!("Failed to read {}", self.c_path.display()))?;
+        let existing = CFileSymbols::parse(&source);
+
+        let context = &splat_instance.spimdisasm_context;
+        let functions: HashSet<_> = self
+            .asm
+            .spimdisasm_section()
+            .symbols()
+            .iter()
+            .map(|function| function.name(context))
+            .collect();
+        for name in existing.missing_functions(|name| functions.contains(name)) {
+            log::warn!(
+                "Function {name} is defined in {} but isn't in the binary",
+                self.c_path.display()
+            );
+        }
+
+        Ok(Some(existing))
+    }
+
+    fn create_asm_file(
+        &self,
+        splat_instance: &SplatInstance,
+        sym_name: &str,
+    ) -> Result<BufWriter<fs::File>> {
+        let path = self.asm_out_dir.join(format!("{sym_name}.s"));
+        let file = fs::File::create(&path)
+            .with_context(|| format!("Failed to create {}", path.display()))?;
+
+        let mut writer = BufWriter::new(file);
+        write!(writer, "{}", splat_instance.options.asm_inc_header)?;
+        Ok(writer)
+    }
+}
+
+/// The line of the C file pulling in the asm of `sym_name`, written to `asm_out_dir`.
+///
+/// `INCLUDE_ASM("asm/nonmatchings/main", func_80000400);` with the path from `base_path`, or
+/// `INCLUDE_ASM(const s32, "main", func_80000400);` from `nonmatchings_path` for the legacy macro. Compilers
+/// without `INCLUDE_ASM` get `#pragma GLOBAL_ASM("asm/nonmatchings/main/func_80000400.s")` instead
+fn include_macro_line(
+    options: &SplatOpts,
+    macro_name: &str,
+    asm_out_dir: &Path,
+    sym_name: &str,
+) -> String {
+    let asm_dir = asm_out_dir
+        .strip_prefix(&options.base_path)
+        .unwrap_or(asm_out_dir);
+
+    if !options.compiler.uses_include_asm() {
+        let asm_path = asm_dir.join(format!("{sym_name}.s"));
+        return format!("#pragma GLOBAL_ASM(\"{}\")", asm_path.display());
+    }
+
+    if options.use_legacy_include_asm {
+        let include_dir = asm_out_dir
+            .strip_prefix(&options.nonmatchings_path)
+            .unwrap_or(asm_dir);
+        format!(
+            "{macro_name}(const s32, \"{}\", {sym_name});",
+            include_dir.display()
+        )
+    } else {
+        format!("{macro_name}(\"{}\", {sym_name});", asm_dir.display())
+    }
+}
+
+fn asm_dependencies(object: &Path, asm_paths: &[PathBuf]) -> String {
+    let mut output = format!("{}:", object.display());
+    for asm_path in asm_paths {
+        output.push_str(&format!(" \\\n    {}", asm_path.display()));
+    }
+    output.push('\n');
+
+    for asm_path in asm_paths {
+        output.push_str(&format!("{}:\n", asm_path.display()));
+    }
+
+    output
+}
+
+/// The preamble followed by the include lines, each separated by a blank line
+fn c_file_contents(preamble: &str, lines: &[String], newline: &str) -> String {
+    let mut contents: Vec<&str> = preamble.lines().collect();
+    contents.push("");
+    for line in lines {
+        contents.push(line);
+        contents.push("");
+    }
+
+    contents.join(newline)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{options, project, split_project};
+
+    #[test]
+    fn test_c_file_contents() {
+        let opts = options("platform: n64\ncompiler: GCC\nuse_legacy_include_asm: false");
+        let asm_out_dir = opts.nonmatchings_path.join("main");
+        let lines = [
+            include_macro_line(&opts, "INCLUDE_ASM", &asm_out_dir, "func_80000400"),
+            include_macro_line(&opts, "INCLUDE_RODATA", &asm_out_dir, "D_80001000"),
+        ];
+
+        assert_eq!(
+            c_file_contents("#include \"common.h\"", &lines, "\n"),
+            "#include \"common.h\"\n\nINCLUDE_ASM(\"asm/nonmatchings/main\", func_80000400);\n\nINCLUDE_RODATA(\"asm/nonmatchings/main\", D_80001000);\n"
+        );
+        assert_eq!(c_file_contents("", &[], "\r\n"), "");
+    }
+
+    #[test]
+    fn test_include_macro_line() {
+        let opts = options("platform: n64\ncompiler: GCC");
+        let asm_out_dir = opts.nonmatchings_path.join("os/thread");
+        assert_eq!(
+            include_macro_line(&opts, "INCLUDE_ASM", &asm_out_dir, "func_80000400"),
+            "INCLUDE_ASM(const s32, \"os/thread\", func_80000400);"
+        );
+
+        let opts = options("platform: n64\ncompiler: GCC\nuse_legacy_include_asm: false");
+        assert_eq!(
+            include_macro_line(&opts, "INCLUDE_RODATA", &asm_out_dir, "D_80001000"),
+            "INCLUDE_RODATA(\"asm/nonmatchings/os/thread\", D_80001000);"
+        );
+
+        let opts = options("platform: n64\ncompiler: IDO\nnonmatchings_path: funcs");
+        let asm_out_dir = opts.nonmatchings_path.join("main");
+        assert_eq!(
+            include_macro_line(&opts, "INCLUDE_ASM", &asm_out_dir, "func_80000400"),
+            "#pragma GLOBAL_ASM(\"asm/funcs/main/func_80000400.s\")"
+        );
+    }
+
+    #[test]
+    fn test_asm_dependencies() {
+        let asm_paths = [
+            PathBuf::from("asm/nonmatchings/main/func_80000400.s"),
+            PathBuf::from("asm/nonmatchings/main/D_80001000.s"),
+        ];
+
+        assert_eq!(
+            asm_dependencies(Path::new("build/src/main.c.o"), &asm_paths),
+            "build/src/main.c.o: \\\n    asm/nonmatchings/main/func_80000400.s \\\n    asm/nonmatchings/main/D_80001000.s\n\
+             asm/nonmatchings/main/func_80000400.s:\nasm/nonmatchings/main/D_80001000.s:\n"
+        );
+    }
+
+    #[test]
+    fn test_compiler_flags_file() {
+        let dir = project(
+            "c_flags",
+            "",
+            "
+segments:
+  - name: main
+    type: code
+    start: 0x0
+    vram: 0x80000400
+    subsegments:
+      - [0x0, c, main, -g -fforce-addr, -O2]
+  - [0x10]
+",
+            &[0; 0x10],
+        );
+        let config_path = dir.join("splat.yaml");
+        let flags_path = dir.join("build/src/main.flags");
+
+        split_project(&dir, false);
+        assert_eq!(
+            fs::read_to_string(&flags_path).unwrap(),
+            "-g -fforce-addr -O2\n"
+        );
+
+        let config = fs::read_to_string(&config_path).unwrap();
+        fs::write(&config_path, config.replace(", -g -fforce-addr, -O2", "")).unwrap();
+        split_project(&dir, false);
+        assert!(!flags_path.exists());
+    }
+
+    const MAIN_C: &str = "#include \"common.h\"
+
+void func_80000400(void) {
+}
+
+INCLUDE_ASM(\"asm/nonmatchings/main\", func_80000408);
+";
+
+    /// Two `jr $ra; nop` functions at 0x80000400 and 0x80000408
+    fn c_project(name: &str) -> PathBuf {
+        let rom = [
+            0x03, 0xE0, 0x00, 0x08, 0x00, 0x00, 0x00, 0x00, //
+            0x03, 0xE0, 0x00, 0x08, 0x00, 0x00, 0x00, 0x00,
+        ];
+        let dir = project(
+            name,
+            "compiler: GCC\nuse_legacy_include_asm: false",
+            "
+segments:
+  - name: main
+    type: code
+    start: 0x0
+    vram: 0x80000400
+    subsegments:
+      - [0x0, c, main]
+  - [0x10]
+",
+            &rom,
+        );
+        fs::create_dir_all(dir.join("src")).unwrap();
+        fs::write(dir.join("src/main.c"), MAIN_C).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_existing_c_file_kept() {
+        let dir = c_project("c_existing");
+
+        split_project(&dir, false);
+        assert_eq!(fs::read_to_string(dir.join("src/main.c")).unwrap(), MAIN_C);
+    }
+
+    #[test]
+    fn test_defined_functions_skip_asm() {
+        let dir = c_project("c_defined");
+        let nonmatchings = dir.join("asm/nonmatchings/main");
+
+        split_project(&dir, false);
+        assert!(!nonmatchings.join("func_80000400.s").exists());
+        assert!(nonmatchings.join("func_80000408.s").exists());
+    }
+}
@@ -1,7 +1,7 @@
 use std::{
     fs,
     io::{BufWriter, Write},
-    path::PathBuf,
+    path::{Path, PathBuf},
     sync::Arc,
 };
 
@@ -14,7 +14,10 @@ use spimdisasm::{
 
 use splat_segment_api::section_trait::SectionTrait;
 
-use crate::config::instance::SplatInstance;
+use crate::{
+    config::instance::SplatInstance,
+    sections::asm_header::{asm_file_header, section_name},
+};
 
 #[derive(Debug, Clone, PartialEq, PartialOrd, Hash)]
 pub struct CommonSegDataProcessed {
@@ -50,6 +53,7 @@ impl CommonSegDataProcessed {
         name: Arc<str>,
         section_type: Arc<str>,
         address: RomVramRange,
+        dir: &Path,
         spimdisasm_section: DataSection,
     ) -> Result<Self> {
         let spimdisasm_processed = spimdisasm_section.post_process(
@@ -57,9 +61,12 @@ impl CommonSegDataProcessed {
             &splat_instance.user_relocs,
         )?;
 
-        // TODO: self.dir in the middle
-        // options.opts.asm_path / self.dir / f"{self.name}.s"
-        let path = splat_instance.options.asm_path.join(format!("{}.s", name));
+        let extension = section_type.strip_prefix('.').unwrap_or(&section_type);
+        let path = splat_instance
+            .options
+            .data_path
+            .join(dir)
+            .join(format!("{name}.{extension}.s"));
 
         Ok(Self {
             name,
@@ -75,7 +82,11 @@ impl CommonSegDataProcessed {
         fs::create_dir_all(self.path.parent().context("unable to get parent dir?")?)?;
 
         let mut writer = BufWriter::new(fs::File::create(&self.path)?);
-        // TODO: get_asm_file_header
+        write!(
+            writer,
+            "{}",
+            asm_file_header(&splat_instance.options, &section_name(&self.section_type))
+        )?;
 
         let data_settings = SymDataDisplaySettings::new();
 
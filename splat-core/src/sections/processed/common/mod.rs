@@ -1,5 +1,14 @@
 mod asm_processed;
+mod bin_processed;
+mod bss_processed;
+mod c_file;
+mod c_processed;
 mod data_processed;
+mod rodata_processed;
 
 pub use asm_processed::CommonSegAsmProcessed;
+pub use bin_processed::CommonSegBinProcessed;
+pub use bss_processed::CommonSegBssProcessed;
+pub use c_processed::CommonSegCProcessed;
 pub use data_processed::CommonSegDataProcessed;
+pub use rodata_processed::CommonSegRodataProcessed;
@@ -0,0 +1,190 @@
+use std::{
+    fs,
+    io::{BufWriter, Write},
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use address_space::{AddressRange, Rom, RomVramRange, Vram};
+use anyhow::{Context, Result};
+use spimdisasm::{
+    sections::{
+        before_proc::DataSection,
+        processed::{DataSectionProcessed, FuncRodataPairing},
+    },
+    symbols::display::SymDataDisplaySettings,
+};
+
+use splat_segment_api::section_trait::SectionTrait;
+
+use crate::{
+    config::instance::SplatInstance,
+    sections::asm_header::{asm_file_header, section_name},
+};
+
+use super::CommonSegAsmProcessed;
+
+#[derive(Debug, Clone, PartialEq, PartialOrd, Hash)]
+pub struct CommonSegRodataProcessed {
+    name: Arc<str>,
+    section_type: Arc<str>,
+    address: RomVramRange,
+    path: PathBuf,
+
+    spimdisasm_section: DataSectionProcessed,
+}
+
+impl SectionTrait for CommonSegRodataProcessed {
+    fn name(&self) -> Arc<str> {
+        Arc::clone(&self.name)
+    }
+
+    fn section_type(&self) -> Arc<str> {
+        Arc::clone(&self.section_type)
+    }
+
+    fn rom(&self) -> Option<AddressRange<Rom>> {
+        Some(*self.address.rom())
+    }
+
+    fn vram(&self) -> Option<AddressRange<Vram>> {
+        Some(*self.address.vram())
+    }
+}
+
+impl CommonSegRodataProcessed {
+    pub(crate) fn new(
+        splat_instance: &mut SplatInstance,
+        name: Arc<str>,
+        section_type: Arc<str>,
+        address: RomVramRange,
+        dir: &Path,
+        spimdisasm_section: DataSection,
+    ) -> Result<Self> {
+        let spimdisasm_processed = spimdisasm_section.post_process(
+            &mut splat_instance.spimdisasm_context,
+            &splat_instance.user_relocs,
+        )?;
+
+        let extension = section_type.strip_prefix('.').unwrap_or(&section_type);
+        let path = splat_instance
+            .options
+            .data_path
+            .join(dir)
+            .join(format!("{name}.{extension}.s"));
+
+        Ok(Self {
+            name,
+            section_type,
+            address,
+            path,
+
+            spimdisasm_section: spimdisasm_processed,
+        })
+    }
+
+    pub(crate) fn spimdisasm_section(&self) -> &DataSectionProcessed {
+        &self.spimdisasm_section
+    }
+
+    /// Writes the rodata, leaving out the symbols migrated to the functions of the paired `text`
+    pub fn split(
+        &self,
+        splat_instance: &SplatInstance,
+        text: Option<&CommonSegAsmProcessed>,
+    ) -> Result<()> {
+        fs::create_dir_all(self.path.parent().context("unable to get parent dir?")?)?;
+
+        let mut writer = BufWriter::new(fs::File::create(&self.path)?);
+        write!(
+            writer,
+            "{}",
+            asm_file_header(&splat_instance.options, &section_name(&self.section_type))
+        )?;
+
+        let data_settings = SymDataDisplaySettings::new();
+        let symbols = self.spimdisasm_section.data_symbols();
+
+        let text = text.filter(|_| splat_instance.options.migrate_rodata_to_functions);
+        let Some(text) = text else {
+            for sym in symbols {
+                let sym_display =
+                    sym.display(&splat_instance.spimdisasm_context, &data_settings)?;
+                writeln!(writer, "{}", sym_display)?;
+            }
+            return Ok(());
+        };
+
+        let pairings = FuncRodataPairing::pair_sections(
+            &splat_instance.spimdisasm_context,
+            Some(text.spimdisasm_section()),
+            Some(&self.spimdisasm_section),
+        );
+        for pairing in pairings {
+            let FuncRodataPairing::SingleRodata { rodata_index } = pairing else {
+                continue;
+            };
+            let sym_display = symbols[rodata_index]
+                .display(&splat_instance.spimdisasm_context, &data_settings)?;
+            writeln!(writer, "{}", sym_display)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_utils::{project, split_project};
+
+    /// `"hello"` followed by `"テスト"` in Shift-JIS, each in its own rodata segment
+    const ROM: &[u8] = b"hello\0\0\0\x83\x65\x83\x58\x83\x67\0\0";
+
+    fn split_rodata(name: &str, extra_options: &str) -> [String; 2] {
+        let dir = project(
+            name,
+            extra_options,
+            "
+segments:
+  - name: main
+    type: code
+    start: 0x0
+    vram: 0x80000400
+    subsegments:
+      - [0x0, rodata, hello]
+      - [0x8, rodata, sjis]
+  - [0x10]
+",
+            ROM,
+        );
+        split_project(&dir, false);
+
+        ["hello", "sjis"].map(|file| {
+            std::fs::read_to_string(dir.join(format!("asm/data/{file}.rodata.s"))).unwrap()
+        })
+    }
+
+    #[test]
+    fn test_rodata_strings() {
+        let [hello, sjis] = split_rodata(
+            "rodata_strings_disabled",
+            "rodata_string_guesser_level: 0\nstring_encoding: SHIFT-JIS",
+        );
+        assert!(!hello.contains(".asciz"), "{hello}");
+        assert!(!sjis.contains(".asciz"), "{sjis}");
+
+        let [hello, sjis] = split_rodata(
+            "rodata_strings_ascii",
+            "rodata_string_guesser_level: 4\nstring_encoding: ASCII",
+        );
+        assert!(hello.contains(".asciz \"hello\""), "{hello}");
+        assert!(!sjis.contains(".asciz"), "{sjis}");
+
+        let [hello, sjis] = split_rodata(
+            "rodata_strings_sjis",
+            "rodata_string_guesser_level: 4\nstring_encoding: SHIFT-JIS",
+        );
+        assert!(hello.contains(".asciz \"hello\""), "{hello}");
+        assert!(sjis.contains(".asciz"), "{sjis}");
+    }
+}
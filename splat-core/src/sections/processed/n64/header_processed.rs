@@ -0,0 +1,221 @@
+use std::{
+    fmt::Write as _,
+    fs,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use address_space::{AddressRange, Rom, Vram};
+use anyhow::{Context, Result};
+
+use splat_segment_api::section_trait::SectionTrait;
+
+use crate::{
+    config::{instance::SplatInstance, options::SplatOpts},
+    n64::N64Header,
+    sections::asm_header::asm_file_header,
+};
+
+#[derive(Debug, Clone, PartialEq, PartialOrd, Hash)]
+pub struct N64SegHeaderProcessed {
+    name: Arc<str>,
+    section_type: Arc<str>,
+    rom: AddressRange<Rom>,
+    path: PathBuf,
+
+    asm: String,
+}
+
+impl SectionTrait for N64SegHeaderProcessed {
+    fn name(&self) -> Arc<str> {
+        Arc::clone(&self.name)
+    }
+
+    fn section_type(&self) -> Arc<str> {
+        Arc::clone(&self.section_type)
+    }
+
+    fn rom(&self) -> Option<AddressRange<Rom>> {
+        Some(self.rom)
+    }
+
+    fn vram(&self) -> Option<AddressRange<Vram>> {
+        None
+    }
+}
+
+impl N64SegHeaderProcessed {
+    pub(crate) fn new(
+        splat_instance: &SplatInstance,
+        name: Arc<str>,
+        section_type: Arc<str>,
+        rom: AddressRange<Rom>,
+        dir: &Path,
+        header: N64Header,
+    ) -> Result<Self> {
+        let options = &splat_instance.options;
+        let title = header.title(&options.header_encoding).with_context(|| {
+            format!(
+                "Failed to decode the title of the header with `header_encoding: {}`",
+                options.header_encoding
+            )
+        })?;
+
+        let path = options.asm_path.join(dir).join(format!("{name}.s"));
+
+        Ok(Self {
+            name,
+            section_type,
+            rom,
+            path,
+
+            asm: header_asm(options, &header, &title),
+        })
+    }
+
+    pub fn split(&self, _splat_instance: &SplatInstance) -> Result<()> {
+        fs::create_dir_all(self.path.parent().context("unable to get parent dir?")?)?;
+        fs::write(&self.path, &self.asm)
+            .with_context(|| format!("Failed to write {}", self.path.display()))?;
+
+        Ok(())
+    }
+}
+
+/// Directives reproducing the header byte for byte, each annotated with what the field is
+fn header_asm(options: &SplatOpts, header: &N64Header, title: &str) -> String {
+    let mut asm = asm_file_header(options, ".data");
+    let mut line = |directive: &str, comment: &str| {
+        writeln!(asm, "{directive} /* {comment} */").unwrap();
+    };
+
+    line(
+        &format!(".word 0x{:08X}", header.pi_bsd_config),
+        "PI BSD Domain 1 register",
+    );
+    line(
+        &format!(".word 0x{:08X}", header.clock_rate),
+        "Clockrate setting",
+    );
+    line(
+        &format!(".word 0x{:08X}", header.entrypoint),
+        "Entrypoint address",
+    );
+    line(
+        &format!(".word 0x{:08X}", header.libultra_version),
+        "libultra version",
+    );
+    line(&format!(".word 0x{:08X}", header.checksum1), "Checksum 1");
+    line(&format!(".word 0x{:08X}", header.checksum2), "Checksum 2");
+    line(&bytes_directive(&header.unknown1), "Unknown 1");
+    line(
+        &ascii_directive(&header.title),
+        // A `*/` in the title would end the comment early
+        &format!("Internal name: {}", title.replace("*/", "* /")),
+    );
+    line(&bytes_directive(&header.unknown2), "Unknown 2");
+    line(&ascii_directive(&header.game_code[..1]), "Media format");
+    line(&ascii_directive(&header.game_code[1..3]), "Cartridge ID");
+    line(
+        &ascii_directive(&header.game_code[3..]),
+        &format!("Country code: {}", header.country()),
+    );
+    line(&bytes_directive(&[header.revision]), "Revision");
+
+    asm
+}
+
+fn bytes_directive(bytes: &[u8]) -> String {
+    let bytes: Vec<_> = bytes.iter().map(|byte| format!("0x{byte:02X}")).collect();
+
+    format!(".byte {}", bytes.join(", "))
+}
+
+/// An `.ascii` string with every byte that isn't printable ASCII escaped, so the title assembles to its original
+/// bytes whatever its encoding
+fn ascii_directive(bytes: &[u8]) -> String {
+    let mut ascii = String::from(".ascii \"");
+    for byte in bytes {
+        match byte {
+            b'"' | b'\\' => write!(ascii, "\\{}", *byte as char).unwrap(),
+            0x20..=0x7E => ascii.push(*byte as char),
+            _ => write!(ascii, "\\{byte:03o}").unwrap(),
+        }
+    }
+    ascii.push('"');
+
+    ascii
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::options;
+
+    /// Assembles the directives `header_asm` emits, the way GNU as would
+    fn assemble(asm: &str) -> Vec<u8> {
+        let mut bytes = Vec::new();
+
+        for line in asm.lines() {
+            let line = line.split("/*").next().unwrap().trim();
+            if let Some(word) = line.strip_prefix(".word 0x") {
+                bytes.extend(u32::from_str_radix(word, 16).unwrap().to_be_bytes());
+            } else if let Some(list) = line.strip_prefix(".byte ") {
+                for byte in list.split(", ") {
+                    bytes.push(u8::from_str_radix(byte.trim_start_matches("0x"), 16).unwrap());
+                }
+            } else if let Some(string) = line.strip_prefix(".ascii \"") {
+                let mut chars = string.strip_suffix('"').unwrap().chars();
+                while let Some(c) = chars.next() {
+                    if c != '\\' {
+                        bytes.push(c as u8);
+                        continue;
+                    }
+                    match chars.next().unwrap() {
+                        escaped @ ('"' | '\\') => bytes.push(escaped as u8),
+                        first => {
+                            let octal: String =
+                                [first, chars.next().unwrap(), chars.next().unwrap()]
+                                    .into_iter()
+                                    .collect();
+                            bytes.push(u8::from_str_radix(&octal, 8).unwrap());
+                        }
+                    }
+                }
+            }
+        }
+
+        bytes
+    }
+
+    #[test]
+    fn test_header_roundtrip() {
+        let mut rom = vec![0; N64Header::SIZE];
+        rom[..0x18].copy_from_slice(&[
+            0x80, 0x37, 0x12, 0x40, 0x00, 0x00, 0x00, 0x0F, 0x80, 0x00, 0x04, 0x00, 0x00, 0x00,
+            0x14, 0x4B, 0x65, 0x42, 0x1E, 0xB3, 0xD1, 0x84, 0x60, 0x8F,
+        ]);
+        // "マリオ" in Shift-JIS, followed by a quote and the usual space padding
+        rom[0x20..0x34].copy_from_slice(b"\x83\x7D\x83\x8A\x83\x49\"MARIO\\       ");
+        rom[0x3B..0x3F].copy_from_slice(b"NMQJ");
+        rom[0x3F] = 1;
+
+        let header = N64Header::parse(&rom).unwrap();
+        let title = header.title("SHIFT_JIS").unwrap();
+        let asm = header_asm(&options("platform: n64"), &header, &title);
+
+        assert!(
+            asm.starts_with(".include \"macro.inc\"\n\n.section .data, \"wa\"\n\n"),
+            "{asm}"
+        );
+        assert!(
+            asm.contains("/* Internal name: マリオ\"MARIO\\ */"),
+            "{asm}"
+        );
+        assert!(
+            asm.contains(".ascii \"J\" /* Country code: Japanese */"),
+            "{asm}"
+        );
+        assert_eq!(assemble(&asm), rom);
+    }
+}
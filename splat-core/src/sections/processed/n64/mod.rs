@@ -0,0 +1,3 @@
+mod header_processed;
+
+pub use header_processed::N64SegHeaderProcessed;
@@ -0,0 +1,9 @@
+mod registry;
+mod resolved;
+mod resolver;
+mod vram_classes;
+
+pub use registry::{SectionConstructor, SegmentType, SegmentTypeImpl, SegmentTypeRegistry};
+pub use resolved::ResolvedSegment;
+pub use resolver::resolve_segments;
+pub use vram_classes::VramClassGraph;
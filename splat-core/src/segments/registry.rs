@@ -0,0 +1,495 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+use address_space::{AddressRange, Rom};
+use anyhow::{Context, Result, bail};
+
+use splat_segment_api::segment_trait::SegmentTrait;
+
+use crate::{
+    config::{
+        instance::SplatInstance,
+        options::{Platform, SplatOpts},
+    },
+    rom::TargetRom,
+    sections::{
+        Section,
+        before_proc::{
+            common::{
+                CommonSegAsm, CommonSegBin, CommonSegBss, CommonSegC, CommonSegData,
+                CommonSegRodata,
+            },
+            n64::N64SegHeader,
+        },
+    },
+};
+
+use super::ResolvedSegment;
+
+/// Builds the section of a leaf segment, given the segment and its top level parent
+pub type SectionConstructor =
+    fn(&mut SplatInstance, &TargetRom, &ResolvedSegment, &ResolvedSegment) -> Result<Section>;
+
+/// What splat knows about a segment `type` of the yaml
+#[derive(Debug, Clone, Copy)]
+pub struct SegmentType {
+    /// `None` for types grouping other segments, or that are recognized but have nothing to split yet
+    pub constructor: Option<SectionConstructor>,
+    /// Linker sections the type outputs, in order. Empty for groups, which follow the `section_order` option
+    pub section_order: &'static [&'static str],
+    /// `--modes` categories the outputs of the type belong to
+    pub modes: &'static [&'static str],
+}
+
+impl SegmentType {
+    const fn new(
+        constructor: Option<SectionConstructor>,
+        section_order: &'static [&'static str],
+        modes: &'static [&'static str],
+    ) -> Self {
+        Self {
+            constructor,
+            section_order,
+            modes,
+        }
+    }
+
+    pub fn is_mode_active(&self, options: &SplatOpts) -> bool {
+        self.modes.iter().any(|mode| options.is_mode_active(mode))
+    }
+
+    /// Known types that have outputs but that splat can't split yet, unlike groups and `pad`s that have none
+    pub fn is_unimplemented(&self) -> bool {
+        self.constructor.is_none() && !self.modes.is_empty()
+    }
+}
+
+/// Where the implementation of a segment type comes from
+#[derive(Debug, Clone)]
+pub enum SegmentTypeImpl<'r> {
+    Native(&'r SegmentType),
+    /// A plugin from `extensions_path`
+    Plugin(PathBuf),
+}
+
+const ALL_SECTIONS: &[&str] = &[".text", ".data", ".rodata", ".bss"];
+
+const CODE: &[&str] = &["code"];
+const DATA: &[&str] = &["data"];
+const BIN: &[&str] = &["bin"];
+const IMG: &[&str] = &["img"];
+
+const COMMON_TYPES: &[(&str, SegmentType)] = &[
+    ("asm", SegmentType::new(Some(new_asm), &[".text"], CODE)),
+    ("hasm", SegmentType::new(Some(new_asm), &[".text"], CODE)),
+    ("c", SegmentType::new(Some(new_c), ALL_SECTIONS, CODE)),
+    ("cpp", SegmentType::new(Some(new_c), ALL_SECTIONS, CODE)),
+    ("data", SegmentType::new(Some(new_data), &[".data"], DATA)),
+    ("sdata", SegmentType::new(None, &[".sdata"], DATA)),
+    (
+        "rodata",
+        SegmentType::new(Some(new_rodata), &[".rodata"], DATA),
+    ),
+    (
+        "rdata",
+        SegmentType::new(Some(new_rodata), &[".rdata"], DATA),
+    ),
+    ("bss", SegmentType::new(Some(new_bss), &[".bss"], DATA)),
+    ("sbss", SegmentType::new(Some(new_bss), &[".sbss"], DATA)),
+    ("eh_frame", SegmentType::new(None, &[".eh_frame"], DATA)),
+    (
+        "gcc_except_table",
+        SegmentType::new(None, &[".gcc_except_table"], DATA),
+    ),
+    ("bin", SegmentType::new(Some(new_bin), &[".data"], BIN)),
+    ("textbin", SegmentType::new(Some(new_bin), &[".text"], BIN)),
+    ("databin", SegmentType::new(Some(new_bin), &[".data"], BIN)),
+    (
+        "rodatabin",
+        SegmentType::new(Some(new_bin), &[".rodata"], BIN),
+    ),
+    ("header", SegmentType::new(None, &[".data"], CODE)),
+    ("lib", SegmentType::new(None, &[".text"], &[])),
+    ("code", SegmentType::new(None, &[], &[])),
+    ("group", SegmentType::new(None, &[], &[])),
+    ("linker_offset", SegmentType::new(None, &[], &[])),
+    ("pad", SegmentType::new(None, &[], &[])),
+    ("stub", SegmentType::new(None, &[], &[])),
+];
+
+/// Replace the common types of the same name
+const N64_TYPES: &[(&str, SegmentType)] = &[
+    (
+        "header",
+        SegmentType::new(Some(new_n64_header), &[".data"], CODE),
+    ),
+    ("ipl3", SegmentType::new(None, &[".text"], CODE)),
+    ("rsp", SegmentType::new(None, &[".text"], CODE)),
+    ("decompressor", SegmentType::new(None, &[".text"], CODE)),
+    ("mio0", SegmentType::new(None, &[".data"], BIN)),
+    ("yay0", SegmentType::new(None, &[".data"], BIN)),
+    ("img", SegmentType::new(None, &[".data"], IMG)),
+    ("ci", SegmentType::new(None, &[".data"], IMG)),
+    ("ci4", SegmentType::new(None, &[".data"], IMG)),
+    ("ci8", SegmentType::new(None, &[".data"], IMG)),
+    ("i1", SegmentType::new(None, &[".data"], IMG)),
+    ("i4", SegmentType::new(None, &[".data"], IMG)),
+    ("i8", SegmentType::new(None, &[".data"], IMG)),
+    ("ia4", SegmentType::new(None, &[".data"], IMG)),
+    ("ia8", SegmentType::new(None, &[".data"], IMG)),
+    ("ia16", SegmentType::new(None, &[".data"], IMG)),
+    ("rgba16", SegmentType::new(None, &[".data"], IMG)),
+    ("rgba32", SegmentType::new(None, &[".data"], IMG)),
+    ("palette", SegmentType::new(None, &[".data"], IMG)),
+    ("gfx", SegmentType::new(None, &[".data"], &["gfx"])),
+    ("vtx", SegmentType::new(None, &[".data"], &["vtx"])),
+    ("linker", SegmentType::new(None, &[], &[])),
+];
+
+/// Maps the `type` of yaml segments to their implementation.
+///
+/// Native types take precedence over plugins of the same name, unless `allow_segment_overrides` is set.
+#[derive(Debug, Clone)]
+pub struct SegmentTypeRegistry {
+    types: HashMap<String, SegmentType>,
+    /// The `.wasm` files of `extensions_path`, by file stem
+    plugins: HashMap<String, PathBuf>,
+    allow_segment_overrides: bool,
+}
+
+impl SegmentTypeRegistry {
+    /// The native types available for the platform of `options`
+    pub fn new(options: &SplatOpts) -> Self {
+        let platform_types = match options.platform {
+            Platform::N64 => N64_TYPES,
+            _ => &[],
+        };
+        let types = COMMON_TYPES
+            .iter()
+            .chain(platform_types)
+            .map(|(name, segment_type)| (name.to_string(), *segment_type))
+            .collect();
+
+        Self {
+            types,
+            plugins: options
+                .extensions_path
+                .as_deref()
+                .map(find_plugins)
+                .unwrap_or_default(),
+            allow_segment_overrides: options.allow_segment_overrides,
+        }
+    }
+
+    /// Adds a native type, replacing a built-in one only if `allow_segment_overrides` is set
+    pub fn register(&mut self, name: impl Into<String>, segment_type: SegmentType) -> Result<()> {
+        let name = name.into();
+        if self.types.contains_key(&name) && !self.allow_segment_overrides {
+            bail!(
+                "Segment type `{name}` is already registered, set `allow_segment_overrides` to replace it"
+            );
+        }
+
+        self.types.insert(name, segment_type);
+        Ok(())
+    }
+
+    pub fn lookup(&self, segment_type: &str) -> Result<SegmentTypeImpl<'_>> {
+        // `.data`, `.rodata`, etc. refer to a section of the sibling segment with the same name
+        let segment_type = segment_type.strip_prefix('.').unwrap_or(segment_type);
+
+        let native = self.types.get(segment_type).map(SegmentTypeImpl::Native);
+        let plugin = || {
+            self.plugins
+                .get(segment_type)
+                .cloned()
+                .map(SegmentTypeImpl::Plugin)
+        };
+        let found = match self.allow_segment_overrides {
+            true => plugin().or(native),
+            false => native.or_else(plugin),
+        };
+
+        found.with_context(|| {
+            format!(
+                "Unknown segment type `{segment_type}` (hint: check that `extensions_path` points to the directory of your extensions)"
+            )
+        })
+    }
+
+    pub fn is_known(&self, segment_type: &str) -> bool {
+        self.lookup(segment_type).is_ok()
+    }
+}
+
+/// The `.wasm` files of the extensions directory, keyed by the segment type they implement
+fn find_plugins(extensions_path: &Path) -> HashMap<String, PathBuf> {
+    let Ok(entries) = std::fs::read_dir(extensions_path) else {
+        return HashMap::new();
+    };
+
+    entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "wasm"))
+        .filter_map(|path| Some((path.file_stem()?.to_str()?.to_string(), path)))
+        .collect()
+}
+
+/// The bytes of a leaf segment, along with where they are in the ROM
+fn segment_bytes<'r>(
+    rom: &'r TargetRom,
+    segment: &ResolvedSegment,
+) -> Result<(&'r [u8], AddressRange<Rom>)> {
+    let name = segment.name();
+    let rom_range = segment
+        .rom()
+        .with_context(|| format!("Segment `{name}` has no ROM address"))?;
+    let raw_bytes = rom
+        .bytes
+        .get(rom_range.start().inner() as usize..rom_range.end().inner() as usize)
+        .with_context(|| format!("Segment `{name}` goes past the end of the target binary"))?;
+
+    Ok((raw_bytes, rom_range))
+}
+
+/// The bytes and addresses of a leaf segment
+fn segment_contents<'r>(
+    rom: &'r TargetRom,
+    segment: &ResolvedSegment,
+) -> Result<(&'r [u8], u32, u32)> {
+    let (raw_bytes, rom_range) = segment_bytes(rom, segment)?;
+    let vram_start = segment
+        .vram_start()
+        .with_context(|| format!("Segment `{}` has no VRAM address", segment.name()))?
+        .inner();
+
+    Ok((raw_bytes, rom_range.start().inner(), vram_start))
+}
+
+fn new_asm(
+    splat_instance: &mut SplatInstance,
+    rom: &TargetRom,
+    segment: &ResolvedSegment,
+    most_parent: &ResolvedSegment,
+) -> Result<Section> {
+    let (raw_bytes, start, vram_start) = segment_contents(rom, segment)?;
+
+    Ok(Section::Asm(CommonSegAsm::new(
+        splat_instance,
+        segment.name(),
+        segment.segment_type(),
+        raw_bytes,
+        start,
+        vram_start,
+        most_parent,
+        segment.dir().to_path_buf(),
+        &(),
+        &(),
+    )?))
+}
+
+fn new_c(
+    splat_instance: &mut SplatInstance,
+    rom: &TargetRom,
+    segment: &ResolvedSegment,
+    most_parent: &ResolvedSegment,
+) -> Result<Section> {
+    let (raw_bytes, start, vram_start) = segment_contents(rom, segment)?;
+    let compiler_flags = segment
+        .yaml()
+        .compiler_flags()
+        .with_context(|| format!("Segment `{}` has invalid arguments", segment.name()))?;
+
+    Ok(Section::C(CommonSegC::new(
+        splat_instance,
+        segment.name(),
+        segment.segment_type(),
+        raw_bytes,
+        start,
+        vram_start,
+        most_parent,
+        segment.dir().to_path_buf(),
+        compiler_flags,
+    )?))
+}
+
+fn new_data(
+    splat_instance: &mut SplatInstance,
+    rom: &TargetRom,
+    segment: &ResolvedSegment,
+    most_parent: &ResolvedSegment,
+) -> Result<Section> {
+    let (raw_bytes, start, vram_start) = segment_contents(rom, segment)?;
+
+    Ok(Section::Data(CommonSegData::new(
+        splat_instance,
+        segment.name(),
+        segment.segment_type(),
+        raw_bytes,
+        start,
+        vram_start,
+        most_parent,
+        segment.dir().to_path_buf(),
+        &(),
+        &(),
+    )?))
+}
+
+fn new_rodata(
+    splat_instance: &mut SplatInstance,
+    rom: &TargetRom,
+    segment: &ResolvedSegment,
+    most_parent: &ResolvedSegment,
+) -> Result<Section> {
+    let (raw_bytes, start, vram_start) = segment_contents(rom, segment)?;
+
+    Ok(Section::Rodata(CommonSegRodata::new(
+        splat_instance,
+        segment.name(),
+        segment.segment_type(),
+        raw_bytes,
+        start,
+        vram_start,
+        most_parent,
+        segment.dir().to_path_buf(),
+    )?))
+}
+
+fn new_bin(
+    _splat_instance: &mut SplatInstance,
+    rom: &TargetRom,
+    segment: &ResolvedSegment,
+    _most_parent: &ResolvedSegment,
+) -> Result<Section> {
+    let (raw_bytes, rom_range) = segment_bytes(rom, segment)?;
+
+    Ok(Section::Bin(CommonSegBin::new(
+        segment.name(),
+        segment.segment_type(),
+        raw_bytes,
+        rom_range,
+        segment.vram_start(),
+        segment.dir().to_path_buf(),
+    )))
+}
+
+fn new_n64_header(
+    _splat_instance: &mut SplatInstance,
+    rom: &TargetRom,
+    segment: &ResolvedSegment,
+    _most_parent: &ResolvedSegment,
+) -> Result<Section> {
+    let (raw_bytes, rom_range) = segment_bytes(rom, segment)?;
+
+    Ok(Section::N64Header(N64SegHeader::new(
+        segment.name(),
+        segment.segment_type(),
+        raw_bytes,
+        rom_range,
+        segment.dir().to_path_buf(),
+    )))
+}
+
+fn new_bss(
+    splat_instance: &mut SplatInstance,
+    _rom: &TargetRom,
+    segment: &ResolvedSegment,
+    most_parent: &ResolvedSegment,
+) -> Result<Section> {
+    let name = segment.name();
+    let vram_start = segment
+        .vram_start()
+        .with_context(|| format!("Segment `{name}` has no VRAM address"))?
+        .inner();
+    let bss_size = segment
+        .bss_size()
+        .with_context(|| format!("Unable to determine the size of the bss segment `{name}`"))?
+        .inner();
+
+    Ok(Section::Bss(CommonSegBss::new(
+        splat_instance,
+        name,
+        segment.segment_type(),
+        vram_start,
+        bss_size,
+        most_parent,
+        segment.dir().to_path_buf(),
+    )?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{options, temp_dir};
+
+    fn native<'r>(registry: &'r SegmentTypeRegistry, segment_type: &str) -> &'r SegmentType {
+        match registry.lookup(segment_type).unwrap() {
+            SegmentTypeImpl::Native(segment_type) => segment_type,
+            SegmentTypeImpl::Plugin(path) => panic!("unexpected plugin {}", path.display()),
+        }
+    }
+
+    #[test]
+    fn test_builtin_types() {
+        let registry = SegmentTypeRegistry::new(&options("platform: n64"));
+
+        assert_eq!(native(&registry, "hasm").modes, ["code"]);
+        assert_eq!(native(&registry, ".data").section_order, [".data"]);
+        assert!(native(&registry, "rgba16").is_unimplemented());
+        assert!(native(&registry, "ipl3").is_unimplemented());
+        assert!(!native(&registry, "asm").is_unimplemented());
+        assert!(!native(&registry, "group").is_unimplemented());
+        assert!(!native(&registry, "pad").is_unimplemented());
+        assert_eq!(native(&registry, "rodatabin").section_order, [".rodata"]);
+        assert!(native(&registry, "textbin").constructor.is_some());
+        assert!(!registry.is_known("pm_sprites"));
+
+        let psx = SegmentTypeRegistry::new(&options("platform: psx"));
+        assert!(psx.is_known("asm"));
+        assert!(!psx.is_known("rgba16"));
+    }
+
+    #[test]
+    fn test_overrides() {
+        let custom = SegmentType::new(None, &[".data"], &["img"]);
+
+        let mut registry = SegmentTypeRegistry::new(&options("platform: n64"));
+        registry.register("pm_sprites", custom).unwrap();
+        assert_eq!(native(&registry, "pm_sprites").modes, ["img"]);
+        let err = registry.register("asm", custom).unwrap_err();
+        assert!(err.to_string().contains("allow_segment_overrides"));
+
+        let mut registry =
+            SegmentTypeRegistry::new(&options("platform: n64\nallow_segment_overrides: true"));
+        registry.register("asm", custom).unwrap();
+        assert_eq!(native(&registry, "asm").modes, ["img"]);
+    }
+
+    #[test]
+    fn test_plugins() {
+        let dir = temp_dir("plugins");
+        for file in ["pm_sprites.wasm", "asm.wasm", "notes.txt"] {
+            std::fs::write(dir.join(file), []).unwrap();
+        }
+        let extensions = format!("platform: n64\nextensions_path: {}", dir.display());
+
+        let registry = SegmentTypeRegistry::new(&options(&extensions));
+        assert!(matches!(
+            registry.lookup("pm_sprites").unwrap(),
+            SegmentTypeImpl::Plugin(path) if path == dir.join("pm_sprites.wasm")
+        ));
+        assert!(!registry.is_known("notes"));
+        assert_eq!(native(&registry, "asm").modes, ["code"]);
+
+        let registry = SegmentTypeRegistry::new(&options(&format!(
+            "{extensions}\nallow_segment_overrides: true"
+        )));
+        assert!(matches!(
+            registry.lookup("asm").unwrap(),
+            SegmentTypeImpl::Plugin(_)
+        ));
+    }
+}
@@ -0,0 +1,89 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use address_space::{AddressRange, Rom, Size, Vram};
+
+use splat_segment_api::segment_trait::{SegmentGroup, SegmentTrait};
+
+use crate::{config::options::SplatOpts, yaml::YamlSegment};
+
+/// A segment from the yaml with its final ROM and VRAM placement worked out
+#[derive(Debug, Clone)]
+pub struct ResolvedSegment<'a> {
+    pub(crate) name: Arc<str>,
+    pub(crate) segment_type: Arc<str>,
+    pub(crate) rom: AddressRange<Rom>,
+    pub(crate) vram_start: Option<Vram>,
+    pub(crate) bss_size: Option<Size>,
+    pub(crate) vram_class: Option<Arc<str>>,
+    /// The `bss_contains_common` attribute of the segment or its closest parent that sets it
+    pub(crate) bss_contains_common: Option<bool>,
+    /// Directory of the outputs, inside the one of the parent
+    pub(crate) dir: PathBuf,
+    pub(crate) subsegments: Vec<ResolvedSegment<'a>>,
+
+    pub(crate) yaml: &'a YamlSegment,
+}
+
+impl<'a> ResolvedSegment<'a> {
+    pub fn segment_type(&self) -> Arc<str> {
+        Arc::clone(&self.segment_type)
+    }
+
+    pub fn vram_class(&self) -> Option<Arc<str>> {
+        self.vram_class.clone()
+    }
+
+    /// Whether the linker places the `COMMON` symbols of the object in its `.bss`, falling back to the
+    /// `ld_bss_contains_common` option when no parent sets it either
+    pub fn bss_contains_common(&self, options: &SplatOpts) -> bool {
+        self.bss_contains_common
+            .unwrap_or(options.ld_bss_contains_common)
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    pub fn subsegments(&self) -> &[ResolvedSegment<'a>] {
+        &self.subsegments
+    }
+
+    pub fn yaml(&self) -> &'a YamlSegment {
+        self.yaml
+    }
+
+    /// Translates a ROM address inside this segment into its VRAM address
+    pub fn rom_to_vram(&self, rom: Rom) -> Option<Vram> {
+        let vram_start = self.vram_start?;
+        let offset = rom.inner().checked_sub(self.rom.start().inner())?;
+
+        Some(vram_start.add_size(&Size::new(offset)))
+    }
+}
+
+impl SegmentTrait for ResolvedSegment<'_> {
+    fn name(&self) -> Arc<str> {
+        Arc::clone(&self.name)
+    }
+
+    fn rom(&self) -> Option<AddressRange<Rom>> {
+        Some(self.rom)
+    }
+
+    fn vram_start(&self) -> Option<Vram> {
+        self.vram_start
+    }
+
+    fn bss_size(&self) -> Option<Size> {
+        self.bss_size
+    }
+}
+
+impl SegmentGroup for ResolvedSegment<'_> {
+    fn overlay_category_name(&self) -> Option<Arc<str>> {
+        self.vram_class.clone()
+    }
+}
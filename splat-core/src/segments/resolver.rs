@@ -0,0 +1,415 @@
+use std::{collections::HashMap, path::PathBuf, sync::Arc};
+
+use address_space::{AddressRange, Rom, Size, Vram};
+use anyhow::{Context, Result, bail};
+
+use splat_segment_api::segment_trait::SegmentTrait;
+
+use crate::yaml::{SplatYaml, YamlSegment};
+
+use super::{ResolvedSegment, VramClassGraph};
+
+/// Walks the yaml segment tree and computes the ROM range, VRAM start and bss size of every segment.
+///
+/// `auto` starts are inferred from the end of the previous sibling (or the start of the parent for the
+/// first subsegment), while ends are taken from the next sibling that has a known start, falling back to
+/// the end of the parent. Single-element end markers such as `[0x2800000]` only delimit their previous
+/// sibling and don't produce a segment.
+pub fn resolve_segments(splat_yaml: &SplatYaml) -> Result<Vec<ResolvedSegment<'_>>> {
+    let mut resolver = Resolver {
+        vram_classes: VramClassGraph::new(splat_yaml.vram_classes.as_deref().unwrap_or_default())?,
+        vram_ends: HashMap::new(),
+    };
+
+    resolver.resolve_siblings(&splat_yaml.segments, None)
+}
+
+struct Resolver<'a> {
+    vram_classes: VramClassGraph<'a>,
+    /// VRAM end of every top level segment resolved so far, used by `follows_vram`
+    vram_ends: HashMap<&'a str, Vram>,
+}
+
+impl<'a> Resolver<'a> {
+    fn resolve_siblings(
+        &mut self,
+        segments: &'a [YamlSegment],
+        parent: Option<&ResolvedSegment<'a>>,
+    ) -> Result<Vec<ResolvedSegment<'a>>> {
+        let parent_range = parent.map(|p| p.rom);
+        let mut resolved = Vec::new();
+        let mut last_rom_end = parent_range.map_or(0, |r| u64::from(r.start().inner()));
+
+        for (i, yaml) in segments.iter().enumerate() {
+            if yaml.is_end_marker() {
+                if let Some(rom) = yaml.rom {
+                    last_rom_end = rom;
+                }
+                continue;
+            }
+
+            let start = yaml.rom.unwrap_or(last_rom_end);
+            let name: Arc<str> = match &yaml.name {
+                Some(name) => name.as_str().into(),
+                None => format!("{start:X}").into(),
+            };
+
+            let end = match segments.get(i + 1).and_then(|next| next.rom) {
+                Some(end) => Some(end),
+                None => yaml
+                    .size
+                    .map(|size| start + size)
+                    .or_else(|| segments[i + 1..].iter().find_map(|next| next.rom))
+                    .or_else(|| parent_range.map(|r| u64::from(r.end().inner()))),
+            };
+            let Some(end) = end else {
+                bail!(
+                    "Unable to determine where segment `{name}` ends. The last segment must be followed by an end marker, such as `- [0x{start:X}]`"
+                );
+            };
+            // Out of order starts are reported by the validator, here we just avoid producing a negative range.
+            // This also happens for `auto` segments whose size depends on their type, like images
+            let end = end.max(start);
+
+            let rom = AddressRange::new(to_rom(start, &name)?, to_rom(end, &name)?);
+            let vram_class = match (&yaml.vram_class, parent) {
+                (Some(class), _) => Some(class.as_str().into()),
+                (None, Some(parent)) => parent.vram_class.clone(),
+                (None, None) => None,
+            };
+            let bss_contains_common = yaml
+                .bss_contains_common
+                .or_else(|| parent.and_then(|parent| parent.bss_contains_common));
+            let dir = match (&yaml.dir, parent) {
+                (Some(dir), Some(parent)) => parent.dir.join(dir),
+                (Some(dir), None) => dir.clone(),
+                (None, Some(parent)) => parent.dir.clone(),
+                (None, None) => PathBuf::new(),
+            };
+            let vram_start = self.vram_start(yaml, &name, rom.start(), parent)?;
+            let bss_size = match yaml.bss_size {
+                Some(bss_size) => Some(Size::new(u32::try_from(bss_size).with_context(|| {
+                    format!("bss_size 0x{bss_size:X} of segment `{name}` is too big")
+                })?)),
+                None => None,
+            };
+
+            let mut segment = ResolvedSegment {
+                name,
+                segment_type: yaml.segment_type.as_str().into(),
+                rom,
+                vram_start,
+                bss_size,
+                vram_class,
+                bss_contains_common,
+                dir,
+                subsegments: Vec::new(),
+                yaml,
+            };
+            if let Some(subsegments) = &yaml.subsegments {
+                segment.subsegments = self.resolve_siblings(subsegments, Some(&segment))?;
+            }
+
+            if parent.is_none()
+                && let (Some(yaml_name), Some(vram_end)) = (&yaml.name, segment.vram_end())
+            {
+                self.vram_ends.insert(yaml_name, vram_end);
+            }
+
+            last_rom_end = end;
+            resolved.push(segment);
+        }
+
+        if let Some(parent) = parent {
+            size_bss_subsegments(&mut resolved, parent);
+        }
+
+        Ok(resolved)
+    }
+
+    fn vram_start(
+        &self,
+        yaml: &YamlSegment,
+        name: &str,
+        rom_start: Rom,
+        parent: Option<&ResolvedSegment>,
+    ) -> Result<Option<Vram>> {
+        if let Some(vram) = yaml.vram {
+            return Ok(Some(to_vram(vram, name)?));
+        }
+
+        if let Some(parent) = parent {
+            return Ok(parent.rom_to_vram(rom_start));
+        }
+
+        if let Some(follows) = &yaml.follows_vram {
+            let vram_end = self.vram_ends.get(follows.as_str()).with_context(|| {
+                format!(
+                    "Segment `{name}` follows the vram of `{follows}`, which is not a top level segment listed before it"
+                )
+            })?;
+            return Ok(Some(*vram_end));
+        }
+
+        if let Some(class_name) = &yaml.vram_class {
+            let class = self.vram_classes.get(class_name).with_context(|| {
+                format!("Segment `{name}` uses the unknown vram_class `{class_name}`")
+            })?;
+            return Ok(Some(to_vram(class.vram, name)?));
+        }
+
+        Ok(None)
+    }
+}
+
+/// bss subsegments take up the VRAM until the next sibling that starts after them, or the end of their parent
+fn size_bss_subsegments(siblings: &mut [ResolvedSegment], parent: &ResolvedSegment) {
+    for i in 0..siblings.len() {
+        let segment = &siblings[i];
+        let is_bss = matches!(
+            segment
+                .segment_type
+                .strip_prefix('.')
+                .unwrap_or(&segment.segment_type),
+            "bss" | "sbss"
+        );
+        if !is_bss || segment.bss_size.is_some() || segment.rom.size().inner() != 0 {
+            continue;
+        }
+        let Some(vram_start) = segment.vram_start else {
+            continue;
+        };
+
+        let vram_end = siblings[i + 1..]
+            .iter()
+            .filter_map(|next| next.vram_start)
+            .find(|next| *next > vram_start)
+            .or_else(|| parent.vram_end());
+        if let Some(vram_end) = vram_end.filter(|end| *end > vram_start) {
+            siblings[i].bss_size = Some(Size::new(vram_end.inner() - vram_start.inner()));
+        }
+    }
+}
+
+fn to_rom(address: u64, name: &str) -> Result<Rom> {
+    let address = u32::try_from(address)
+        .with_context(|| format!("ROM address 0x{address:X} of segment `{name}` is too big"))?;
+    Ok(Rom::new(address))
+}
+
+fn to_vram(address: u64, name: &str) -> Result<Vram> {
+    let address = u32::try_from(address)
+        .with_context(|| format!("VRAM address 0x{address:X} of segment `{name}` is too big"))?;
+    Ok(Vram::new(address))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::*;
+    use crate::{
+        test_utils::{options, splat_yaml},
+        yaml::load_yaml,
+    };
+
+    #[test]
+    fn test_auto_subsegments() {
+        let yaml = splat_yaml(
+            "
+segments:
+  - name: main
+    type: code
+    start: 0x1000
+    vram: 0x80000400
+    bss_size: 0x20
+    subsegments:
+      - [auto, c, a]
+      - [0x1100, c, b]
+      - [auto, c, c]
+      - [0x1300, data, d]
+  - [0x1400]
+",
+        );
+        let segments = resolve_segments(&yaml).unwrap();
+        assert_eq!(segments.len(), 1);
+
+        let main = &segments[0];
+        assert_eq!(
+            main.rom(),
+            Some(AddressRange::new(Rom::new(0x1000), Rom::new(0x1400)))
+        );
+        assert_eq!(main.vram_end(), Some(Vram::new(0x80000820)));
+
+        let subs: Vec<_> = main
+            .subsegments()
+            .iter()
+            .map(|s| {
+                (
+                    s.rom.start().inner(),
+                    s.rom.end().inner(),
+                    s.vram_start.unwrap().inner(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            subs,
+            [
+                (0x1000, 0x1100, 0x80000400),
+                (0x1100, 0x1300, 0x80000500),
+                (0x1300, 0x1300, 0x80000700),
+                (0x1300, 0x1400, 0x80000700),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_segment_dirs() {
+        let yaml = splat_yaml(
+            "
+segments:
+  - name: main
+    type: code
+    dir: game
+    start: 0x1000
+    vram: 0x80000400
+    subsegments:
+      - [0x1000, c, a]
+      - { start: 0x1100, type: c, name: os/nusys/nugfxtaskmgr, dir: lib }
+  - name: boot
+    type: bin
+    start: 0x1200
+  - [0x1400]
+",
+        );
+        let segments = resolve_segments(&yaml).unwrap();
+
+        let dirs: Vec<_> = segments[0]
+            .subsegments()
+            .iter()
+            .map(|s| s.dir().join(format!("{}.c", s.name())))
+            .collect();
+        assert_eq!(
+            dirs,
+            [
+                Path::new("game/a.c"),
+                Path::new("game/lib/os/nusys/nugfxtaskmgr.c")
+            ]
+        );
+        assert_eq!(segments[1].dir(), Path::new(""));
+    }
+
+    #[test]
+    fn test_bss_subsegments() {
+        let yaml = splat_yaml(
+            "
+segments:
+  - name: main
+    type: code
+    start: 0x1000
+    vram: 0x80000400
+    bss_size: 0x300
+    subsegments:
+      - [0x1000, asm, a]
+      - { type: bss, vram: 0x80000500, name: a }
+      - { type: .bss, vram: 0x80000600, name: b }
+  - [0x1100]
+",
+        );
+        let segments = resolve_segments(&yaml).unwrap();
+
+        let bss: Vec<_> = segments[0].subsegments()[1..]
+            .iter()
+            .map(|s| {
+                (
+                    s.rom().unwrap().size().inner(),
+                    s.vram_start.unwrap().inner(),
+                    s.vram_end().unwrap().inner(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            bss,
+            [(0, 0x80000500, 0x80000600), (0, 0x80000600, 0x80000800)]
+        );
+    }
+
+    #[test]
+    fn test_bss_contains_common() {
+        let yaml = splat_yaml(
+            "
+segments:
+  - name: main
+    type: code
+    start: 0x1000
+    vram: 0x80000400
+    bss_size: 0x100
+    bss_contains_common: true
+    subsegments:
+      - name: lib
+        type: group
+        start: 0x1000
+        subsegments:
+          - [0x1000, asm, a]
+          - { type: bss, vram: 0x80000500, name: a, bss_contains_common: false }
+      - { type: bss, vram: 0x80000500, name: b }
+  - name: other
+    type: code
+    start: 0x1100
+    vram: 0x80000500
+    subsegments:
+      - [0x1100, asm, c]
+  - [0x1200]
+",
+        );
+        let segments = resolve_segments(&yaml).unwrap();
+        let opts = options("platform: n64");
+
+        let lib = &segments[0].subsegments()[0];
+        assert!(lib.subsegments()[0].bss_contains_common(&opts));
+        assert!(!lib.subsegments()[1].bss_contains_common(&opts));
+        assert!(segments[0].subsegments()[1].bss_contains_common(&opts));
+        assert!(!segments[1].subsegments()[0].bss_contains_common(&opts));
+        let opts = options("platform: n64\nld_bss_contains_common: true");
+        assert!(segments[1].subsegments()[0].bss_contains_common(&opts));
+    }
+
+    #[test]
+    fn test_stub_segments() {
+        let yaml = splat_yaml(
+            "
+segments:
+  - [0x0, stub]
+  - { start: 0x100, type: stub, name: unused }
+  - [0x200]
+",
+        );
+        let segments = resolve_segments(&yaml).unwrap();
+
+        let stubs: Vec<_> = segments
+            .iter()
+            .map(|s| (s.name(), s.rom.start().inner(), s.rom.end().inner()))
+            .collect();
+        assert_eq!(
+            stubs,
+            [("0".into(), 0x0, 0x100), ("unused".into(), 0x100, 0x200)]
+        );
+    }
+
+    #[test]
+    fn test_missing_end() {
+        let yaml = splat_yaml("segments:\n  - [0x0, header, header]\n");
+        assert!(resolve_segments(&yaml).is_err());
+    }
+
+    #[test]
+    fn test_resolve_test_data() {
+        let yaml = load_yaml(Path::new("test_data/splat.yaml")).unwrap();
+        let segments = resolve_segments(&yaml).unwrap();
+
+        let main = segments.iter().find(|s| &*s.name() == "main").unwrap();
+        assert_eq!(main.vram_start(), Some(Vram::new(0x80025C00)));
+        assert_eq!(main.rom().unwrap().start(), Rom::new(0x1000));
+        assert_eq!(main.rom().unwrap().end(), Rom::new(0x759B0));
+    }
+}
@@ -0,0 +1,214 @@
+use std::collections::HashMap;
+
+use address_space::Vram;
+use anyhow::{Context, Result, bail};
+
+use splat_segment_api::segment_trait::SegmentTrait;
+
+use crate::yaml::VramClass;
+
+use super::ResolvedSegment;
+
+/// The vram_classes of a config, with the `follows_classes` relations between them checked for unknown
+/// names and cycles
+#[derive(Debug, Clone)]
+pub struct VramClassGraph<'a> {
+    classes: Vec<&'a VramClass>,
+    indices: HashMap<&'a str, usize>,
+    /// Indices into `classes`, with every class placed after all the classes it follows
+    order: Vec<usize>,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum VisitState {
+    Unvisited,
+    InProgress,
+    Done,
+}
+
+impl<'a> VramClassGraph<'a> {
+    pub fn new(classes: &'a [VramClass]) -> Result<Self> {
+        let mut indices = HashMap::new();
+        for (i, class) in classes.iter().enumerate() {
+            if indices.insert(class.name.as_str(), i).is_some() {
+                bail!("vram_class `{}` is defined more than once", class.name);
+            }
+        }
+
+        for class in classes {
+            for follows in &class.follows_classes {
+                if !indices.contains_key(follows.as_str()) {
+                    bail!(
+                        "vram_class `{}` follows the unknown vram_class `{follows}`",
+                        class.name
+                    );
+                }
+            }
+        }
+
+        let mut graph = Self {
+            classes: classes.iter().collect(),
+            indices,
+            order: Vec::with_capacity(classes.len()),
+        };
+
+        let mut states = vec![VisitState::Unvisited; classes.len()];
+        let mut path = Vec::new();
+        for i in 0..classes.len() {
+            graph.visit(i, &mut states, &mut path)?;
+        }
+
+        Ok(graph)
+    }
+
+    /// Depth first walk appending each class to `order` once everything it follows has been appended
+    fn visit(&mut self, i: usize, states: &mut [VisitState], path: &mut Vec<usize>) -> Result<()> {
+        match states[i] {
+            VisitState::Done => return Ok(()),
+            VisitState::InProgress => {
+                let cycle_start = path.iter().position(|&p| p == i).unwrap_or_default();
+                let cycle: Vec<&str> = path[cycle_start..]
+                    .iter()
+                    .chain([&i])
+                    .map(|&p| self.classes[p].name.as_str())
+                    .collect();
+                bail!("vram_classes form a cycle: {}", cycle.join(" -> "));
+            }
+            VisitState::Unvisited => {}
+        }
+
+        states[i] = VisitState::InProgress;
+        path.push(i);
+
+        let class = self.classes[i];
+        for follows in &class.follows_classes {
+            let dependency = self.indices[follows.as_str()];
+            self.visit(dependency, states, path)?;
+        }
+
+        path.pop();
+        states[i] = VisitState::Done;
+        self.order.push(i);
+
+        Ok(())
+    }
+
+    pub fn get(&self, name: &str) -> Option<&'a VramClass> {
+        self.indices.get(name).map(|&i| self.classes[i])
+    }
+
+    /// Classes in dependency order: every class comes after all the classes it follows
+    pub fn dependency_order(&self) -> impl Iterator<Item = &'a VramClass> + '_ {
+        self.order.iter().map(|&i| self.classes[i])
+    }
+
+    /// The VRAM end of every class, which is the furthest VRAM end of the top level segments assigned to
+    /// it. Classes with no segments end where they start.
+    pub fn vram_ends(&self, segments: &[ResolvedSegment]) -> Result<HashMap<&'a str, Vram>> {
+        let mut ends: HashMap<&'a str, Vram> = self
+            .classes
+            .iter()
+            .map(|class| {
+                let vram = u32::try_from(class.vram).with_context(|| {
+                    format!(
+                        "VRAM address 0x{:X} of vram_class `{}` is too big",
+                        class.vram, class.name
+                    )
+                })?;
+                Ok((class.name.as_str(), Vram::new(vram)))
+            })
+            .collect::<Result<_>>()?;
+
+        for segment in segments {
+            let (Some(class_name), Some(vram_end)) = (segment.vram_class(), segment.vram_end())
+            else {
+                continue;
+            };
+            if let Some(end) = self
+                .get(&class_name)
+                .and_then(|class| ends.get_mut(class.name.as_str()))
+                && vram_end > *end
+            {
+                *end = vram_end;
+            }
+        }
+
+        Ok(ends)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::*;
+    use crate::{segments::resolve_segments, yaml::load_yaml};
+
+    fn parse(yaml: &str) -> Vec<VramClass> {
+        serde_yaml::from_str(yaml).unwrap()
+    }
+
+    #[test]
+    fn test_vram_class_shapes() {
+        let classes = parse(
+            "
+- [effect_gfx, 0x09000000]
+- { name: heaps, vram: 0x80197000, vram_symbol: engine2_VRAM_END }
+- { name: battle_code, vram: 0x8023E000, follows_classes: [effect_gfx] }
+- { name: texture_memory, vram: 0x8028E000, follows_classes: [battle_code, heaps] }
+",
+        );
+
+        assert_eq!(classes[0].name, "effect_gfx");
+        assert_eq!(classes[0].vram, 0x09000000);
+        assert_eq!(classes[1].vram_symbol.as_deref(), Some("engine2_VRAM_END"));
+        assert_eq!(classes[3].follows_classes, ["battle_code", "heaps"]);
+
+        let graph = VramClassGraph::new(&classes).unwrap();
+        let order: Vec<_> = graph.dependency_order().map(|c| c.name.as_str()).collect();
+        assert_eq!(
+            order,
+            ["effect_gfx", "heaps", "battle_code", "texture_memory"]
+        );
+    }
+
+    #[test]
+    fn test_invalid_graphs() {
+        let cycle = parse(
+            "
+- { name: a, vram: 0, follows_classes: [c] }
+- { name: b, vram: 0, follows_classes: [a] }
+- { name: c, vram: 0, follows_classes: [b] }
+",
+        );
+        let err = VramClassGraph::new(&cycle).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "vram_classes form a cycle: a -> c -> b -> a"
+        );
+
+        let unknown = parse("- { name: a, vram: 0, follows_classes: [missing] }");
+        assert!(VramClassGraph::new(&unknown).is_err());
+
+        let duplicate = parse("- [a, 0]\n- [a, 4]");
+        assert!(VramClassGraph::new(&duplicate).is_err());
+    }
+
+    #[test]
+    fn test_vram_ends_test_data() {
+        let yaml = load_yaml(Path::new("test_data/splat.yaml")).unwrap();
+        let segments = resolve_segments(&yaml).unwrap();
+        let graph = VramClassGraph::new(yaml.vram_classes.as_deref().unwrap()).unwrap();
+
+        let main = segments.iter().find(|s| &*s.name() == "main").unwrap();
+        let ends = graph.vram_ends(&segments).unwrap();
+        assert_eq!(ends["main"], main.vram_end().unwrap());
+
+        let too_big = parse("- [overlays, 0x180000000]");
+        let err = VramClassGraph::new(&too_big)
+            .unwrap()
+            .vram_ends(&[])
+            .unwrap_err();
+        assert!(err.to_string().contains("overlays"), "{err}");
+    }
+}
@@ -0,0 +1,303 @@
+use std::{collections::HashMap, path::Path, sync::Arc};
+
+use anyhow::{Result, bail};
+use spimdisasm::sections::processed::FuncRodataPairing;
+
+use splat_segment_api::{section_trait::SectionTrait, segment_trait::SegmentTrait};
+
+use crate::{
+    config::instance::SplatInstance,
+    linker,
+    relocs::load_reloc_addrs,
+    rom::TargetRom,
+    sections::{Section, SectionProcessed, processed::common::CommonSegAsmProcessed},
+    segments::{
+        ResolvedSegment, SegmentType, SegmentTypeImpl, SegmentTypeRegistry, VramClassGraph,
+        resolve_segments,
+    },
+    symbols::load_symbol_addrs,
+    yaml,
+};
+
+/// Splits the binary described by the config at `config_path`, only writing the outputs of the given modes.
+///
+/// Every segment is scanned regardless of `modes` so references across segments are still resolved.
+/// Existing `hasm` files are only overwritten with `force_hasm`.
+pub fn split(config_path: &Path, modes: Vec<String>, force_hasm: bool) -> Result<()> {
+    let mut splat_yaml = yaml::load_yaml(config_path)?;
+    splat_yaml.options.set_modes(modes)?;
+    splat_yaml.options.set_force_hasm(force_hasm);
+
+    let rom = TargetRom::load(&splat_yaml)?;
+    let registry = SegmentTypeRegistry::new(&splat_yaml.options);
+    let segments = resolve_segments(&splat_yaml)?;
+    let symbols = load_symbol_addrs(&splat_yaml.options.symbol_addrs_paths)?;
+    let relocs = load_reloc_addrs(&splat_yaml.options.reloc_addrs_paths)?;
+    let mut splat_instance = SplatInstance::new(
+        splat_yaml.options.clone(),
+        &registry,
+        &segments,
+        symbols,
+        &relocs,
+    )?;
+
+    let mut sections = Vec::new();
+    let mut next_group = 1;
+    for segment in &segments {
+        scan_segment(
+            &mut splat_instance,
+            &registry,
+            &rom,
+            segment,
+            segment,
+            0,
+            &mut next_group,
+            &mut sections,
+        )?;
+    }
+
+    let mut processed = Vec::with_capacity(sections.len());
+    for scanned in sections {
+        processed.push(ScannedSection {
+            segment_type: scanned.segment_type,
+            group: scanned.group,
+            section: scanned.section.post_process(&mut splat_instance)?,
+        });
+    }
+
+    let siblings = pair_siblings(&processed);
+    if splat_instance.options.pair_rodata_to_text {
+        suggest_rodata_pairs(&splat_instance, &processed, &siblings);
+    }
+
+    for (i, scanned) in processed.iter().enumerate() {
+        if scanned.segment_type.is_mode_active(&splat_instance.options) {
+            let sibling = siblings.get(&i).map(|j| &processed[*j].section);
+            scanned.section.split(&splat_instance, sibling)?;
+        }
+    }
+
+    if splat_instance.options.is_mode_active("ld") {
+        let vram_classes =
+            VramClassGraph::new(splat_yaml.vram_classes.as_deref().unwrap_or_default())?;
+        linker::write_linker_script(&splat_instance.options, &registry, &vram_classes, &segments)?;
+    }
+
+    Ok(())
+}
+
+/// A section along with the type of its segment and the group of siblings it belongs to
+struct ScannedSection<'r, S> {
+    segment_type: &'r SegmentType,
+    group: usize,
+    section: S,
+}
+
+/// The disassembled text of `asm` and `c` sections
+fn text_section(section: &SectionProcessed) -> Option<&CommonSegAsmProcessed> {
+    match section {
+        SectionProcessed::Asm(text) => Some(text),
+        SectionProcessed::C(c) => Some(c.asm()),
+        _ => None,
+    }
+}
+
+fn is_text(section: &SectionProcessed) -> bool {
+    text_section(section).is_some()
+}
+
+fn is_rodata(section: &SectionProcessed) -> bool {
+    matches!(section, SectionProcessed::Rodata(_))
+}
+
+/// Pairs every text section with the rodata section of the same name in the same group, in both directions
+fn pair_siblings(processed: &[ScannedSection<SectionProcessed>]) -> HashMap<usize, usize> {
+    let texts: HashMap<(usize, Arc<str>), usize> = processed
+        .iter()
+        .enumerate()
+        .filter(|(_, scanned)| is_text(&scanned.section))
+        .map(|(i, scanned)| ((scanned.group, scanned.section.name()), i))
+        .collect();
+
+    let mut siblings = HashMap::new();
+    for (i, scanned) in processed.iter().enumerate() {
+        if !is_rodata(&scanned.section) {
+            continue;
+        }
+        if let Some(text) = texts.get(&(scanned.group, scanned.section.name())) {
+            siblings.insert(i, *text);
+            siblings.insert(*text, i);
+        }
+    }
+
+    siblings
+}
+
+/// Points out the text section that uses a rodata section without a sibling, which likely come from the same file
+fn suggest_rodata_pairs(
+    splat_instance: &SplatInstance,
+    processed: &[ScannedSection<SectionProcessed>],
+    siblings: &HashMap<usize, usize>,
+) {
+    let context = &splat_instance.spimdisasm_context;
+
+    for (i, scanned) in processed.iter().enumerate() {
+        let SectionProcessed::Rodata(rodata) = &scanned.section else {
+            continue;
+        };
+        if siblings.contains_key(&i) {
+            continue;
+        }
+
+        let candidates = processed
+            .iter()
+            .filter(|other| other.group == scanned.group);
+        for candidate in candidates {
+            let Some(text) = text_section(&candidate.section) else {
+                continue;
+            };
+
+            let pairings = FuncRodataPairing::pair_sections(
+                context,
+                Some(text.spimdisasm_section()),
+                Some(rodata.spimdisasm_section()),
+            );
+            let uses_rodata = pairings.iter().any(|pairing| {
+                matches!(pairing, FuncRodataPairing::SingleFunction { rodata_indices, .. } if !rodata_indices.is_empty())
+            });
+            if uses_rodata {
+                log::info!(
+                    "Rodata segment '{}' may belong to the text segment '{}'",
+                    rodata.name(),
+                    text.name()
+                );
+                break;
+            }
+        }
+    }
+}
+
+fn scan_segment<'r>(
+    splat_instance: &mut SplatInstance,
+    registry: &'r SegmentTypeRegistry,
+    rom: &TargetRom,
+    segment: &ResolvedSegment,
+    most_parent: &ResolvedSegment,
+    group: usize,
+    next_group: &mut usize,
+    sections: &mut Vec<ScannedSection<'r, Section>>,
+) -> Result<()> {
+    if !segment.subsegments().is_empty() {
+        let subgroup = *next_group;
+        *next_group += 1;
+
+        for subsegment in segment.subsegments() {
+            scan_segment(
+                splat_instance,
+                registry,
+                rom,
+                subsegment,
+                most_parent,
+                subgroup,
+                next_group,
+                sections,
+            )?;
+        }
+        return Ok(());
+    }
+
+    let segment_type = match registry.lookup(&segment.segment_type())? {
+        SegmentTypeImpl::Native(segment_type) => segment_type,
+        // TODO: run the plugin once the segment interface exposes splitting
+        SegmentTypeImpl::Plugin(path) => bail!(
+            "Segment `{}` uses the extension {}, but splitting with extensions is not supported yet",
+            segment.name(),
+            path.display()
+        ),
+    };
+    let Some(constructor) = segment_type.constructor else {
+        if segment_type.is_unimplemented() && segment_type.is_mode_active(&splat_instance.options) {
+            log::warn!(
+                "Segment `{}` has the type `{}`, which can't be split yet. Nothing was written for it",
+                segment.name(),
+                segment.segment_type()
+            );
+        }
+        return Ok(());
+    };
+    if segment.size().is_none_or(|size| size.inner() == 0) {
+        return Ok(());
+    }
+
+    let section = constructor(splat_instance, rom, segment, most_parent)?;
+    sections.push(ScannedSection {
+        segment_type,
+        group,
+        section,
+    });
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::test_utils::{project, split_modes};
+
+    const YAML: &str = "
+segments:
+  - name: main
+    type: code
+    start: 0x0
+    vram: 0x80000400
+    subsegments:
+      - [0x0, asm, main]
+      - [0x10, bin, table]
+  - [0x20]
+";
+
+    /// The files splitting wrote, relative to the project directory
+    fn outputs(dir: &Path) -> Vec<PathBuf> {
+        fn walk(dir: &Path, files: &mut Vec<PathBuf>) {
+            for entry in std::fs::read_dir(dir).unwrap().flatten() {
+                match entry.path().is_dir() {
+                    true => walk(&entry.path(), files),
+                    false => files.push(entry.path()),
+                }
+            }
+        }
+
+        let mut files = Vec::new();
+        walk(dir, &mut files);
+        let mut outputs: Vec<_> = files
+            .into_iter()
+            .map(|file| file.strip_prefix(dir).unwrap().to_path_buf())
+            .filter(|file| file != Path::new("splat.yaml") && file != Path::new("test.z64"))
+            .collect();
+        outputs.sort();
+        outputs
+    }
+
+    #[test]
+    fn test_modes() {
+        let dir = project("modes_code", "", YAML, &[0; 0x20]);
+        split_modes(&dir, &["code"], false);
+        assert_eq!(outputs(&dir), [PathBuf::from("asm/main.s")]);
+
+        let dir = project("modes_bin", "", YAML, &[0; 0x20]);
+        split_modes(&dir, &["bin"], false);
+        assert_eq!(
+            outputs(&dir),
+            [
+                PathBuf::from("asm/data/table.s"),
+                PathBuf::from("assets/table.bin")
+            ]
+        );
+
+        let dir = project("modes_ld", "", YAML, &[0; 0x20]);
+        split_modes(&dir, &["ld"], false);
+        assert_eq!(outputs(&dir), [PathBuf::from("test.ld")]);
+    }
+}
@@ -0,0 +1,257 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result, bail};
+
+/// A symbol declared in a symbol_addrs file:
+///
+/// `name = 0x80001234; // type:func size:0x40 rom:0x1234 segment:foo`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Symbol {
+    pub name: String,
+    pub vram: u32,
+    /// `type`: `func`, `jtbl`, `u32`, `f32`, `asciz`, ... or any user type
+    pub sym_type: Option<String>,
+    pub size: Option<u32>,
+    pub rom: Option<u32>,
+    /// Name of the segment the symbol belongs to, to disambiguate overlays sharing the same VRAM
+    pub segment: Option<String>,
+    /// Label to emit at the end of the symbol
+    pub name_end: Option<String>,
+    /// The symbol is defined somewhere else, so references to it are never guessed from other segments
+    pub defined: bool,
+    /// Whether references to `name + addend` are allowed to use this symbol, instead of the default
+    pub allow_addend: Option<bool>,
+    /// The address is never symbolized
+    pub ignore: bool,
+    /// Always migrate the rodata symbol to the function using it, even when it would not be
+    pub force_migration: bool,
+    /// Never migrate the rodata symbol to a function
+    pub force_not_migration: bool,
+    /// Function the rodata symbol should be migrated to, regardless of which ones reference it
+    pub function_owner: Option<String>,
+    /// Whether an asset symbol is extracted, `true` unless set otherwise
+    pub extract: bool,
+    /// Other symbols may share the same name
+    pub allow_duplicated: bool,
+    /// Whether the symbol may reference other symbols, instead of the default
+    pub can_reference: Option<bool>,
+    /// Whether other symbols may reference this one, instead of the default
+    pub can_be_referenced: Option<bool>,
+    pub align: Option<u32>,
+
+    /// Where the symbol was declared, for error messages
+    pub path: PathBuf,
+    pub line: usize,
+}
+
+/// Reads every symbol_addrs file that exists, in order
+pub fn load_symbol_addrs(paths: &[PathBuf]) -> Result<Vec<Symbol>> {
+    let mut symbols = Vec::new();
+
+    for path in paths.iter().filter(|path| path.exists()) {
+        let source = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        symbols.extend(parse_symbol_addrs(path, &source)?);
+    }
+
+    Ok(symbols)
+}
+
+pub fn parse_symbol_addrs(path: &Path, source: &str) -> Result<Vec<Symbol>> {
+    let mut symbols = Vec::new();
+
+    for (i, line) in source.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with("//") {
+            continue;
+        }
+
+        let symbol = parse_line(line, path, i + 1)
+            .with_context(|| format!("{}:{}: `{line}`", path.display(), i + 1))?;
+        symbols.push(symbol);
+    }
+
+    Ok(symbols)
+}
+
+fn parse_line(line: &str, path: &Path, line_number: usize) -> Result<Symbol> {
+    let (main, attributes) = match line.split_once("//") {
+        Some((main, attributes)) => (main.trim(), attributes.trim()),
+        None => (line, ""),
+    };
+
+    let Some((name, vram)) = main.strip_suffix(';').and_then(|main| main.split_once('=')) else {
+        bail!("Symbols should be of the form `name = 0x80001234; // attribute:value ...`");
+    };
+    let name = name.trim();
+    if name.is_empty() {
+        bail!("Missing symbol name");
+    }
+    let vram = parse_int(vram.trim())?;
+
+    let mut symbol = Symbol {
+        name: name.to_string(),
+        vram,
+        sym_type: None,
+        size: None,
+        rom: None,
+        segment: None,
+        name_end: None,
+        defined: false,
+        allow_addend: None,
+        ignore: false,
+        force_migration: false,
+        force_not_migration: false,
+        function_owner: None,
+        extract: true,
+        allow_duplicated: false,
+        can_reference: None,
+        can_be_referenced: None,
+        align: None,
+        path: path.to_path_buf(),
+        line: line_number,
+    };
+
+    // Like splat, words that aren't `attribute:value` pairs are part of a regular comment
+    let pairs = attributes
+        .split_whitespace()
+        .filter_map(|word| word.split_once(':'))
+        .filter(|(key, value)| !key.is_empty() && !value.is_empty());
+    for (key, value) in pairs {
+        match key {
+            "type" => symbol.sym_type = Some(value.to_string()),
+            "size" => symbol.size = Some(parse_int(value)?),
+            "rom" => symbol.rom = Some(parse_int(value)?),
+            "segment" => symbol.segment = Some(value.to_string()),
+            "name_end" => symbol.name_end = Some(value.to_string()),
+            "defined" => symbol.defined = parse_bool(key, value)?,
+            "allow_addend" => symbol.allow_addend = Some(parse_bool(key, value)?),
+            "dont_allow_addend" => symbol.allow_addend = Some(!parse_bool(key, value)?),
+            "ignore" => symbol.ignore = parse_bool(key, value)?,
+            "force_migration" => symbol.force_migration = parse_bool(key, value)?,
+            "force_not_migration" => symbol.force_not_migration = parse_bool(key, value)?,
+            "function_owner" => symbol.function_owner = Some(value.to_string()),
+            "extract" => symbol.extract = parse_bool(key, value)?,
+            "allow_duplicated" => symbol.allow_duplicated = parse_bool(key, value)?,
+            "can_reference" => symbol.can_reference = Some(parse_bool(key, value)?),
+            "can_be_referenced" => symbol.can_be_referenced = Some(parse_bool(key, value)?),
+            "align" => symbol.align = Some(parse_int(value)?),
+            _ => log::warn!(
+                "{}:{line_number}: Ignoring the unknown attribute `{key}` of symbol `{}`",
+                path.display(),
+                symbol.name
+            ),
+        }
+    }
+
+    Ok(symbol)
+}
+
+/// Parses a number written in hexadecimal, octal, binary or decimal
+pub(crate) fn parse_int(value: &str) -> Result<u32> {
+    let (digits, radix) = if let Some(hex) = value.strip_prefix("0x").or(value.strip_prefix("0X")) {
+        (hex, 16)
+    } else if let Some(octal) = value.strip_prefix("0o") {
+        (octal, 8)
+    } else if let Some(binary) = value.strip_prefix("0b") {
+        (binary, 2)
+    } else {
+        (value, 10)
+    };
+
+    u32::from_str_radix(digits, radix).with_context(|| format!("Invalid number `{value}`"))
+}
+
+fn parse_bool(key: &str, value: &str) -> Result<bool> {
+    match value.to_ascii_lowercase().as_str() {
+        "true" => Ok(true),
+        "false" => Ok(false),
+        _ => bail!("Attribute `{key}` must be `true` or `false`, got `{value}`"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(source: &str) -> Result<Vec<Symbol>> {
+        parse_symbol_addrs(Path::new("symbol_addrs.txt"), source)
+    }
+
+    #[test]
+    fn test_symbol_addrs() {
+        let symbols = parse(
+            r#"
+// libultra
+osInitialize = 0x80001234; // type:func size:0x40 rom:0x1234
+D_80100000 = 0x80100000;//type:f32
+
+  battle_table = 0x802A0000; // segment:battle_area name_end:battle_table_end defined:true allow_addend:False
+gSomething=0x80101010; // dont_allow_addend:true
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(symbols.len(), 4);
+        assert_eq!(symbols[0].name, "osInitialize");
+        assert_eq!(symbols[0].vram, 0x80001234);
+        assert_eq!(symbols[0].sym_type.as_deref(), Some("func"));
+        assert_eq!(symbols[0].size, Some(0x40));
+        assert_eq!(symbols[0].rom, Some(0x1234));
+        assert_eq!(symbols[0].line, 3);
+        assert_eq!(symbols[1].sym_type.as_deref(), Some("f32"));
+        assert_eq!(symbols[2].segment.as_deref(), Some("battle_area"));
+        assert_eq!(symbols[2].name_end.as_deref(), Some("battle_table_end"));
+        assert!(symbols[2].defined);
+        assert_eq!(symbols[2].allow_addend, Some(false));
+        assert_eq!(symbols[3].name, "gSomething");
+        assert_eq!(symbols[3].allow_addend, Some(false));
+    }
+
+    #[test]
+    fn test_symbol_comments() {
+        let symbols = parse(
+            r#"
+D_80241CB0 = 0x80241CB0; // type:asciz rom:0x9E2550 force_migration:true function_owner:func_80240B4C
+D_802A1A58 = 0x802A1A58; // rodata used by both overlays, see the battle segment. ignore:true can_reference:false
+gMainGfxPos = 0x8009A66C; // size:0x4 align:8 extract:False allow_duplicated:True can_be_referenced:false TODO: rename colour:red
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(symbols.len(), 3);
+        assert_eq!(symbols[0].sym_type.as_deref(), Some("asciz"));
+        assert!(symbols[0].force_migration);
+        assert!(!symbols[0].force_not_migration);
+        assert_eq!(symbols[0].function_owner.as_deref(), Some("func_80240B4C"));
+        assert!(symbols[0].extract);
+        assert!(symbols[1].ignore);
+        assert_eq!(symbols[1].can_reference, Some(false));
+        assert_eq!(symbols[1].can_be_referenced, None);
+        assert_eq!(symbols[2].size, Some(4));
+        assert_eq!(symbols[2].align, Some(8));
+        assert!(!symbols[2].extract);
+        assert!(symbols[2].allow_duplicated);
+        assert_eq!(symbols[2].can_be_referenced, Some(false));
+    }
+
+    #[test]
+    fn test_invalid_symbol_addrs() {
+        for (source, message) in [
+            ("func_80001234 = 0x80001234", "should be of the form"),
+            ("func_80001234 = 0x8000123G;", "Invalid number `0x8000123G`"),
+            (
+                "func_80001234 = 0x80001234; // defined:yes",
+                "must be `true` or `false`",
+            ),
+            (
+                "func_80001234 = 0x80001234; // align:four",
+                "Invalid number `four`",
+            ),
+        ] {
+            let err = format!("{:#}", parse(source).unwrap_err());
+            assert!(err.starts_with("symbol_addrs.txt:1: "), "{err}");
+            assert!(err.contains(message), "{err}");
+        }
+    }
+}
@@ -0,0 +1,62 @@
+//! Fixtures shared by the unit tests
+
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+
+use crate::{config::options::SplatOpts, yaml::SplatYaml};
+
+/// The options every config has to set
+const REQUIRED_OPTIONS: &str = "basename: test\nbase_path: .\ntarget_path: test.z64\n";
+
+/// Options of a `test` project, with the `extra` yaml lines added to the required ones
+pub(crate) fn try_options(extra: &str) -> Result<SplatOpts> {
+    SplatOpts::parse(serde_yaml::from_str(&format!("{REQUIRED_OPTIONS}{extra}"))?)
+}
+
+pub(crate) fn options(extra: &str) -> SplatOpts {
+    try_options(extra).unwrap()
+}
+
+/// The yaml of an n64 config named `test`, with the `extra` options and `yaml` holding what comes after
+/// the options, like `segments`
+fn config(extra: &str, yaml: &str) -> String {
+    let options: String = format!("{REQUIRED_OPTIONS}platform: n64\n{extra}")
+        .lines()
+        .map(|line| format!("  {line}\n"))
+        .collect();
+
+    format!("name: test\noptions:\n{options}{yaml}")
+}
+
+pub(crate) fn splat_yaml(yaml: &str) -> SplatYaml {
+    serde_yaml::from_str(&config("", yaml)).unwrap()
+}
+
+/// An empty directory under the system temporary directory, cleared of what a previous run of the `name`
+/// test left in it
+pub(crate) fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join("splat-tests").join(name);
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// A `test` project in a new temporary directory, with its `splat.yaml` and `rom` as the target binary
+pub(crate) fn project(name: &str, extra_options: &str, yaml: &str, rom: &[u8]) -> PathBuf {
+    let dir = temp_dir(name);
+    std::fs::write(dir.join("splat.yaml"), config(extra_options, yaml)).unwrap();
+    std::fs::write(dir.join("test.z64"), rom).unwrap();
+    dir
+}
+
+/// Splits the project in `dir` with every mode active
+pub(crate) fn split_project(dir: &Path, force_hasm: bool) {
+    split_modes(dir, &["all"], force_hasm);
+}
+
+/// Splits the project in `dir`, only writing the outputs of `modes`
+pub(crate) fn split_modes(dir: &Path, modes: &[&str], force_hasm: bool) {
+    let modes = modes.iter().map(|mode| mode.to_string()).collect();
+    crate::split::split(&dir.join("splat.yaml"), modes, force_hasm).unwrap();
+}
@@ -0,0 +1,205 @@
+use std::{
+    collections::HashMap,
+    fmt,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+use yaml_rust2::{
+    parser::{Event, MarkedEventReceiver, Parser},
+    scanner::Marker,
+};
+
+/// A position in a yaml file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Location {
+    pub path: PathBuf,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}:{}", self.path.display(), self.line, self.column)
+    }
+}
+
+/// One step from a yaml node to one of its children
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum PathElement {
+    Key(String),
+    Index(usize),
+}
+
+impl From<&str> for PathElement {
+    fn from(key: &str) -> Self {
+        PathElement::Key(key.to_string())
+    }
+}
+
+impl From<usize> for PathElement {
+    fn from(index: usize) -> Self {
+        PathElement::Index(index)
+    }
+}
+
+/// Where every node of a yaml document starts, indexed by the keys and indices leading to it from the root
+#[derive(Debug)]
+pub struct SourceMap {
+    path: PathBuf,
+    nodes: HashMap<Vec<PathElement>, Marker>,
+}
+
+impl SourceMap {
+    pub fn new(path: PathBuf, source: &str) -> Result<Self> {
+        let mut builder = SourceMapBuilder::default();
+        Parser::new_from_str(source)
+            .load(&mut builder, false)
+            .with_context(|| format!("Failed to parse {}", path.display()))?;
+
+        Ok(Self {
+            path,
+            nodes: builder.nodes,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn get(&self, node: &[PathElement]) -> Option<Location> {
+        self.nodes.get(node).map(|marker| Location {
+            path: self.path.clone(),
+            line: marker.line(),
+            column: marker.col() + 1,
+        })
+    }
+
+    /// Location of the node, or of its closest parent if the node itself isn't in the document
+    pub fn closest(&self, node: &[PathElement]) -> Location {
+        (0..=node.len())
+            .rev()
+            .find_map(|len| self.get(&node[..len]))
+            .unwrap_or_else(|| Location {
+                path: self.path.clone(),
+                line: 1,
+                column: 1,
+            })
+    }
+}
+
+enum Frame {
+    Sequence { index: usize },
+    Mapping { key: Option<String> },
+}
+
+#[derive(Default)]
+struct SourceMapBuilder {
+    nodes: HashMap<Vec<PathElement>, Marker>,
+    frames: Vec<Frame>,
+    path: Vec<PathElement>,
+}
+
+impl SourceMapBuilder {
+    /// Records the event as the pending key if the current mapping expects one
+    fn take_key(&mut self, event: &Event) -> bool {
+        let Some(Frame::Mapping { key }) = self.frames.last_mut() else {
+            return false;
+        };
+        if key.is_some() {
+            return false;
+        }
+
+        let Event::Scalar(value, ..) = event else {
+            return false;
+        };
+        *key = Some(value.clone());
+        true
+    }
+
+    fn node_path(&self) -> Vec<PathElement> {
+        let mut path = self.path.clone();
+        match self.frames.last() {
+            Some(Frame::Sequence { index }) => path.push(PathElement::Index(*index)),
+            Some(Frame::Mapping { key: Some(key) }) => path.push(PathElement::Key(key.clone())),
+            _ => {}
+        }
+        path
+    }
+
+    /// Moves the parent on to its next child once a node has been fully read
+    fn end_node(&mut self) {
+        match self.frames.last_mut() {
+            Some(Frame::Sequence { index }) => *index += 1,
+            Some(Frame::Mapping { key }) => *key = None,
+            None => {}
+        }
+    }
+}
+
+impl MarkedEventReceiver for SourceMapBuilder {
+    fn on_event(&mut self, event: Event, mark: Marker) {
+        match event {
+            Event::Scalar(..) | Event::Alias(_) => {
+                if self.take_key(&event) {
+                    return;
+                }
+                self.nodes.insert(self.node_path(), mark);
+                self.end_node();
+            }
+            Event::SequenceStart(..) | Event::MappingStart(..) => {
+                // Splat configs never use collections as mapping keys
+                let path = self.node_path();
+                self.nodes.insert(path.clone(), mark);
+                self.path = path;
+                self.frames.push(match event {
+                    Event::SequenceStart(..) => Frame::Sequence { index: 0 },
+                    _ => Frame::Mapping { key: None },
+                });
+            }
+            Event::SequenceEnd | Event::MappingEnd => {
+                self.frames.pop();
+                self.path.pop();
+                self.end_node();
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_source_map() {
+        let source = "\
+name: test
+segments:
+  - [0x0, header, header]
+  - name: main
+    subsegments:
+      - [0x1000, c, main]
+";
+        let map = SourceMap::new("splat.yaml".into(), source).unwrap();
+        let at = |node: &[PathElement]| {
+            let location = map.get(node).unwrap();
+            (location.line, location.column)
+        };
+
+        assert_eq!(at(&["name".into()]), (1, 7));
+        assert_eq!(at(&["segments".into(), 0.into()]), (3, 5));
+        assert_eq!(at(&["segments".into(), 0.into(), 1.into()]), (3, 11));
+        assert_eq!(at(&["segments".into(), 1.into(), "name".into()]), (4, 11));
+        assert_eq!(
+            at(&[
+                "segments".into(),
+                1.into(),
+                "subsegments".into(),
+                0.into(),
+                2.into()
+            ]),
+            (6, 21)
+        );
+    }
+}
@@ -0,0 +1,579 @@
+mod locations;
+
+use std::{collections::HashMap, fmt, path::Path};
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use serde_yaml::Value;
+
+pub use locations::{Location, PathElement, SourceMap};
+
+use crate::{
+    config::options::SplatOpts,
+    segments::{SegmentTypeRegistry, VramClassGraph},
+    yaml::{self, SplatYaml, VramClass, YamlSegment},
+};
+
+/// A problem found in the config, pointing back at the yaml node that caused it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub location: Location,
+    pub message: String,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: error: {}", self.location, self.message)
+    }
+}
+
+/// Loads the config at `path` and checks it for mistakes that would otherwise only show up as a wrong split
+pub fn validate_config(path: &Path) -> Result<Vec<Diagnostic>> {
+    let source = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    let source_map = SourceMap::new(path.to_path_buf(), &source)?;
+    let splat_yaml = match yaml::load_yaml(path) {
+        Ok(splat_yaml) => splat_yaml,
+        Err(e) => {
+            let Some(error) = e.downcast_ref::<serde_yaml::Error>() else {
+                return Err(e);
+            };
+            return Ok(vec![parse_diagnostic(&source, &source_map, error)]);
+        }
+    };
+
+    Ok(validate(&splat_yaml, &source_map))
+}
+
+/// A config that doesn't deserialize, reported at the option, vram_class or segment that fails.
+///
+/// serde only knows the collection it was reading when a segment fails, so the nodes are deserialized one by one
+/// to find the culprit
+fn parse_diagnostic(source: &str, source_map: &SourceMap, error: &serde_yaml::Error) -> Diagnostic {
+    let failing = serde_yaml::from_str::<Value>(source)
+        .ok()
+        .and_then(|root| failing_node(&root));
+    if let Some((path, message)) = failing {
+        return Diagnostic {
+            location: source_map.closest(&path),
+            message,
+        };
+    }
+
+    let message = error.to_string();
+    let Some(location) = error.location() else {
+        return Diagnostic {
+            location: source_map.closest(&[]),
+            message,
+        };
+    };
+    // The message ends with the position the diagnostic already points at
+    let suffix = format!(" at line {} column {}", location.line(), location.column());
+    Diagnostic {
+        location: Location {
+            path: source_map.path().to_path_buf(),
+            line: location.line(),
+            column: location.column(),
+        },
+        message: message
+            .strip_suffix(&suffix)
+            .unwrap_or(&message)
+            .to_string(),
+    }
+}
+
+/// Path and error of the innermost node of the config that fails to deserialize
+fn failing_node(root: &Value) -> Option<(Vec<PathElement>, String)> {
+    if let Some(options) = root.get("options")
+        && let Err(e) = SplatOpts::deserialize(options)
+    {
+        let message = e.to_string();
+        let path = match option_key(options, &message) {
+            Some(key) => vec!["options".into(), key.into()],
+            None => vec!["options".into()],
+        };
+        return Some((path, message));
+    }
+
+    if let Some(Value::Sequence(classes)) = root.get("vram_classes") {
+        for (i, class) in classes.iter().enumerate() {
+            if let Err(e) = VramClass::deserialize(class) {
+                return Some((vec!["vram_classes".into(), i.into()], e.to_string()));
+            }
+        }
+    }
+
+    match root.get("segments") {
+        Some(Value::Sequence(segments)) => failing_segment(segments, &["segments".into()]),
+        _ => None,
+    }
+}
+
+fn failing_segment(segments: &[Value], path: &[PathElement]) -> Option<(Vec<PathElement>, String)> {
+    for (i, segment) in segments.iter().enumerate() {
+        let segment_path = child_path(path, i);
+        if let Some(Value::Sequence(subsegments)) = segment.get("subsegments")
+            && let Some(failing) =
+                failing_segment(subsegments, &child_path(&segment_path, "subsegments"))
+        {
+            return Some(failing);
+        }
+
+        if let Err(e) = YamlSegment::deserialize(segment) {
+            return Some((segment_path, e.to_string()));
+        }
+    }
+
+    None
+}
+
+/// The option an error of [`SplatOpts::parse`] is about, when it names one of the keys of the config
+fn option_key<'v>(options: &'v Value, message: &str) -> Option<&'v str> {
+    let unknown = message.strip_prefix("Unknown option(s): ");
+    options
+        .as_mapping()?
+        .keys()
+        .filter_map(Value::as_str)
+        .find(|key| match unknown {
+            Some(unknown) => unknown.split(", ").any(|name| name == *key),
+            None => message.contains(&format!("`{key}`")),
+        })
+}
+
+pub fn validate(splat_yaml: &SplatYaml, source_map: &SourceMap) -> Vec<Diagnostic> {
+    let mut validator = Validator {
+        splat_yaml,
+        registry: SegmentTypeRegistry::new(&splat_yaml.options),
+        source_map,
+        diagnostics: Vec::new(),
+    };
+
+    validator.check_vram_classes();
+    validator.check_siblings(&splat_yaml.segments, &["segments".into()], None);
+
+    validator.diagnostics
+}
+
+struct Validator<'a> {
+    splat_yaml: &'a SplatYaml,
+    registry: SegmentTypeRegistry,
+    source_map: &'a SourceMap,
+    diagnostics: Vec<Diagnostic>,
+}
+
+/// Name used to refer to a segment in diagnostics, which defaults to its start like the resolver does
+fn display_name(yaml: &YamlSegment) -> String {
+    match (&yaml.name, yaml.rom) {
+        (Some(name), _) => name.clone(),
+        (None, Some(rom)) => format!("{rom:X}"),
+        (None, None) => yaml.segment_type.clone(),
+    }
+}
+
+/// The ROM range of a segment, as far as the config tells
+struct Extent {
+    name: String,
+    start: u64,
+    end: Option<u64>,
+}
+
+/// The start of a segment given in the config, which for `auto` groups is the start of their first subsegment
+fn explicit_start(yaml: &YamlSegment) -> Option<u64> {
+    yaml.rom.or_else(|| {
+        yaml.subsegments
+            .as_ref()
+            .and_then(|subsegments| subsegments.iter().find_map(explicit_start))
+    })
+}
+
+/// Where the segment at `i` ends: after its `size`, or else at the next start or the end of its parent, as
+/// the resolver ends it
+fn segment_end(
+    segments: &[YamlSegment],
+    i: usize,
+    start: u64,
+    parent_end: Option<u64>,
+) -> Option<u64> {
+    segments[i]
+        .size
+        .map(|size| start + size)
+        .or_else(|| segments[i + 1..].iter().find_map(explicit_start))
+        .or(parent_end)
+        .map(|end| end.max(start))
+}
+
+fn child_path(path: &[PathElement], child: impl Into<PathElement>) -> Vec<PathElement> {
+    let mut path = path.to_vec();
+    path.push(child.into());
+    path
+}
+
+impl Validator<'_> {
+    fn error(&mut self, location: Location, message: String) {
+        self.diagnostics.push(Diagnostic { location, message });
+    }
+
+    /// Location of a segment field, for both the dict and the list forms of segments
+    fn field_location(&self, path: &[PathElement], field: &str) -> Location {
+        let list_index = match field {
+            "start" => Some(0),
+            "type" => Some(1),
+            "name" => Some(2),
+            _ => None,
+        };
+
+        self.source_map
+            .get(&child_path(path, field))
+            .or_else(|| list_index.and_then(|i| self.source_map.get(&child_path(path, i))))
+            .unwrap_or_else(|| self.source_map.closest(path))
+    }
+
+    fn check_vram_classes(&mut self) {
+        let classes = self.splat_yaml.vram_classes.as_deref().unwrap_or_default();
+        if let Err(e) = VramClassGraph::new(classes) {
+            let location = self.source_map.closest(&["vram_classes".into()]);
+            self.error(location, format!("{e:#}"));
+        }
+    }
+
+    /// Siblings can share a name as long as their types differ, like an image and its palette
+    fn check_unique_names(&mut self, segments: &[YamlSegment], path: &[PathElement]) {
+        let mut seen: HashMap<(String, &str), usize> = HashMap::new();
+
+        for (i, yaml) in segments.iter().enumerate() {
+            if yaml.is_end_marker() {
+                continue;
+            }
+
+            let name = display_name(yaml);
+            let key = (name.clone(), yaml.segment_type.as_str());
+            if let Some(first) = seen.get(&key) {
+                let first = self.source_map.closest(&child_path(path, *first));
+                let location = self.field_location(&child_path(path, i), "name");
+                self.error(
+                    location,
+                    format!(
+                        "Segment name `{name}` is not unique among the `{}` segments, it is also used at {first}",
+                        yaml.segment_type
+                    ),
+                );
+            } else {
+                seen.insert(key, i);
+            }
+        }
+    }
+
+    fn check_siblings(
+        &mut self,
+        segments: &[YamlSegment],
+        path: &[PathElement],
+        parent: Option<&Extent>,
+    ) {
+        self.check_unique_names(segments, path);
+
+        // `auto` segments start where the previous one ends, like the resolver places them
+        let mut last_end = parent.map_or(0, |parent| parent.start);
+        let mut previous: Option<Extent> = None;
+
+        for (i, yaml) in segments.iter().enumerate() {
+            let segment_path = child_path(path, i);
+
+            if let Some(start) = yaml.rom {
+                self.check_start(yaml, start, &segment_path, previous.as_ref(), parent);
+            }
+            let start = explicit_start(yaml).unwrap_or(last_end);
+            if yaml.is_end_marker() {
+                last_end = start;
+                previous = Some(Extent {
+                    name: display_name(yaml),
+                    start,
+                    end: Some(start),
+                });
+                continue;
+            }
+
+            self.check_type(yaml, &segment_path);
+            self.check_vram_class(yaml, &segment_path);
+            self.check_bss_size(yaml, &segment_path);
+
+            let extent = Extent {
+                name: display_name(yaml),
+                start,
+                end: segment_end(segments, i, start, parent.and_then(|parent| parent.end)),
+            };
+            if let Some(subsegments) = &yaml.subsegments {
+                self.check_siblings(
+                    subsegments,
+                    &child_path(&segment_path, "subsegments"),
+                    Some(&extent),
+                );
+            }
+
+            last_end = extent.end.unwrap_or(start);
+            previous = Some(extent);
+        }
+    }
+
+    fn check_start(
+        &mut self,
+        yaml: &YamlSegment,
+        start: u64,
+        path: &[PathElement],
+        previous: Option<&Extent>,
+        parent: Option<&Extent>,
+    ) {
+        let subject = match yaml.is_end_marker() {
+            true => "The end marker".to_string(),
+            false => format!("Segment `{}`", display_name(yaml)),
+        };
+        let location = self.field_location(path, "start");
+
+        if let Some(previous) = previous {
+            if start < previous.start {
+                self.error(
+                    location.clone(),
+                    format!(
+                        "{subject} starts at 0x{start:X}, before the previous segment `{}` at 0x{:X}. Segments must be listed in ascending ROM order",
+                        previous.name, previous.start
+                    ),
+                );
+            } else if let Some(previous_end) = previous.end
+                && previous_end > start
+            {
+                self.error(
+                    location.clone(),
+                    format!(
+                        "{subject} starts at 0x{start:X}, which overlaps the previous segment `{}` ending at 0x{previous_end:X}",
+                        previous.name
+                    ),
+                );
+            }
+        }
+
+        let Some(parent) = parent else {
+            return;
+        };
+        if start < parent.start {
+            self.error(
+                location,
+                format!(
+                    "{subject} starts at 0x{start:X}, before the start of its parent `{}` at 0x{:X}",
+                    parent.name, parent.start
+                ),
+            );
+        } else if let Some(parent_end) = parent.end
+            && start > parent_end
+        {
+            self.error(
+                location,
+                format!(
+                    "{subject} starts at 0x{start:X}, after the end of its parent `{}` at 0x{parent_end:X}",
+                    parent.name
+                ),
+            );
+        }
+    }
+
+    fn check_type(&mut self, yaml: &YamlSegment, path: &[PathElement]) {
+        let segment_type = yaml.segment_type.as_str();
+        if self.registry.is_known(segment_type) {
+            return;
+        }
+
+        let location = self.field_location(path, "type");
+        self.error(
+            location,
+            format!(
+                "Segment `{}` has the unknown type `{segment_type}`",
+                display_name(yaml)
+            ),
+        );
+    }
+
+    fn check_vram_class(&mut self, yaml: &YamlSegment, path: &[PathElement]) {
+        let Some(class_name) = &yaml.vram_class else {
+            return;
+        };
+        let classes = self.splat_yaml.vram_classes.iter().flatten();
+        if classes.clone().any(|class| class.name == *class_name) {
+            return;
+        }
+
+        let location = self.field_location(path, "vram_class");
+        self.error(
+            location,
+            format!(
+                "Segment `{}` uses the vram_class `{class_name}`, which is not listed in `vram_classes`",
+                display_name(yaml)
+            ),
+        );
+    }
+
+    fn check_bss_size(&mut self, yaml: &YamlSegment, path: &[PathElement]) {
+        if yaml.bss_size.is_none() || can_have_bss_size(&yaml.segment_type) {
+            return;
+        }
+
+        let location = self.field_location(path, "bss_size");
+        self.error(
+            location,
+            format!(
+                "Segment `{}` of type `{}` can't have a `bss_size`, only `code` and `group` segments can",
+                display_name(yaml),
+                yaml.segment_type
+            ),
+        );
+    }
+}
+
+/// Only segments grouping several files get an implicit bss section sized by `bss_size`
+fn can_have_bss_size(segment_type: &str) -> bool {
+    matches!(segment_type, "code" | "group")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn validate_str(segments: &str) -> Vec<String> {
+        let source = format!(
+            "\
+name: test
+sha1: ''
+options:
+  basename: test
+  base_path: .
+  target_path: test.z64
+  platform: n64
+vram_classes:
+  - [overlays, 0x80200000]
+{segments}"
+        );
+        let source_map = SourceMap::new("splat.yaml".into(), &source).unwrap();
+        let splat_yaml = serde_yaml::from_str(&source).unwrap();
+
+        validate(&splat_yaml, &source_map)
+            .iter()
+            .map(|d| d.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_valid_config() {
+        let diagnostics = validate_str(
+            "\
+segments:
+  - [0x0, header, header]
+  - name: main
+    type: code
+    start: 0x1000
+    vram_class: overlays
+    bss_size: 0x100
+    subsegments:
+      - [0x1000, c, main]
+      - [auto, .data, main]
+  - [0x2000]
+",
+        );
+        assert_eq!(diagnostics, Vec::<String>::new());
+    }
+
+    #[test]
+    fn test_invalid_config() {
+        let diagnostics = validate_str(
+            "\
+segments:
+  - [0x0, header, header]
+  - name: main
+    type: code
+    start: 0x1000
+    vram_class: missing
+    subsegments:
+      - [0x1100, c, a]
+      - [0x1080, c, b]
+      - { start: 0x1200, type: c, name: c, bss_size: 0x10 }
+      - [0x3000, unknown, d]
+  - { name: header, type: header, start: 0x2000, size: 0x200 }
+  - [0x2100]
+",
+        );
+        assert_eq!(
+            diagnostics,
+            [
+                "splat.yaml:21:13: error: Segment name `header` is not unique among the `header` segments, it is also used at splat.yaml:11:5",
+                "splat.yaml:15:17: error: Segment `main` uses the vram_class `missing`, which is not listed in `vram_classes`",
+                "splat.yaml:18:10: error: Segment `b` starts at 0x1080, before the previous segment `a` at 0x1100. Segments must be listed in ascending ROM order",
+                "splat.yaml:19:54: error: Segment `c` of type `c` can't have a `bss_size`, only `code` and `group` segments can",
+                "splat.yaml:20:10: error: Segment `d` starts at 0x3000, after the end of its parent `main` at 0x2000",
+                "splat.yaml:20:18: error: Segment `d` has the unknown type `unknown`",
+                "splat.yaml:22:6: error: The end marker starts at 0x2100, which overlaps the previous segment `header` ending at 0x2200",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_segment_ranges() {
+        let diagnostics = validate_str(
+            "\
+segments:
+  - name: main
+    type: code
+    start: 0x1000
+    subsegments:
+      - [0x1000, asm, a]
+      - { type: asm, name: b, size: 0x300 }
+      - [0x1200, asm, c]
+  - name: overlays
+    type: group
+    start: 0x2000
+    subsegments:
+      - name: ovl
+        type: code
+        start: 0x2000
+        size: 0x100
+        subsegments:
+          - [0x2000, c, ovl]
+          - [0x2200, c, past_size]
+      - name: last
+        type: code
+        start: 0x2400
+        subsegments:
+          - [0x2400, c, last]
+          - [0x3100, c, past_group]
+  - [0x3000]
+",
+        );
+        assert_eq!(
+            diagnostics,
+            [
+                "splat.yaml:17:10: error: Segment `c` starts at 0x1200, which overlaps the previous segment `b` ending at 0x1500",
+                "splat.yaml:28:14: error: Segment `past_size` starts at 0x2200, after the end of its parent `ovl` at 0x2100",
+                "splat.yaml:34:14: error: Segment `past_group` starts at 0x3100, after the end of its parent `last` at 0x3000",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_diagnostic() {
+        let parse = |source: &str| {
+            let source_map = SourceMap::new("splat.yaml".into(), source).unwrap();
+            let error = serde_yaml::from_str::<SplatYaml>(source).unwrap_err();
+            parse_diagnostic(source, &source_map, &error).to_string()
+        };
+        let options = "name: test\noptions:\n  basename: test\n  base_path: .\n  target_path: test.z64\n  platform: n64\n";
+
+        assert_eq!(
+            parse(&format!(
+                "{options}segments:\n  - [0x0, header, header]\n  - name: main\n    type: code\n    start: 0x40\n    subsegments:\n      - [0x40, c, main]\n      - {{ start: 0x80, type: 3 }}\n  - [0x100]\n"
+            )),
+            "splat.yaml:14:9: error: Invalid type value Number(3)."
+        );
+        assert_eq!(
+            parse(&options.replace(
+                "  platform: n64\n",
+                "  platform: n64\n  create_c_files: 3\nsegments: []\n"
+            )),
+            "splat.yaml:7:19: error: Invalid value for option `create_c_files`: invalid type: integer `3`, expected a boolean"
+        );
+    }
+}
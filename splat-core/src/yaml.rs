@@ -3,10 +3,12 @@ use std::{
     path::{Path, PathBuf},
 };
 
-use anyhow::Result;
+use anyhow::{Context, Result, bail};
 use serde::{Deserialize, Deserializer};
 use serde_yaml::Value;
 
+use crate::config::options::SplatOpts;
+
 #[derive(Debug)]
 pub enum YamlSegmentArgs {
     List(Vec<Value>),
@@ -16,8 +18,8 @@ pub enum YamlSegmentArgs {
 #[derive(Debug, Deserialize)]
 pub struct SplatYaml {
     pub name: String,
-    pub sha1: String,
-    pub options: HashMap<String, Value>,
+    pub sha1: Option<String>,
+    pub options: SplatOpts,
     pub vram_classes: Option<Vec<VramClass>>,
     pub segments: Vec<YamlSegment>,
 }
@@ -78,6 +80,7 @@ impl From<DictSegment> for YamlSegment {
             symbol_name_format: ds.symbol_name_format,
             symbol_name_format_no_rom: ds.symbol_name_format_no_rom,
             args: ds.args.map(YamlSegmentArgs::Dict),
+            end_marker: false,
         }
     }
 }
@@ -107,6 +110,33 @@ pub struct YamlSegment {
     pub symbol_name_format: Option<String>,
     pub symbol_name_format_no_rom: Option<String>,
     pub args: Option<YamlSegmentArgs>,
+    /// Set for bare `[0x2800000]` entries, see [`YamlSegment::is_end_marker`]
+    pub end_marker: bool,
+}
+
+impl YamlSegment {
+    /// Single-element list entries such as `[0x2800000]` don't describe a segment,
+    /// they only mark where the previous one ends
+    pub fn is_end_marker(&self) -> bool {
+        self.end_marker
+    }
+
+    /// The extra arguments of list entries such as `[0x1000, c, main, -g -fforce-addr]`, split into compiler flags
+    pub fn compiler_flags(&self) -> Result<Vec<String>> {
+        let Some(YamlSegmentArgs::List(args)) = &self.args else {
+            return Ok(Vec::new());
+        };
+
+        let mut flags = Vec::new();
+        for arg in args {
+            let Value::String(arg) = arg else {
+                bail!("Invalid compiler flag {arg:?}, flags must be strings");
+            };
+            flags.extend(arg.split_whitespace().map(str::to_string));
+        }
+
+        Ok(flags)
+    }
 }
 
 impl<'de> Deserialize<'de> for YamlSegment {
@@ -393,6 +423,7 @@ where
                 symbol_name_format,
                 symbol_name_format_no_rom,
                 args: args.map(YamlSegmentArgs::Dict),
+                end_marker: false,
             })
         }
         Value::Sequence(l) => {
@@ -427,6 +458,7 @@ where
                 segment_type,
                 name,
                 args: args.map(YamlSegmentArgs::List),
+                end_marker: l.len() == 1,
                 ..Default::default()
             })
         }
@@ -434,40 +466,79 @@ where
     }
 }
 
-#[derive(Debug, Deserialize)]
-#[serde(untagged)]
-pub enum VramClass {
-    Symbol(SymbolVramClass),
-    Follows(FollowsVramClass),
-    Hardcoded(HardcodedVramClass),
-}
-
-#[derive(Debug, Deserialize)]
-pub struct SymbolVramClass {
+/// A group of overlays sharing the same VRAM region, either as `{ name: ..., vram: ... }` or `[name, vram]`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VramClass {
     pub name: String,
     pub vram: u64,
-    pub vram_symbol: String,
+    /// Symbol the linker script uses as the start of the class instead of the hardcoded `vram`
+    pub vram_symbol: Option<String>,
+    /// Classes whose end this class starts after
+    pub follows_classes: Vec<String>,
 }
 
 #[derive(Debug, Deserialize)]
-pub struct FollowsVramClass {
+#[serde(deny_unknown_fields)]
+pub struct DictVramClass {
     pub name: String,
     pub vram: u64,
-    pub follows_classes: String,
+    pub vram_symbol: Option<String>,
+    pub follows_classes: Option<Vec<String>>,
 }
 
-#[derive(Debug, Deserialize)]
-pub struct HardcodedVramClass {
-    pub name: String,
-    pub vram: u64,
+impl From<DictVramClass> for VramClass {
+    fn from(dc: DictVramClass) -> Self {
+        VramClass {
+            name: dc.name,
+            vram: dc.vram,
+            vram_symbol: dc.vram_symbol,
+            follows_classes: dc.follows_classes.unwrap_or_default(),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for VramClass {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        match Value::deserialize(deserializer)? {
+            value @ Value::Mapping(_) => DictVramClass::deserialize(value)
+                .map(VramClass::from)
+                .map_err(serde::de::Error::custom),
+            Value::Sequence(l) => {
+                let [Value::String(name), vram] = l.as_slice() else {
+                    return Err(serde::de::Error::custom(
+                        "A vram_class list must have exactly two elements: [name, vram]",
+                    ));
+                };
+                let vram = parse_number(vram)
+                    .map_err(serde::de::Error::custom)?
+                    .ok_or_else(|| {
+                        serde::de::Error::custom(format!("vram_class {name} has no vram"))
+                    })?;
+
+                Ok(VramClass {
+                    name: name.clone(),
+                    vram,
+                    vram_symbol: None,
+                    follows_classes: Vec::new(),
+                })
+            }
+            _ => Err(serde::de::Error::custom("Invalid vram_class")),
+        }
+    }
 }
 
 pub fn load_yaml(path: &Path) -> Result<SplatYaml> {
     let yaml_data = std::fs::read_to_string(path)?;
-    match serde_yaml::from_str(&yaml_data) {
-        Ok(yaml) => Ok(yaml),
-        Err(e) => Err(anyhow::anyhow!("Failed to parse YAML: {}", e)),
+    let mut yaml: SplatYaml = serde_yaml::from_str(&yaml_data).context("Failed to parse YAML")?;
+
+    if let Some(config_dir) = path.parent() {
+        yaml.options.resolve_relative_to(config_dir);
     }
+
+    Ok(yaml)
 }
 
 #[cfg(test)]
@@ -479,4 +550,48 @@ mod tests {
         let path = Path::new("test_data/splat.yaml");
         load_yaml(path).unwrap();
     }
+
+    #[test]
+    fn test_load_yaml_options() {
+        let path = Path::new("test_data/splat.yaml");
+        let yaml = load_yaml(path).unwrap();
+
+        assert_eq!(yaml.options.basename, "papermario");
+        assert_eq!(
+            yaml.options.asm_path,
+            Path::new("test_data/../../ver/us/asm")
+        );
+        assert_eq!(yaml.options.mnemonic_ljust, 10);
+        assert_eq!(yaml.options.subalign, Some(8));
+        assert!(yaml.options.hasm_in_src_path);
+        assert!(!yaml.options.ld_use_symbolic_vram_addresses);
+    }
+
+    #[test]
+    fn test_compiler_flags() {
+        let segment: YamlSegment =
+            serde_yaml::from_str("[0x1000, c, main, -g -fforce-addr, -O2]").unwrap();
+        assert_eq!(
+            segment.compiler_flags().unwrap(),
+            ["-g", "-fforce-addr", "-O2"]
+        );
+
+        let segment: YamlSegment = serde_yaml::from_str("[0x1000, c, main]").unwrap();
+        assert!(segment.compiler_flags().unwrap().is_empty());
+
+        let segment: YamlSegment = serde_yaml::from_str("[0x1000, c, main, 2]").unwrap();
+        assert!(segment.compiler_flags().is_err());
+    }
+
+    #[test]
+    fn test_end_marker() {
+        let segment: YamlSegment = serde_yaml::from_str("[0x2000]").unwrap();
+        assert!(segment.is_end_marker());
+
+        for stub in ["[0x1000, stub]", "{ start: 0x1000, type: stub }"] {
+            let segment: YamlSegment = serde_yaml::from_str(stub).unwrap();
+            assert_eq!(segment.segment_type, "stub");
+            assert!(!segment.is_end_marker(), "{stub}");
+        }
+    }
 }